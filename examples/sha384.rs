@@ -4,39 +4,26 @@
 //! to call a Cryptol function via the `cryptol-remote-api`.
 
 use cryptol_client::CryptolClient;
-use serde::{Deserialize, Serialize};
 use std::env;
+use std::fmt::Write;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-/// This is the JSON blob representing a response for a call to SHA384.
-///
-/// For example:
-///   `{"data":"5d13bb39a64c4ee16e0e8d2e1c13ec4731ff1ac69652c072d0cdc355eb9e0ec41b08aef3dd6fe0541e9fa9e3dcc80f7b","encoding":"hex","expression":"bits","width":384}`
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SHA384ResultValue {
-    data: String,
-    encoding: String,
-    expression: String,
-    width: i64,
-}
-
 /// This function calls SHA384 via `cryptol-remote-api`.
-
 fn sha384(mut cryptol_client: CryptolClient, input: &str) -> Result<String> {
     // Load Cryptol's `SuiteB` module.
     cryptol_client.load_module("SuiteB")?;
 
-    // Add the input to the list of input parameters and call Cryptol's `sha384`.
+    // Add the input to the list of input parameters and call Cryptol's
+    // `sha384`, decoding the resulting `[384]` into bytes.
     let arguments = [input];
-    let answer = cryptol_client.call("sha384", &arguments)?;
-
-    // Transform the resulting JSON into a `SHA384ResultValue` type.
-    let sha384_result: SHA384ResultValue = serde_json::from_value(answer.value).unwrap();
+    let digest: [u8; 48] = cryptol_client.call_as("sha384", &arguments)?;
 
-    // Prepend '0x' to the resulting hex string.
-    Ok(format!("0x{}", sha384_result.data))
+    // Render the digest as a hex string prefixed with '0x'.
+    Ok(digest.iter().fold("0x".to_string(), |mut hex, b| {
+        let _ = write!(hex, "{b:02x}");
+        hex
+    }))
 }
 
 /// This is the `main` function for this example. Here are some sample
@@ -45,7 +32,6 @@ fn sha384(mut cryptol_client: CryptolClient, input: &str) -> Result<String> {
 /// `cargo run --example sha384 "(join \"Hello World\")"`
 ///
 /// `cargo run --example sha384 "0x1234"`
-
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -0,0 +1,70 @@
+/*
+ * C bindings for cryptol_client, a client for cryptol-remote-api.
+ *
+ * Strings are NUL-terminated UTF-8. Every function that can fail
+ * returns CRYPTOL_CLIENT_OK on success and one of the other codes on
+ * failure; cryptol_client_last_error() then describes the failure.
+ *
+ * A client must not be used by several threads at the same time.
+ */
+
+#ifndef CRYPTOL_CLIENT_H
+#define CRYPTOL_CLIENT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CRYPTOL_CLIENT_OK 0
+#define CRYPTOL_CLIENT_INVALID_ARGUMENT 1
+#define CRYPTOL_CLIENT_SERVER_ERROR 2
+#define CRYPTOL_CLIENT_DECODE_ERROR 3
+#define CRYPTOL_CLIENT_TIMEOUT 4
+#define CRYPTOL_CLIENT_TRANSPORT_ERROR 5
+#define CRYPTOL_CLIENT_OTHER_ERROR 6
+#define CRYPTOL_CLIENT_PANIC 7
+#define CRYPTOL_CLIENT_RESPONSE_TOO_LARGE 8
+#define CRYPTOL_CLIENT_INCOMPATIBLE_VERSION 9
+
+typedef struct CryptolClient CryptolClient;
+
+/* Connects to url, or to $CRYPTOL_SERVER_URL if url is NULL. */
+int cryptol_client_connect(const char *url, CryptolClient **client);
+
+/* Frees a client. Does nothing if client is NULL. */
+void cryptol_client_free(CryptolClient *client);
+
+/* Loads a Cryptol module. */
+int cryptol_client_load_module(CryptolClient *client, const char *module);
+
+/*
+ * Calls function with arguments_json, a JSON array of Cryptol
+ * expressions (as strings) or values in the JSON encoding of
+ * cryptol-remote-api. The answer is stored as a JSON object in
+ * *answer_json and must be freed with cryptol_client_string_free.
+ */
+int cryptol_client_call_json(CryptolClient *client, const char *function,
+                             const char *arguments_json, char **answer_json);
+
+/*
+ * Evaluates a Cryptol expression. The answer is stored as a JSON
+ * object in *answer_json and must be freed with
+ * cryptol_client_string_free.
+ */
+int cryptol_client_evaluate_json(CryptolClient *client,
+                                 const char *expression, char **answer_json);
+
+/*
+ * Returns the message of the last failure on this thread, or "". The
+ * message is valid until the next failure on this thread.
+ */
+const char *cryptol_client_last_error(void);
+
+/* Frees a string returned by this library. Does nothing if s is NULL. */
+void cryptol_client_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CRYPTOL_CLIENT_H */
@@ -0,0 +1,329 @@
+//! # `cryptol_client_ffi`
+//!
+//! `cryptol_client_ffi` exposes [`cryptol_client`] to C and C++, e.g.
+//! to verification harnesses that want to reuse the client instead of
+//! implementing the protocol of `cryptol-remote-api` again. It builds
+//! a shared and a static library, whose functions are declared in
+//! `include/cryptol_client.h`.
+//!
+//! Strings are passed as NUL-terminated UTF-8. Arguments and answers
+//! are passed as JSON: the arguments of a call are a JSON array whose
+//! elements are Cryptol expressions, as strings, or values in the
+//! JSON encoding of `cryptol-remote-api`, and an answer is a JSON
+//! object with its `value`, `type` and `type string`.
+//!
+//! Every function that can fail returns `CRYPTOL_CLIENT_OK`, which is
+//! zero, on success and one of the other `CRYPTOL_CLIENT_*` codes on
+//! failure. The message of the last failure on the calling thread is
+//! returned by [`cryptol_client_last_error`].
+//!
+//! ```c
+//! CryptolClient *client;
+//! char *answer;
+//! if (cryptol_client_connect(NULL, &client) != CRYPTOL_CLIENT_OK ||
+//!     cryptol_client_load_module(client, "SuiteB") != CRYPTOL_CLIENT_OK ||
+//!     cryptol_client_call_json(client, "sha384", "[\"0x0001\"]", &answer) != CRYPTOL_CLIENT_OK) {
+//!     fprintf(stderr, "%s\n", cryptol_client_last_error());
+//!     return 1;
+//! }
+//! puts(answer);
+//! cryptol_client_string_free(answer);
+//! cryptol_client_free(client);
+//! ```
+
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+
+use cryptol_client::error::Error;
+use cryptol_client::{Answer, Args, CryptolClient, CryptolClientBuilder};
+use serde_json::Value;
+
+/// The function succeeded.
+pub const CRYPTOL_CLIENT_OK: c_int = 0;
+/// An argument was null, not UTF-8 or not valid JSON.
+pub const CRYPTOL_CLIENT_INVALID_ARGUMENT: c_int = 1;
+/// `cryptol-remote-api` answered the request with an error.
+pub const CRYPTOL_CLIENT_SERVER_ERROR: c_int = 2;
+/// A response or answer could not be decoded.
+pub const CRYPTOL_CLIENT_DECODE_ERROR: c_int = 3;
+/// No response arrived before the request timed out.
+pub const CRYPTOL_CLIENT_TIMEOUT: c_int = 4;
+/// The request could not be delivered or its response received.
+pub const CRYPTOL_CLIENT_TRANSPORT_ERROR: c_int = 5;
+/// Any other failure, e.g. `CRYPTOL_SERVER_URL` is not set.
+pub const CRYPTOL_CLIENT_OTHER_ERROR: c_int = 6;
+/// The client panicked. The client should not be used again.
+pub const CRYPTOL_CLIENT_PANIC: c_int = 7;
+/// The response was larger than the transport accepts.
+pub const CRYPTOL_CLIENT_RESPONSE_TOO_LARGE: c_int = 8;
+/// The server's version is not known to be compatible.
+pub const CRYPTOL_CLIENT_INCOMPATIBLE_VERSION: c_int = 9;
+
+thread_local! {
+    /// The message of the last failure on this thread.
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// A failure, with its code and message.
+struct Failure {
+    code: c_int,
+    message: String,
+}
+
+impl Failure {
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Failure {
+            code: CRYPTOL_CLIENT_INVALID_ARGUMENT,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for Failure {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let code = match e.downcast_ref::<Error>() {
+            Some(Error::Server(_)) => CRYPTOL_CLIENT_SERVER_ERROR,
+            Some(Error::Decode(_)) => CRYPTOL_CLIENT_DECODE_ERROR,
+            Some(Error::Timeout(_)) => CRYPTOL_CLIENT_TIMEOUT,
+            Some(Error::Transport(_)) => CRYPTOL_CLIENT_TRANSPORT_ERROR,
+            Some(Error::ResponseTooLarge { .. }) => CRYPTOL_CLIENT_RESPONSE_TOO_LARGE,
+            Some(Error::IncompatibleVersion { .. }) => CRYPTOL_CLIENT_INCOMPATIBLE_VERSION,
+            None => CRYPTOL_CLIENT_OTHER_ERROR,
+        };
+        Failure {
+            code,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// This function runs `f`, recording its failure, if any, as the last
+/// error of this thread and returning its code.
+fn run(f: impl FnOnce() -> Result<(), Failure>) -> c_int {
+    let failure = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return CRYPTOL_CLIENT_OK,
+        Ok(Err(failure)) => failure,
+        Err(_) => Failure {
+            code: CRYPTOL_CLIENT_PANIC,
+            message: "cryptol_client panicked".to_string(),
+        },
+    };
+
+    // Messages cannot contain NUL, so anything after one is dropped.
+    let message = failure.message.split('\0').next().unwrap_or_default();
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    failure.code
+}
+
+/// This function reads the string `s`, naming it `name` in errors.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string that outlives
+/// `'a`.
+unsafe fn string<'a>(s: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if s.is_null() {
+        return Err(Failure::invalid_argument(format!("{name} is null")));
+    }
+    // SAFETY: `s` is not null, and the caller guarantees the rest.
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|e| Failure::invalid_argument(format!("{name} is not UTF-8: {e}")))
+}
+
+/// This function returns the client behind `client`.
+///
+/// # Safety
+///
+/// `client` must be null or have been returned by
+/// [`cryptol_client_connect`] and not freed, and must not be used
+/// concurrently.
+unsafe fn client<'a>(client: *mut CryptolClient) -> Result<&'a mut CryptolClient, Failure> {
+    // SAFETY: the caller guarantees that a non-null `client` is valid
+    // and not aliased.
+    unsafe { client.as_mut() }.ok_or_else(|| Failure::invalid_argument("client is null"))
+}
+
+/// This function checks that the output pointer `out` is not null, so
+/// that no request is sent whose result cannot be returned.
+fn check_output<T>(out: *mut *mut T) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(Failure::invalid_argument("the output pointer is null"));
+    }
+    Ok(())
+}
+
+/// This function stores `answer` as JSON in `out` for the caller to
+/// free with [`cryptol_client_string_free`].
+///
+/// # Safety
+///
+/// `out` must be non-null and valid for writes.
+unsafe fn write_answer(out: *mut *mut c_char, answer: &Answer) -> Result<(), Failure> {
+    let json = serde_json::to_string(answer).map_err(|e| Failure {
+        code: CRYPTOL_CLIENT_DECODE_ERROR,
+        message: format!("cannot encode the answer: {e}"),
+    })?;
+    // Serialized JSON escapes NUL, so it cannot contain one.
+    let json = CString::new(json).unwrap_or_default();
+    // SAFETY: guaranteed by the caller.
+    unsafe { out.write(json.into_raw()) };
+    Ok(())
+}
+
+/// This function connects to `cryptol-remote-api` at `url`, or at
+/// `CRYPTOL_SERVER_URL` if `url` is null, and stores the client in
+/// `*client`. The client must be freed with [`cryptol_client_free`].
+///
+/// # Safety
+///
+/// `url` must be null or a NUL-terminated string, and `client` must
+/// be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cryptol_client_connect(
+    url: *const c_char,
+    client: *mut *mut CryptolClient,
+) -> c_int {
+    run(|| {
+        check_output(client)?;
+        let mut builder = CryptolClientBuilder::new();
+        if !url.is_null() {
+            // SAFETY: the caller guarantees that `url` is a string.
+            builder = builder.url(unsafe { string(url, "url") }?);
+        }
+        let connected = Box::new(builder.connect()?);
+        // SAFETY: `client` is not null, and the caller guarantees it
+        // is valid.
+        unsafe { client.write(Box::into_raw(connected)) };
+        Ok(())
+    })
+}
+
+/// This function frees a client returned by
+/// [`cryptol_client_connect`]. It does nothing if `client` is null.
+///
+/// # Safety
+///
+/// `client` must be null or a client that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cryptol_client_free(client: *mut CryptolClient) {
+    if !client.is_null() {
+        // SAFETY: the caller guarantees that `client` was returned by
+        // `cryptol_client_connect` and is freed only once.
+        drop(unsafe { Box::from_raw(client) });
+    }
+}
+
+/// This function loads the given Cryptol module.
+///
+/// # Safety
+///
+/// `client` must be a client that has not been freed and is not used
+/// concurrently, and `module` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn cryptol_client_load_module(
+    client: *mut CryptolClient,
+    module: *const c_char,
+) -> c_int {
+    run(|| {
+        // SAFETY: guaranteed by the caller.
+        let (client, module) = unsafe { (self::client(client)?, string(module, "module")?) };
+        Ok(client.load_module(module)?)
+    })
+}
+
+/// This function calls the given function with the arguments in
+/// `arguments_json`, a JSON array, and stores the answer as JSON in
+/// `*answer_json`. The answer must be freed with
+/// [`cryptol_client_string_free`].
+///
+/// # Safety
+///
+/// `client` must be a client that has not been freed and is not used
+/// concurrently, `function` and `arguments_json` NUL-terminated
+/// strings, and `answer_json` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cryptol_client_call_json(
+    client: *mut CryptolClient,
+    function: *const c_char,
+    arguments_json: *const c_char,
+    answer_json: *mut *mut c_char,
+) -> c_int {
+    run(|| {
+        check_output(answer_json)?;
+        // SAFETY: guaranteed by the caller.
+        let (client, function, arguments) = unsafe {
+            (
+                self::client(client)?,
+                string(function, "function")?,
+                string(arguments_json, "arguments_json")?,
+            )
+        };
+        let arguments: Vec<Value> = serde_json::from_str(arguments).map_err(|e| {
+            Failure::invalid_argument(format!("arguments_json is not a JSON array: {e}"))
+        })?;
+        let arguments = arguments.into_iter().fold(Args::new(), Args::json);
+
+        let answer = client.call(function, arguments)?;
+        // SAFETY: `answer_json` is not null, and the caller guarantees
+        // it is valid.
+        unsafe { write_answer(answer_json, &answer) }
+    })
+}
+
+/// This function evaluates the given Cryptol expression and stores
+/// the answer as JSON in `*answer_json`. The answer must be freed with
+/// [`cryptol_client_string_free`].
+///
+/// # Safety
+///
+/// `client` must be a client that has not been freed and is not used
+/// concurrently, `expression` a NUL-terminated string, and
+/// `answer_json` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cryptol_client_evaluate_json(
+    client: *mut CryptolClient,
+    expression: *const c_char,
+    answer_json: *mut *mut c_char,
+) -> c_int {
+    run(|| {
+        check_output(answer_json)?;
+        // SAFETY: guaranteed by the caller.
+        let (client, expression) =
+            unsafe { (self::client(client)?, string(expression, "expression")?) };
+
+        let answer = client.evaluate(expression)?;
+        // SAFETY: `answer_json` is not null, and the caller guarantees
+        // it is valid.
+        unsafe { write_answer(answer_json, &answer) }
+    })
+}
+
+/// This function returns the message of the last failure on the
+/// calling thread, or an empty string if there was none. The message
+/// is owned by the library and valid until the next failure on the
+/// thread.
+#[no_mangle]
+pub extern "C" fn cryptol_client_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// This function frees a string returned by this library. It does
+/// nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn cryptol_client_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: the caller guarantees that `s` came from
+        // `CString::into_raw` and is freed only once.
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
@@ -0,0 +1,181 @@
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use serde_json::Value;
+
+use cryptol_client::CryptolClient;
+use cryptol_client_ffi::{
+    cryptol_client_call_json, cryptol_client_connect, cryptol_client_evaluate_json,
+    cryptol_client_free, cryptol_client_last_error, cryptol_client_load_module,
+    cryptol_client_string_free, CRYPTOL_CLIENT_INVALID_ARGUMENT, CRYPTOL_CLIENT_OK,
+    CRYPTOL_CLIENT_OTHER_ERROR, CRYPTOL_CLIENT_SERVER_ERROR,
+};
+
+fn connect() -> *mut CryptolClient {
+    let mut client = ptr::null_mut();
+    let code = unsafe { cryptol_client_connect(ptr::null(), &raw mut client) };
+    assert_eq!(code, CRYPTOL_CLIENT_OK, "{}", last_error());
+    client
+}
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(cryptol_client_last_error()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// This function takes ownership of a JSON answer returned by the
+/// bindings.
+fn take_json(json: *mut c_char) -> Value {
+    let value = serde_json::from_str(&unsafe { CStr::from_ptr(json) }.to_string_lossy()).unwrap();
+    unsafe { cryptol_client_string_free(json) };
+    value
+}
+
+#[test]
+fn test_connect_success() {
+    let client = connect();
+    assert!(!client.is_null());
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_connect_failure() {
+    let url = CString::new("not a url").unwrap();
+    let mut client = ptr::null_mut();
+    let code = unsafe { cryptol_client_connect(url.as_ptr(), &raw mut client) };
+    assert_ne!(code, CRYPTOL_CLIENT_OK);
+    assert!(client.is_null());
+    assert!(!last_error().is_empty());
+
+    let code = unsafe { cryptol_client_connect(ptr::null(), ptr::null_mut()) };
+    assert_eq!(code, CRYPTOL_CLIENT_INVALID_ARGUMENT);
+}
+
+#[test]
+fn test_load_module_success() {
+    let client = connect();
+    let module = CString::new("SuiteB").unwrap();
+    let code = unsafe { cryptol_client_load_module(client, module.as_ptr()) };
+    assert_eq!(code, CRYPTOL_CLIENT_OK, "{}", last_error());
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_load_module_failure() {
+    let client = connect();
+    let module = CString::new("nosuchmodule").unwrap();
+    let code = unsafe { cryptol_client_load_module(client, module.as_ptr()) };
+    assert_eq!(code, CRYPTOL_CLIENT_SERVER_ERROR);
+    assert!(!last_error().is_empty());
+
+    let code = unsafe { cryptol_client_load_module(ptr::null_mut(), module.as_ptr()) };
+    assert_eq!(code, CRYPTOL_CLIENT_INVALID_ARGUMENT);
+    assert_eq!(last_error(), "client is null");
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_call_json_success() {
+    let client = connect();
+    let function = CString::new("reverse").unwrap();
+    let arguments = CString::new(r#"["[1, 2, 3, 4]"]"#).unwrap();
+    let mut answer = ptr::null_mut();
+    let code = unsafe {
+        cryptol_client_call_json(
+            client,
+            function.as_ptr(),
+            arguments.as_ptr(),
+            &raw mut answer,
+        )
+    };
+    assert_eq!(code, CRYPTOL_CLIENT_OK, "{}", last_error());
+
+    let answer = take_json(answer);
+    assert!(answer.get("value").is_some(), "{answer}");
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_call_json_failure() {
+    let client = connect();
+    let function = CString::new("nonsense").unwrap();
+    let arguments = CString::new(r#"["[1, 2, 3, 4]"]"#).unwrap();
+    let mut answer = ptr::null_mut();
+    let code = unsafe {
+        cryptol_client_call_json(
+            client,
+            function.as_ptr(),
+            arguments.as_ptr(),
+            &raw mut answer,
+        )
+    };
+    assert_eq!(code, CRYPTOL_CLIENT_SERVER_ERROR);
+    assert!(answer.is_null());
+
+    let arguments = CString::new("not json").unwrap();
+    let code = unsafe {
+        cryptol_client_call_json(
+            client,
+            function.as_ptr(),
+            arguments.as_ptr(),
+            &raw mut answer,
+        )
+    };
+    assert_eq!(code, CRYPTOL_CLIENT_INVALID_ARGUMENT);
+    assert!(last_error().starts_with("arguments_json is not a JSON array"));
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_evaluate_json_success() {
+    let client = connect();
+    let expression = CString::new("0x0102").unwrap();
+    let mut answer = ptr::null_mut();
+    let code =
+        unsafe { cryptol_client_evaluate_json(client, expression.as_ptr(), &raw mut answer) };
+    assert_eq!(code, CRYPTOL_CLIENT_OK, "{}", last_error());
+
+    let answer = take_json(answer);
+    assert_eq!(answer["type string"], "[16]", "{answer}");
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_evaluate_json_failure() {
+    let client = connect();
+    let expression = CString::new("nosuchname").unwrap();
+    let mut answer = ptr::null_mut();
+    let code =
+        unsafe { cryptol_client_evaluate_json(client, expression.as_ptr(), &raw mut answer) };
+    assert_eq!(code, CRYPTOL_CLIENT_SERVER_ERROR);
+
+    let code =
+        unsafe { cryptol_client_evaluate_json(client, expression.as_ptr(), ptr::null_mut()) };
+    assert_eq!(code, CRYPTOL_CLIENT_INVALID_ARGUMENT);
+    assert_eq!(last_error(), "the output pointer is null");
+    unsafe { cryptol_client_free(client) };
+}
+
+#[test]
+fn test_last_error_success() {
+    let code = unsafe { cryptol_client_load_module(ptr::null_mut(), ptr::null()) };
+    assert_eq!(code, CRYPTOL_CLIENT_INVALID_ARGUMENT);
+    assert_eq!(last_error(), "client is null");
+}
+
+#[test]
+fn test_last_error_failure() {
+    // Errors are per thread, so a fresh thread has none.
+    let message = std::thread::spawn(last_error).join().unwrap();
+    assert_eq!(message, "");
+    assert_ne!(CRYPTOL_CLIENT_OTHER_ERROR, CRYPTOL_CLIENT_OK);
+}
+
+#[test]
+fn test_free_null() {
+    unsafe {
+        cryptol_client_free(ptr::null_mut());
+        cryptol_client_string_free(ptr::null_mut());
+    }
+}
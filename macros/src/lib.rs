@@ -0,0 +1,124 @@
+//! # `cryptol_client_macros`
+//!
+//! Procedural macros for [`cryptol_client`], re-exported by it with
+//! the `macros` feature. See
+//! [`cryptol_test`](macro@cryptol_test).
+//!
+//! [`cryptol_client`]: https://docs.rs/cryptol_client
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Error, Expr, ExprArray, FnArg, ItemFn, Lit, LitStr, ReturnType};
+
+/// Turns a function taking a `&mut CryptolClient` into a test that
+/// runs it with a client connected to `cryptol-remote-api`, replacing
+/// the connect-or-panic boilerplate of integration tests.
+///
+/// The client is connected as
+/// [`CryptolClient::connect`](https://docs.rs/cryptol_client/latest/cryptol_client/struct.CryptolClient.html#method.connect)
+/// would, i.e. from `CRYPTOL_SERVER_URL` and the configuration file. If
+/// no server is configured or reachable, the test prints why and
+/// passes without running. Once it has run, the state it ended in is
+/// cleared on the server, whether it passed or not. See
+/// `cryptol_client::testing::TestClient`.
+///
+/// The modules to load in place of the Cryptol prelude are given with
+/// `modules`. The function may return `()` or a `Result<(), E>`, and
+/// may carry other test attributes, e.g. `#[should_panic]`.
+///
+/// ```ignore
+/// use cryptol_client::{cryptol_test, CryptolClient};
+///
+/// #[cryptol_test(modules = ["SuiteB"])]
+/// fn sha384_of_one(cryptol_client: &mut CryptolClient) {
+///     let answer = cryptol_client.call("sha384", &["0x0001"]).unwrap();
+///     assert_eq!(answer.bit_width(), Some(384));
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn cryptol_test(attributes: TokenStream, item: TokenStream) -> TokenStream {
+    let mut modules = Vec::new();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("modules") {
+            modules = module_names(&meta.value()?.parse()?)?;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported cryptol_test property"))
+        }
+    });
+    parse_macro_input!(attributes with parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+    expand(&function, &modules)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// This function returns the string literals of `array`.
+fn module_names(array: &ExprArray) -> syn::Result<Vec<LitStr>> {
+    array
+        .elems
+        .iter()
+        .map(|element| match element {
+            Expr::Lit(literal) => match &literal.lit {
+                Lit::Str(name) => Ok(name.clone()),
+                _ => Err(Error::new_spanned(element, "expected a module name")),
+            },
+            _ => Err(Error::new_spanned(element, "expected a module name")),
+        })
+        .collect()
+}
+
+/// This function wraps `function` in a test that connects a client
+/// loading `modules`, and passes it to the function.
+fn expand(function: &ItemFn, modules: &[LitStr]) -> syn::Result<proc_macro2::TokenStream> {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+    if sig.inputs.len() != 1 || !matches!(sig.inputs.first(), Some(FnArg::Typed(_))) {
+        return Err(Error::new_spanned(
+            &sig.inputs,
+            "a cryptol_test takes one argument, a `&mut CryptolClient`",
+        ));
+    }
+    if let Some(token) = &sig.asyncness {
+        return Err(Error::new_spanned(token, "a cryptol_test cannot be async"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "a cryptol_test cannot be generic",
+        ));
+    }
+
+    let name = &sig.ident;
+    let argument = &sig.inputs;
+    let output = &sig.output;
+    let skipped = match output {
+        ReturnType::Default => quote!(),
+        ReturnType::Type(..) => quote!(::core::result::Result::Ok(())),
+    };
+    let builder = if modules.is_empty() {
+        quote!(::cryptol_client::CryptolClient::builder())
+    } else {
+        quote!(::cryptol_client::CryptolClient::builder().initial_modules([#(#modules),*]))
+    };
+
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #name() #output {
+            fn #name(#argument) #output #block
+
+            let ::core::option::Option::Some(mut cryptol_client) =
+                ::cryptol_client::testing::TestClient::connect(#builder)
+            else {
+                return #skipped;
+            };
+            #name(&mut cryptol_client)
+        }
+    })
+}
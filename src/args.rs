@@ -0,0 +1,225 @@
+//! # Arguments
+//!
+//! `Args` collects the arguments of a call one at a time, so that each
+//! can have a different Rust type.
+//!
+//! ```no_run
+//! use cryptol_client::{Args, CryptolClient};
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! let key = [0x2bu8; 16];
+//! let arguments = Args::new().bytes(&key).bool(true).expression("zero : [128]");
+//! let answer = cryptol_client.call("myCipher", arguments).unwrap();
+//! ```
+//!
+//! Slices, arrays and vectors of a single serializable type convert
+//! into `Args` too, so `call("reverse", &["[1, 2, 3]"])` still works.
+//!
+//! The answer of one call can be passed to the next with
+//! [`Args::answer`] without decoding it.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::convert::{string_literal, ToCryptolValue};
+use crate::error::Error;
+use crate::value::{BitVector, CryptolValue, Integer};
+use crate::Answer;
+
+/// The arguments of a call, in order.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct Args {
+    arguments: Vec<Value>,
+    /// The value of each argument that was given as one, for checking
+    /// it against the type of its parameter.
+    #[serde(skip)]
+    decoded: Vec<Option<CryptolValue>>,
+}
+
+impl PartialEq for Args {
+    fn eq(&self, other: &Self) -> bool {
+        self.arguments == other.arguments
+    }
+}
+
+impl Args {
+    /// This function returns an empty argument list.
+    #[must_use]
+    pub fn new() -> Self {
+        Args::default()
+    }
+
+    /// This function returns the number of arguments.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.arguments.len()
+    }
+
+    /// This function returns whether there are no arguments.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.arguments.is_empty()
+    }
+
+    /// This function returns the JSON encoding of the arguments.
+    pub(crate) fn values(&self) -> &[Value] {
+        &self.arguments
+    }
+
+    /// This function returns the JSON encoding of the arguments for
+    /// wiping it.
+    #[cfg(feature = "zeroize")]
+    pub(crate) fn arguments_mut(&mut self) -> &mut [Value] {
+        &mut self.arguments
+    }
+
+    /// This function returns the value of each argument that was given
+    /// as a value rather than as an expression or JSON.
+    pub(crate) fn decoded(&self) -> impl Iterator<Item = Option<&CryptolValue>> {
+        self.decoded.iter().map(Option::as_ref)
+    }
+
+    /// This function adds an argument with its value, if known.
+    fn push(mut self, argument: Value, value: Option<CryptolValue>) -> Self {
+        self.arguments.push(argument);
+        self.decoded.push(value);
+        self
+    }
+
+    /// This function adds an argument written in Cryptol syntax, e.g.
+    /// `"[1, 2, 3]"` or `"join \"abc\""`.
+    #[must_use]
+    pub fn expression(self, expression: &str) -> Self {
+        self.push(json!(expression), None)
+    }
+
+    /// This function adds a value, as converted by
+    /// [`ToCryptolValue`], e.g. a `u32` as a `[32]` or a `Vec<u8>` as
+    /// an `[n][8]`.
+    #[must_use]
+    pub fn value<T: ToCryptolValue + ?Sized>(self, value: &T) -> Self {
+        self.cryptol_value(&value.to_cryptol_value())
+    }
+
+    /// This function adds any value that implements
+    /// `serde::Serialize`, as encoded by
+    /// [`ser::to_cryptol_value`](crate::ser::to_cryptol_value), e.g. a
+    /// struct as a record.
+    ///
+    /// ```no_run
+    /// use serde::Serialize;
+    ///
+    /// use cryptol_client::{Args, CryptolClient};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Block {
+    ///     key: Vec<u8>,
+    ///     rounds: u8,
+    /// }
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let block = Block { key: vec![0x2b; 16], rounds: 10 };
+    /// let arguments = Args::new().serialize(&block).unwrap();
+    /// let answer = cryptol_client.call("encrypt", arguments).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if `value` cannot be
+    /// encoded as a Cryptol value.
+    pub fn serialize<T: Serialize + ?Sized>(self, value: &T) -> Result<Self, Error> {
+        Ok(self.cryptol_value(&crate::ser::to_cryptol_value(value)?))
+    }
+
+    /// This function adds a decoded Cryptol value.
+    #[must_use]
+    pub fn cryptol_value(self, value: &CryptolValue) -> Self {
+        self.push(json!(value.to_expression()), Some(value.clone()))
+    }
+
+    /// This function adds a string as its UTF-8 bytes, an `[n][8]`,
+    /// written as a Cryptol string literal where possible. See
+    /// [`string_literal`](crate::convert::string_literal).
+    #[must_use]
+    pub fn string(self, s: &str) -> Self {
+        self.push(json!(string_literal(s)), Some(s.to_cryptol_value()))
+    }
+
+    /// This function adds a bitvector.
+    #[must_use]
+    pub fn bit_vector(self, bits: &BitVector) -> Self {
+        self.value(bits)
+    }
+
+    /// This function adds a `Bit`.
+    #[must_use]
+    pub fn bool(self, bit: bool) -> Self {
+        self.value(&bit)
+    }
+
+    /// This function adds an `Integer`.
+    #[must_use]
+    pub fn integer(self, n: impl Into<Integer>) -> Self {
+        self.cryptol_value(&CryptolValue::Integer(n.into()))
+    }
+
+    /// This function adds bytes as a single bitvector of width
+    /// `8 * bytes.len()`, most significant byte first, as keys and
+    /// blocks are usually typed. Use [`value`](Self::value) to pass
+    /// them as a sequence `[n][8]` instead.
+    #[must_use]
+    pub fn bytes(self, bytes: &[u8]) -> Self {
+        self.bit_vector(&BitVector::from_be_bytes(bytes.to_vec()))
+    }
+
+    /// This function adds the value of an earlier answer, in the
+    /// encoding the server returned it in. This saves decoding and
+    /// re-encoding large intermediate results, e.g. an expanded key
+    /// passed to many calls, and also works for values the server
+    /// only refers to by name, like functions.
+    #[must_use]
+    pub fn answer(self, answer: &Answer) -> Self {
+        self.json(answer.value.clone())
+    }
+
+    /// This function adds an argument in the JSON encoding of
+    /// `cryptol-remote-api`.
+    #[must_use]
+    pub fn json(self, argument: Value) -> Self {
+        self.push(argument, None)
+    }
+}
+
+impl<P: Serialize> From<&[P]> for Args {
+    fn from(arguments: &[P]) -> Self {
+        Args {
+            arguments: arguments.iter().map(|argument| json!(argument)).collect(),
+            decoded: vec![None; arguments.len()],
+        }
+    }
+}
+
+impl<P: Serialize, const N: usize> From<&[P; N]> for Args {
+    fn from(arguments: &[P; N]) -> Self {
+        Args::from(arguments.as_slice())
+    }
+}
+
+impl<P: Serialize> From<Vec<P>> for Args {
+    fn from(arguments: Vec<P>) -> Self {
+        Args::from(arguments.as_slice())
+    }
+}
+
+impl<P: Serialize> From<&Vec<P>> for Args {
+    fn from(arguments: &Vec<P>) -> Self {
+        Args::from(arguments.as_slice())
+    }
+}
+
+impl From<&Args> for Args {
+    fn from(arguments: &Args) -> Self {
+        arguments.clone()
+    }
+}
@@ -0,0 +1,133 @@
+//! # Batches
+//!
+//! A `Batch` queues independent requests and submits them to
+//! `cryptol-remote-api` as a single JSON-RPC batch, saving a round trip
+//! per request. Every queued request is issued from the state the
+//! `CryptolClient` was in when the batch was created, and submitting
+//! the batch leaves that state unchanged.
+//!
+//! ```no_run
+//! use cryptol_client::CryptolClient;
+//!
+//! let cryptol_client = CryptolClient::connect().unwrap();
+//! let mut batch = cryptol_client.batch();
+//! batch.call("reverse", &["[1, 2, 3, 4]"]);
+//! batch.call("reverse", &["[5, 6, 7, 8]"]);
+//! for answer in batch.send().unwrap() {
+//!     println!("{:?}", answer.map(|answer| answer.value));
+//! }
+//! ```
+
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use tracing::field;
+use web_time::Instant;
+
+use crate::error::Error;
+use crate::metrics::{Outcome, RequestEvent};
+use crate::{parse, trace, Answer, Args, CryptolClient, Result};
+
+/// A queue of independent requests to be submitted together. Created
+/// by [`CryptolClient::batch`].
+#[derive(Debug)]
+pub struct Batch<'a> {
+    client: &'a CryptolClient,
+    requests: Vec<(&'static str, Map<String, Value>)>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(client: &'a CryptolClient) -> Self {
+        Batch {
+            client,
+            requests: Vec::new(),
+        }
+    }
+
+    /// This function queues a call of the given function in the loaded
+    /// Cryptol module. See [`CryptolClient::call`].
+    pub fn call(&mut self, function: &str, arguments: impl Into<Args>) -> &mut Self {
+        let params = self.client.call_params(function, &arguments.into());
+        self.requests.push(("call", params));
+        self
+    }
+
+    /// This function returns the number of queued requests.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// This function returns whether no requests have been queued.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// This function submits the queued requests and returns their
+    /// answers in the order they were queued. The elapsed time of each
+    /// answer is the round-trip time of the whole batch.
+    ///
+    /// The submission is recorded in a `batch` tracing span and
+    /// reported to the installed `Metrics` hook as a single request
+    /// with method `batch`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the batch could not be
+    /// submitted. A request that fails on its own is reported in the
+    /// corresponding entry of the result instead.
+    pub fn send(self) -> Result<Vec<std::result::Result<Answer, Error>>> {
+        let span = tracing::info_span!(
+            "batch",
+            state = self.client.session.state(),
+            size = self.requests.len(),
+            duration_ms = field::Empty,
+        );
+
+        let start = Instant::now();
+        let result = span.in_scope(|| self.client.session.exchange_batch(&self.requests));
+        let latency = self.client.elapsed_since(start);
+        span.record("duration_ms", latency.as_secs_f64() * 1000.0);
+
+        let record = |outcome, payload_size| {
+            if let Some(metrics) = &self.client.metrics {
+                metrics.record(&RequestEvent {
+                    method: "batch",
+                    outcome,
+                    latency,
+                    payload_size,
+                });
+            }
+        };
+
+        let (size, responses) = match result {
+            Ok(exchange) => exchange,
+            Err(e) => {
+                tracing::warn!(parent: &span, error = %e, "batch failed");
+                record(
+                    match e {
+                        Error::Timeout(_) => Outcome::Timeout,
+                        _ => Outcome::TransportError,
+                    },
+                    None,
+                );
+                return Err(e.into());
+            }
+        };
+        record(Outcome::Success, Some(size));
+
+        Ok(responses
+            .into_iter()
+            .map(|response| {
+                let response = response?;
+                let answer = response.answer().map_or("null", RawValue::get);
+                let mut answer = parse::answer(answer)?;
+                answer.elapsed = latency;
+                answer.strict = self.client.strict;
+                answer.attach_output(&response);
+                span.in_scope(|| trace::emit(&answer.trace));
+                Ok(answer)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,169 @@
+//! # Big numbers
+//!
+//! Cryptographic specifications work with numbers far wider than a
+//! `u128`: RSA moduli, elliptic curve points, field elements. The
+//! [`BigNumber`] trait lets such values be read from and written to
+//! bitvectors and [`Integer`]s with whichever arbitrary-precision type
+//! the application already uses. Both are converted through their
+//! big-endian bytes, so no precision is lost on the way. With the
+//! `num-bigint` feature, it is implemented for `num_bigint::BigUint`
+//! and `num_bigint::BigInt`.
+//!
+//! Other backends plug in by implementing the trait, e.g. for a
+//! newtype around `rug::Integer`, which is faster than a pure Rust
+//! implementation on RSA-sized values:
+//!
+//! ```ignore
+//! use cryptol_client::bignum::BigNumber;
+//! use cryptol_client::value::Integer;
+//! use rug::integer::Order;
+//!
+//! struct Gmp(rug::Integer);
+//!
+//! impl BigNumber for Gmp {
+//!     fn from_be_bytes(bytes: &[u8]) -> Self {
+//!         Gmp(rug::Integer::from_digits(bytes, Order::Msf))
+//!     }
+//!
+//!     fn to_be_bytes(&self) -> Option<Vec<u8>> {
+//!         (self.0 >= 0).then(|| self.0.to_digits(Order::Msf))
+//!     }
+//!
+//!     fn from_integer(n: &Integer) -> Option<Self> {
+//!         let magnitude = rug::Integer::from_digits(n.magnitude(), Order::Msf);
+//!         Some(Gmp(if n.is_negative() { -magnitude } else { magnitude }))
+//!     }
+//!
+//!     fn to_integer(&self) -> Integer {
+//!         let magnitude = self.0.as_abs().to_digits(Order::Msf);
+//!         Integer::from_sign_magnitude(self.0 < 0, &magnitude)
+//!     }
+//! }
+//! ```
+//!
+//! A [`Big`] converts from Cryptol values with any backend:
+//!
+//! ```
+//! # #[cfg(feature = "num-bigint")]
+//! # {
+//! use cryptol_client::bignum::Big;
+//! use cryptol_client::convert::FromCryptolValue;
+//! use cryptol_client::value::{BitVector, CryptolValue};
+//! use num_bigint::BigUint;
+//!
+//! let modulus = BigUint::from(1u8) << 2047u32;
+//! let bits = BitVector::from_big(2048, &modulus).unwrap();
+//! let Big(n) = Big::<BigUint>::from_cryptol_value(CryptolValue::BitVector(bits)).unwrap();
+//! assert_eq!(n, modulus);
+//! # }
+//! ```
+
+use crate::convert::{mismatch, FromCryptolValue};
+use crate::error::Error;
+use crate::value::{BitVector, CryptolValue, Integer};
+
+/// An arbitrary-precision integer type, the backing representation of
+/// values too wide for Rust's integer types.
+pub trait BigNumber: Sized {
+    /// This function returns the non-negative number whose big-endian
+    /// bytes are `bytes`.
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+
+    /// This function returns the big-endian bytes of the number, with
+    /// or without leading zero bytes, or `None` if it is negative.
+    fn to_be_bytes(&self) -> Option<Vec<u8>>;
+
+    /// This function returns `n`, or `None` if the type cannot
+    /// represent it, e.g. because it is negative and the type is
+    /// unsigned.
+    fn from_integer(n: &Integer) -> Option<Self>;
+
+    /// This function returns the number as a Cryptol `Integer`.
+    fn to_integer(&self) -> Integer;
+}
+
+#[cfg(feature = "num-bigint")]
+impl BigNumber for num_bigint::BigUint {
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        num_bigint::BigUint::from_bytes_be(bytes)
+    }
+
+    fn to_be_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.to_bytes_be())
+    }
+
+    fn from_integer(n: &Integer) -> Option<Self> {
+        (!n.is_negative()).then(|| num_bigint::BigUint::from_bytes_be(n.magnitude()))
+    }
+
+    fn to_integer(&self) -> Integer {
+        Integer::from_sign_magnitude(false, &self.to_bytes_be())
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl BigNumber for num_bigint::BigInt {
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        num_bigint::BigInt::from_bytes_be(num_bigint::Sign::Plus, bytes)
+    }
+
+    fn to_be_bytes(&self) -> Option<Vec<u8>> {
+        self.to_biguint().map(|n| n.to_bytes_be())
+    }
+
+    fn from_integer(n: &Integer) -> Option<Self> {
+        let sign = if n.is_negative() {
+            num_bigint::Sign::Minus
+        } else {
+            num_bigint::Sign::Plus
+        };
+        Some(num_bigint::BigInt::from_bytes_be(sign, n.magnitude()))
+    }
+
+    fn to_integer(&self) -> Integer {
+        let (sign, magnitude) = self.to_bytes_be();
+        Integer::from_sign_magnitude(sign == num_bigint::Sign::Minus, &magnitude)
+    }
+}
+
+impl BitVector {
+    /// This function returns the value of the bitvector as an unsigned
+    /// number of type `N`.
+    #[must_use]
+    pub fn to_big<N: BigNumber>(&self) -> N {
+        N::from_be_bytes(self.as_bytes())
+    }
+
+    /// This function creates a bitvector of the given `width` holding
+    /// `n`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if `n` is negative or does
+    /// not fit in `width` bits.
+    pub fn from_big<N: BigNumber>(width: usize, n: &N) -> Result<Self, Error> {
+        let bytes = n.to_be_bytes().ok_or_else(|| {
+            Error::Decode(format!(
+                "a negative number does not fit in a bitvector of width {width}"
+            ))
+        })?;
+        BitVector::from_unpadded_be_bytes(width, &bytes)
+    }
+}
+
+/// A number of any [`BigNumber`] type, converted from bitvectors of any
+/// width, read as unsigned, and from `Integer`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Big<N>(pub N);
+
+impl<N: BigNumber> FromCryptolValue for Big<N> {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::BitVector(bits) => Ok(Big(bits.to_big())),
+            CryptolValue::Integer(n) => N::from_integer(&n)
+                .map(Big)
+                .ok_or_else(|| Error::Decode(format!("integer {n} does not fit"))),
+            value => Err(mismatch("a bitvector or an integer", &value)),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+//! # Client builder
+//!
+//! `CryptolClientBuilder` collects the settings used to establish a
+//! connection with `cryptol-remote-api` before connecting.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use tracing::{field, Span};
+
+use crate::cache::Cache;
+use crate::compat::VersionCheck;
+use crate::config::Config;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::HttpConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::discover;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::disk_cache::DiskCache;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::keepalive::Keepalive;
+use crate::metrics::Metrics;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::progress::{Control, ProgressCallback, ProgressEvent};
+use crate::session::RemoteSession;
+use crate::transcript::Transcript;
+use crate::transport::{self, Transport};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::{Endpoint, HttpTransportBuilder, RateLimitedTransport};
+use crate::{CryptolClient, Result};
+
+/// Builder for a `CryptolClient`.
+///
+/// ```no_run
+/// use cryptol_client::CryptolClientBuilder;
+///
+/// let cryptol_client = CryptolClientBuilder::new()
+///     .url("http://0.0.0.0:49352")
+///     .connect();
+/// ```
+#[derive(Clone, Default)]
+pub struct CryptolClientBuilder {
+    /// The settings given to the builder, which take precedence over
+    /// the environment and the configuration file.
+    pub(crate) settings: Config,
+    /// The settings of the configuration file, if any.
+    pub(crate) file: Config,
+    transport: Option<Arc<dyn Transport>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    http: Option<HttpTransportBuilder>,
+    metrics: Option<Arc<dyn Metrics>>,
+    cache: Option<Arc<Cache>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<Arc<DiskCache>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    progress: Option<(Duration, Arc<ProgressCallback>)>,
+    pub(crate) transcript: Option<Transcript>,
+}
+
+impl fmt::Debug for CryptolClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("CryptolClientBuilder");
+        f.field("settings", &self.settings)
+            .field("file", &self.file)
+            .field("transport", &self.transport)
+            .field("metrics", &self.metrics.is_some())
+            .field("cache", &self.cache.is_some())
+            .field("transcript", &self.transcript);
+        #[cfg(not(target_arch = "wasm32"))]
+        f.field("http", &self.http)
+            .field("disk_cache", &self.disk_cache.is_some())
+            .field(
+                "progress",
+                &self.progress.as_ref().map(|(interval, _)| interval),
+            );
+        f.finish()
+    }
+}
+
+impl CryptolClientBuilder {
+    /// Creates a builder with default settings.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the URL of `cryptol-remote-api`, or a connection string
+    /// that also selects the transport, e.g.
+    /// `cryptol+socket://localhost:49352`; see
+    /// [connection strings](crate::transport#connection-strings). When
+    /// no URL is given, the value of `CRYPTOL_SERVER_URL` is used.
+    #[must_use]
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.settings.url = Some(url.into());
+        self
+    }
+
+    /// Sets the URLs to look for `cryptol-remote-api` at, in order,
+    /// when no URL is given by the builder, the environment or the
+    /// configuration file. The client connects to the first at which a
+    /// server accepts connections. By default the client does not
+    /// look for a server. See [`discover`](crate::discover).
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn discover<U: Into<String>>(mut self, urls: impl IntoIterator<Item = U>) -> Self {
+        self.settings.discover = Some(urls.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets the transport used to reach `cryptol-remote-api`. This
+    /// takes precedence over [`url`](Self::url). By default the
+    /// [default transport](crate::transport) for the URL is used.
+    #[must_use]
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Sets the settings of the HTTP transport created for the URL.
+    /// This has no effect if a transport is given. Settings of the
+    /// transport given by [`timeout`](Self::timeout), the environment
+    /// or the configuration file take precedence over those of `http`.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn http(mut self, http: HttpTransportBuilder) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Sets how long the HTTP transport waits for the response to a
+    /// request. When no timeout is given, the value of
+    /// `CRYPTOL_SERVER_TIMEOUT`, in seconds, is used. The default is
+    /// one hour.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.settings.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Installs a hook that is called once for every request the
+    /// client makes. See [`Metrics`].
+    #[must_use]
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Installs a cache that memoizes the responses of repeated
+    /// requests. The cache may be shared with other clients. See
+    /// [`Cache`].
+    #[must_use]
+    pub fn cache(mut self, cache: Arc<Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Installs a cache kept on disk that memoizes the responses of
+    /// requests across runs of the program. The cache may be shared
+    /// with other clients. See [`DiskCache`].
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn disk_cache(mut self, disk_cache: Arc<DiskCache>) -> Self {
+        self.disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// Sets whether `load_module` skips loading a module that is
+    /// already loaded in the current state. This is on by default;
+    /// turn it off to reload modules whose source may have changed.
+    #[must_use]
+    pub fn memoize_module_loads(mut self, memoize_module_loads: bool) -> Self {
+        self.settings.memoize_module_loads = Some(memoize_module_loads);
+        self
+    }
+
+    /// Sets whether `call` looks up the type of the function before
+    /// calling it and checks the number of arguments, and the shape of
+    /// those given as values, without a round trip to the server. This
+    /// is off by default. The type of each function is looked up once
+    /// per state.
+    #[must_use]
+    pub fn validate_calls(mut self, validate_calls: bool) -> Self {
+        self.settings.validate_calls = Some(validate_calls);
+        self
+    }
+
+    /// Sets whether every change of the state token is logged at debug
+    /// level, with the old token, the method that changed it and the
+    /// new token, including requests answered from the cache. This
+    /// makes it possible to reconstruct what a multi-step session did
+    /// against the server. It is off by default.
+    #[must_use]
+    pub fn trace_state_transitions(mut self, trace_state_transitions: bool) -> Self {
+        self.settings.trace_state_transitions = Some(trace_state_transitions);
+        self
+    }
+
+    /// Sets whether every decoded answer is checked against the type
+    /// the server reports for it: the widths of bitvectors, the lengths
+    /// of sequences and tuples, the fields of records and the moduli of
+    /// `Z n` values. A mismatch fails with `Error::Decode` rather than
+    /// going unnoticed. This catches decoding bugs and changes in the
+    /// server's encodings, at the cost of decoding the type of each
+    /// answer. It is off by default. See
+    /// [`CryptolValue::check_type`](crate::value::CryptolValue::check_type).
+    #[must_use]
+    pub fn strict_decoding(mut self, strict_decoding: bool) -> Self {
+        self.settings.strict_decoding = Some(strict_decoding);
+        self
+    }
+
+    /// Sets the module loaded upon connection, e.g. a prelude for the
+    /// application. By default it is the Cryptol prelude, `Cryptol`.
+    #[must_use]
+    pub fn initial_module(self, module: impl Into<String>) -> Self {
+        self.initial_modules([module])
+    }
+
+    /// Sets the modules loaded upon connection, in order. Calls and
+    /// expressions are then evaluated in the context of the last one.
+    /// If no modules are given, the Cryptol prelude is loaded.
+    #[must_use]
+    pub fn initial_modules<M: Into<String>>(
+        mut self,
+        modules: impl IntoIterator<Item = M>,
+    ) -> Self {
+        let mut modules: Vec<String> = modules.into_iter().map(Into::into).collect();
+        if modules.is_empty() {
+            modules.push("Cryptol".to_string());
+        }
+        self.settings.initial_modules = Some(modules);
+        self
+    }
+
+    /// Starts a background thread that keeps the connection and the
+    /// current state alive by issuing a trivial query whenever the
+    /// client has made no request for `interval`. Failed pings are
+    /// logged and otherwise ignored. A zero `interval` turns this off,
+    /// which is the default.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.settings.keepalive = Some(interval);
+        self
+    }
+
+    /// Sets the maximum number of requests the client, and its forks,
+    /// have in flight at a time. Requests over the limit wait for an
+    /// earlier one to complete. Zero means no limit, which is the
+    /// default. See [`RateLimitedTransport`].
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.settings.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    /// Sets the maximum number of requests the client, and its forks,
+    /// start per second. Requests over the rate wait their turn. A rate
+    /// that is not positive means no limit, which is the default. See
+    /// [`RateLimitedTransport`].
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn requests_per_second(mut self, requests_per_second: f64) -> Self {
+        self.settings.requests_per_second = Some(requests_per_second);
+        self
+    }
+
+    /// Installs a callback that is called every `interval` while a
+    /// request is pending, on a separate thread, and can interrupt the
+    /// request. A zero `interval` turns this off, which is the
+    /// default. See [`progress`](crate::progress).
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn progress(
+        mut self,
+        interval: Duration,
+        callback: impl Fn(&ProgressEvent<'_>) -> Control + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = (!interval.is_zero()).then(|| {
+            let callback: Arc<ProgressCallback> = Arc::new(callback);
+            (interval, callback)
+        });
+        self
+    }
+
+    /// This function establishes a connection with
+    /// `cryptol-remote-api`. Upon connection, `cryptol-remote-api`
+    /// will load the Cryptol prelude, or the modules given with
+    /// [`initial_modules`](Self::initial_modules), and return a token
+    /// representing the state of the connection.
+    ///
+    /// The connection attempt is recorded in a `connect` tracing span
+    /// carrying the server `url`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if neither a transport nor a URL
+    /// was given and `CRYPTOL_SERVER_URL` is not set, unless a server
+    /// is [discovered](Self::discover), or if the initial requests to
+    /// `cryptol-remote-api` fail, e.g. because an initial module cannot
+    /// be found.
+    #[tracing::instrument(name = "connect", skip(self), fields(url = field::Empty))]
+    pub fn connect(self) -> Result<CryptolClient> {
+        let mut cryptol_client = self.connect_lazy()?;
+        tracing::info!("attempting to connect to cryptol-remote-api");
+
+        // Make requests to `cryptol-remote-api` to load the initial
+        // modules, the Cryptol prelude by default.
+        cryptol_client.load_pending_modules()?;
+
+        Ok(cryptol_client)
+    }
+
+    /// This function creates a client without contacting
+    /// `cryptol-remote-api`. The initial modules are loaded before the
+    /// first request the client makes, and the first request fails if
+    /// they cannot be loaded.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if neither a transport nor a URL
+    /// was given and `CRYPTOL_SERVER_URL` is not set, unless a server
+    /// is [discovered](Self::discover), or if an environment variable
+    /// is set to an invalid value.
+    pub fn connect_lazy(self) -> Result<CryptolClient> {
+        let config = self.resolved_config()?;
+        let client = if let Some(transport) = self.transport {
+            transport
+        } else {
+            let cryptol_server_url = match config.url {
+                Some(url) => url,
+                #[cfg(not(target_arch = "wasm32"))]
+                None if !config.discover.is_empty() => discover::discover(&config.discover)?,
+                None => return Err(env::VarError::NotPresent.into()),
+            };
+            Span::current().record("url", cryptol_server_url.as_str());
+            #[cfg(not(target_arch = "wasm32"))]
+            match Endpoint::parse(&cryptol_server_url)? {
+                Endpoint::Http(url)
+                    if self.http.is_some() || config.http != HttpConfig::default() =>
+                {
+                    let http = config.http.apply(self.http.unwrap_or_default());
+                    let transport: Arc<dyn Transport> = Arc::new(http.build(&url)?);
+                    transport
+                }
+                _ => Arc::from(transport::default_transport(&cryptol_server_url)?),
+            }
+            #[cfg(target_arch = "wasm32")]
+            Arc::from(transport::default_transport(&cryptol_server_url)?)
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let client: Arc<dyn Transport> =
+            if config.max_in_flight.is_some() || config.requests_per_second.is_some() {
+                let mut limits = RateLimitedTransport::builder();
+                if let Some(max_in_flight) = config.max_in_flight {
+                    limits = limits.max_in_flight(max_in_flight);
+                }
+                if let Some(requests_per_second) = config.requests_per_second {
+                    limits = limits.requests_per_second(requests_per_second);
+                }
+                Arc::new(limits.build(client))
+            } else {
+                client
+            };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let keepalive = config
+            .keepalive
+            .map(|interval| Keepalive::start(Arc::clone(&client), interval));
+
+        // Create a new CryptolClient object to represent the stateful
+        // connection. It has no state until the first module is loaded.
+        Ok(CryptolClient {
+            session: RemoteSession::new(client),
+            answer: None,
+            metrics: self.metrics,
+            cache: self.cache,
+            memoize_loads: config.memoize_module_loads,
+            validate_calls: config.validate_calls,
+            trace_states: config.trace_state_transitions,
+            strict: config.strict_decoding,
+            signatures: HashMap::new(),
+            loaded: None,
+            modules: Vec::new(),
+            definitions: Vec::new(),
+            pending_modules: config.initial_modules,
+            pending_version_check: (config.version_check != VersionCheck::Off)
+                .then_some((config.version_check, config.force_version)),
+            solver: config.solver,
+            transcript: self.transcript,
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: self.disk_cache,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_contexts: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            keepalive,
+            #[cfg(not(target_arch = "wasm32"))]
+            progress: self.progress,
+        })
+    }
+}
@@ -0,0 +1,127 @@
+//! # Caching
+//!
+//! A `Cache` memoizes the responses of requests whose outcome depends
+//! on nothing but their parameters, such as `call` and `evaluate`.
+//! Requests are keyed by their method and parameters, which include
+//! the token of the state they are issued from, so a cached response
+//! is only reused from the same point in the lineage of states.
+//!
+//! This is useful when the same calls are made repeatedly, for example
+//! when property-testing a Rust implementation against a Cryptol
+//! specification.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use cryptol_client::cache::Cache;
+//! use cryptol_client::CryptolClient;
+//!
+//! let cache = Arc::new(Cache::new());
+//! let mut cryptol_client = CryptolClient::builder()
+//!     .cache(cache.clone())
+//!     .connect()
+//!     .unwrap();
+//! cryptol_client.call("reverse", &["[1, 2, 3, 4]"]).unwrap();
+//! cryptol_client.call("reverse", &["[1, 2, 3, 4]"]).unwrap();
+//! assert_eq!(cache.hits(), 1);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde_json::{Map, Value};
+
+use crate::CryptolResult;
+
+/// The methods whose responses may be cached.
+pub(crate) const CACHEABLE: &[&str] = &["call", "evaluate expression"];
+
+/// A memoization layer for responses from `cryptol-remote-api`. It may
+/// be shared between clients. Only successful responses are cached.
+#[derive(Debug, Default)]
+pub struct Cache {
+    entries: Mutex<HashMap<(String, String), CryptolResult>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Cache {
+    /// Creates an empty cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This function returns the number of cached responses.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the cache was poisoned by a panic in
+    /// another thread.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// This function returns whether no responses are cached.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the cache was poisoned by a panic in
+    /// another thread.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This function returns the number of requests that were
+    /// answered from the cache.
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// This function returns the number of cacheable requests that
+    /// had to be sent to the server.
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// This function discards all cached responses.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the cache was poisoned by a panic in
+    /// another thread.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// This function returns the key of a request, or `None` if the
+    /// request must not be cached.
+    pub(crate) fn key(method: &str, params: &Map<String, Value>) -> Option<(String, String)> {
+        if !CACHEABLE.contains(&method) || params.get("state").is_none_or(Value::is_null) {
+            return None;
+        }
+        Some((method.to_string(), serde_json::to_string(params).ok()?))
+    }
+
+    /// This function looks up a request, counting a hit or a miss.
+    pub(crate) fn get(&self, key: &(String, String)) -> Option<CryptolResult> {
+        let entry = self.entries.lock().unwrap().get(key).cloned();
+        let counter = if entry.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        entry
+    }
+
+    /// This function records the response to a request.
+    pub(crate) fn insert(&self, key: (String, String), response: CryptolResult) {
+        self.entries.lock().unwrap().insert(key, response);
+    }
+}
@@ -0,0 +1,145 @@
+//! # Checking properties by testing
+//!
+//! `CryptolClient::check` has `cryptol-remote-api` test a property
+//! with parameters, as `:check` does in the Cryptol REPL, on random
+//! values or on every value of its parameters. The [`CheckResult`]
+//! says how many tests were run out of how many are possible, whether
+//! they covered the whole space, and, if the property does not hold,
+//! which arguments falsify it.
+//!
+//! ```no_run
+//! use cryptol_client::check::TestCount;
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! let result = cryptol_client
+//!     .check("\\(x : [8]) -> reverse (reverse [x]) == [x]", TestCount::All)
+//!     .unwrap();
+//! assert!(result.passed() && result.is_exhaustive());
+//! println!("{} of {:?} tests", result.tests_run, result.tests_possible);
+//! ```
+
+use serde_json::json;
+
+use crate::value::{CryptolValue, Integer};
+use crate::{parse, CryptolClient, Result};
+
+/// How many tests `check` runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestCount {
+    /// Test the property on the given number of random arguments.
+    Random(u64),
+    /// Test the property on every possible argument. The server
+    /// refuses this if the space of arguments is infinite.
+    All,
+}
+
+impl Default for TestCount {
+    /// This function returns the number of tests the Cryptol REPL runs
+    /// by default, 100 random ones.
+    fn default() -> Self {
+        TestCount::Random(100)
+    }
+}
+
+/// What testing a property found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The property held on every argument tested.
+    Passed,
+    /// The property is `False` on the counterexample, the arguments in
+    /// order.
+    Failed {
+        /// The arguments on which the property does not hold.
+        counterexample: Vec<CryptolValue>,
+    },
+    /// Evaluating the property on the arguments raised an error, e.g.
+    /// a division by zero.
+    Error {
+        /// The error raised.
+        message: String,
+        /// The arguments on which the error was raised.
+        arguments: Vec<CryptolValue>,
+    },
+}
+
+/// The result of [`CryptolClient::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    /// What the tests found.
+    pub outcome: CheckOutcome,
+    /// The number of tests run, including the one that failed, if any.
+    pub tests_run: u64,
+    /// The number of distinct arguments of the property, or `None` if
+    /// there are infinitely many. It may be far too large for a `u64`,
+    /// e.g. `2^^128` for a property of two `[64]` arguments.
+    pub tests_possible: Option<Integer>,
+}
+
+impl CheckResult {
+    /// This function returns whether the property held on every
+    /// argument tested.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.outcome == CheckOutcome::Passed
+    }
+
+    /// This function returns whether every possible argument was
+    /// tested, i.e. whether a property that passed is proved.
+    #[must_use]
+    pub fn is_exhaustive(&self) -> bool {
+        self.tests_possible == Some(Integer::from(self.tests_run))
+    }
+
+    /// This function returns the arguments on which the property does
+    /// not hold, or `None` if it held or raised an error.
+    #[must_use]
+    pub fn counterexample(&self) -> Option<&[CryptolValue]> {
+        match &self.outcome {
+            CheckOutcome::Failed { counterexample } => Some(counterexample),
+            _ => None,
+        }
+    }
+}
+
+impl CryptolClient {
+    /// This function tests the given property, an expression of a
+    /// function type returning `Bit`, on as many arguments as `tests`
+    /// asks for. A property without parameters is tested once.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Server` if the property does not
+    /// type check, or if `tests` is [`TestCount::All`] and the space of
+    /// arguments is infinite. It returns `Error::Decode` if the result
+    /// cannot be decoded. A property that does not hold is not an
+    /// error.
+    #[tracing::instrument(skip(self))]
+    pub fn check(&mut self, property: &str, tests: TestCount) -> Result<CheckResult> {
+        let mut params = self.session.params();
+        params.insert(
+            "expression".into(),
+            json!(self.substitute_definitions(property)),
+        );
+        let tests = match tests {
+            TestCount::Random(count) => json!(count),
+            TestCount::All => json!("all"),
+        };
+        params.insert("number of tests".into(), tests);
+
+        self.request("check", &params)?;
+
+        let answer = parse::Json::parse(
+            self.answer
+                .as_deref()
+                .map_or("null", serde_json::value::RawValue::get),
+        )?;
+        let result = parse::decode_check_result(&answer)?;
+        tracing::debug!(
+            passed = result.passed(),
+            tests_run = result.tests_run,
+            "checked property"
+        );
+        Ok(result)
+    }
+}
@@ -0,0 +1,177 @@
+//! # Server versions
+//!
+//! The JSON encodings of values and types are not versioned, so a
+//! client talking to a `cryptol-remote-api` it was not written for
+//! tends to fail late and obscurely, with a decoding error on some
+//! answer. The client therefore asks the server for its version before
+//! its first request, compares it against [`COMPATIBLE_VERSIONS`], and
+//! logs a warning on a mismatch. [`CryptolClientBuilder::version_check`]
+//! turns the warning into a failure, or the check off:
+//!
+//! ```no_run
+//! use cryptol_client::compat::VersionCheck;
+//! use cryptol_client::CryptolClient;
+//!
+//! let cryptol_client = CryptolClient::builder()
+//!     .version_check(VersionCheck::Fail)
+//!     .connect()
+//!     .unwrap();
+//! ```
+//!
+//! A deployment known to work despite a mismatch can be used anyway
+//! with [`CryptolClientBuilder::force_version`], or `force_version =
+//! true` in the configuration file, which turns the failure into a
+//! warning.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::{CryptolClient, CryptolClientBuilder, Result};
+
+/// The versions of the protocol of `cryptol-remote-api`, its `RPC
+/// server version`, that this client is known to work with. A version
+/// matches an entry if the entry is a prefix of it made of whole
+/// components, e.g. `3.2.0.99` matches `3.2`.
+pub const COMPATIBLE_VERSIONS: &[&str] = &["0.3", "3.0", "3.1", "3.2", "3.3"];
+
+/// What the client does when the server's version is not known to be
+/// compatible, or the server does not report one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionCheck {
+    /// Do not ask the server for its version.
+    Off,
+    /// Log a warning and carry on.
+    #[default]
+    Warn,
+    /// Fail with `Error::IncompatibleVersion`, unless the check is
+    /// [forced](CryptolClientBuilder::force_version).
+    Fail,
+}
+
+/// The version of `cryptol-remote-api`, as reported by its `version`
+/// method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerVersion {
+    /// The version of the protocol, e.g. `3.2.0.99`.
+    pub rpc_version: String,
+    /// The version of Cryptol the server is built with, e.g. `3.2.0`.
+    pub cryptol_version: String,
+    /// The commit the server is built from, if it reports one.
+    pub commit_hash: Option<String>,
+}
+
+impl ServerVersion {
+    /// This function returns whether the protocol version is one of
+    /// [`COMPATIBLE_VERSIONS`].
+    #[must_use]
+    pub fn is_compatible(&self) -> bool {
+        is_compatible(&self.rpc_version)
+    }
+}
+
+/// This function returns whether `rpc_version` matches one of
+/// [`COMPATIBLE_VERSIONS`].
+#[must_use]
+pub fn is_compatible(rpc_version: &str) -> bool {
+    COMPATIBLE_VERSIONS.iter().any(|known| {
+        rpc_version
+            .strip_prefix(known)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
+
+impl CryptolClientBuilder {
+    /// Sets what the client does if, before its first request, the
+    /// server reports a version that is not one of
+    /// [`COMPATIBLE_VERSIONS`], or none at all. By default a warning is
+    /// logged. See [`compat`](crate::compat).
+    #[must_use]
+    pub fn version_check(mut self, version_check: VersionCheck) -> Self {
+        self.settings.version_check = Some(version_check);
+        self
+    }
+
+    /// Sets whether a failed [`version_check`](Self::version_check) is
+    /// only logged as a warning, for a deployment known to work. This
+    /// is off by default.
+    #[must_use]
+    pub fn force_version(mut self, force_version: bool) -> Self {
+        self.settings.force_version = Some(force_version);
+        self
+    }
+}
+
+impl CryptolClient {
+    /// This function asks the server for its version.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Server` if the server has no
+    /// `version` method, as older ones do not, or `Error::Decode` if
+    /// the answer is not a version.
+    #[tracing::instrument(skip(self))]
+    pub fn server_version(&mut self) -> Result<ServerVersion> {
+        self.load_pending_modules()?;
+        self.request_server_version()
+    }
+
+    /// This function makes the request behind `server_version`, which
+    /// leaves the state unchanged.
+    fn request_server_version(&mut self) -> Result<ServerVersion> {
+        let params = self.session.params();
+        self.round_trip("version", &params, false)?;
+        let answer: Value = match self.answer.take() {
+            Some(answer) => serde_json::from_str(answer.get())?,
+            None => Value::Null,
+        };
+        let field = |name: &str| {
+            answer[name]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| Error::Decode(format!("missing field {name:?} in version")))
+        };
+        Ok(ServerVersion {
+            rpc_version: field("RPC server version")?,
+            cryptol_version: field("version")?,
+            commit_hash: field("commit hash").ok(),
+        })
+    }
+
+    /// This function checks the version of the server as the builder
+    /// asked, if it has not been checked yet.
+    pub(crate) fn check_pending_version(&mut self) -> Result<()> {
+        let Some((check, force)) = self.pending_version_check.take() else {
+            return Ok(());
+        };
+        let version = match self.request_server_version() {
+            Ok(version) if version.is_compatible() => {
+                tracing::debug!(
+                    version = version.rpc_version,
+                    "server version is compatible"
+                );
+                return Ok(());
+            }
+            Ok(version) => Some(version.rpc_version),
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<Error>(),
+                    Some(Error::Server(_) | Error::Decode(_))
+                ) =>
+            {
+                None
+            }
+            Err(e) => {
+                self.pending_version_check = Some((check, force));
+                return Err(e);
+            }
+        };
+        let e = Error::IncompatibleVersion { version };
+        if check == VersionCheck::Fail && !force {
+            return Err(e.into());
+        }
+        tracing::warn!(forced = force, "{e}");
+        Ok(())
+    }
+}
@@ -0,0 +1,426 @@
+//! # Configuration files
+//!
+//! Connection settings can be shared across tools in a
+//! `cryptol-client.toml` file, which [`Config::discover`] looks for in
+//! the working directory and its ancestors. The keys are named after
+//! the methods of [`CryptolClientBuilder`] and
+//! [`HttpTransportBuilder`]
+//! they stand for. Durations are given in seconds, and paths are
+//! relative to the working directory:
+//!
+//! ```toml
+//! url = "https://cryptol.example.com"
+//! initial_modules = ["Cryptol", "SuiteB"]
+//! validate_calls = true
+//! keepalive = 30
+//! max_in_flight = 4
+//! version_check = "fail"
+//! solver = "yices"
+//!
+//! [http]
+//! timeout = 600
+//! compress_requests = true
+//! ca_bundle = "certs/ca.pem"
+//! client_cert = "certs/client.pem"
+//! ```
+//!
+//! Settings missing from the file keep their defaults. Unknown keys
+//! are rejected, so that a misspelt setting is not silently ignored.
+//!
+//! ## Precedence
+//!
+//! Each setting is taken from the first of these that gives it:
+//!
+//! 1. the methods of the `CryptolClientBuilder`, e.g.
+//!    [`url`](CryptolClientBuilder::url);
+//! 2. the environment: `CRYPTOL_SERVER_URL` for `url` and
+//!    `CRYPTOL_SERVER_TIMEOUT` for `http.timeout`, see
+//!    [`Config::from_env`];
+//! 3. the configuration the builder was created from with
+//!    [`CryptolClientBuilder::from_config`];
+//! 4. the default of the setting.
+//!
+//! If none of them gives a URL, the client looks for a server at the
+//! URLs of the `discover` setting, if any; see
+//! [`discover`](crate::discover).
+//!
+//! Settings that are not in the file, like the transport or the
+//! metrics hook, can only be given to the builder.
+//! [`CryptolClientBuilder::resolved_config`] returns the settings a
+//! builder would connect with and where each comes from.
+//!
+//! ```no_run
+//! use cryptol_client::config::Config;
+//! use cryptol_client::CryptolClientBuilder;
+//!
+//! let config = Config::discover().unwrap().unwrap_or_default();
+//! let cryptol_client = CryptolClientBuilder::from_config(&config)
+//!     .validate_calls(false)
+//!     .connect();
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::compat::VersionCheck;
+use crate::prove::DEFAULT_SOLVER;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::HttpTransportBuilder;
+use crate::{CryptolClientBuilder, Result};
+
+/// The name of the file [`Config::discover`] looks for.
+pub const CONFIG_FILE_NAME: &str = "cryptol-client.toml";
+
+/// The environment variable giving the URL of `cryptol-remote-api`.
+pub const URL_VAR: &str = "CRYPTOL_SERVER_URL";
+
+/// The environment variable giving the HTTP timeout in seconds.
+pub const TIMEOUT_VAR: &str = "CRYPTOL_SERVER_TIMEOUT";
+
+/// Client settings read from a configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The URL of `cryptol-remote-api`.
+    pub url: Option<String>,
+    /// The URLs to look for a server at when no URL is given. See
+    /// [`CryptolClientBuilder::discover`].
+    pub discover: Option<Vec<String>>,
+    /// The modules loaded upon connection. See
+    /// [`CryptolClientBuilder::initial_modules`].
+    pub initial_modules: Option<Vec<String>>,
+    /// See [`CryptolClientBuilder::memoize_module_loads`].
+    pub memoize_module_loads: Option<bool>,
+    /// See [`CryptolClientBuilder::validate_calls`].
+    pub validate_calls: Option<bool>,
+    /// See [`CryptolClientBuilder::trace_state_transitions`].
+    pub trace_state_transitions: Option<bool>,
+    /// See [`CryptolClientBuilder::strict_decoding`].
+    pub strict_decoding: Option<bool>,
+    /// See [`CryptolClientBuilder::keepalive`].
+    #[serde(default, deserialize_with = "seconds")]
+    pub keepalive: Option<Duration>,
+    /// See [`CryptolClientBuilder::max_in_flight`].
+    pub max_in_flight: Option<usize>,
+    /// See [`CryptolClientBuilder::requests_per_second`].
+    pub requests_per_second: Option<f64>,
+    /// See [`CryptolClientBuilder::version_check`].
+    pub version_check: Option<VersionCheck>,
+    /// See [`CryptolClientBuilder::force_version`].
+    pub force_version: Option<bool>,
+    /// See [`CryptolClientBuilder::solver`].
+    pub solver: Option<String>,
+    /// The settings of the HTTP transport.
+    #[serde(default)]
+    pub http: HttpConfig,
+}
+
+/// Settings of the HTTP transport, the `[http]` table of a
+/// configuration file. See
+/// [`HttpTransportBuilder`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// How long to wait for the response to a request.
+    #[serde(default, deserialize_with = "seconds")]
+    pub timeout: Option<Duration>,
+    /// Whether to ask for compressed responses.
+    pub accept_compressed: Option<bool>,
+    /// Whether to gzip request bodies.
+    pub compress_requests: Option<bool>,
+    /// How long an unused connection is kept open for reuse.
+    #[serde(default, deserialize_with = "seconds")]
+    pub idle_timeout: Option<Duration>,
+    /// The maximum number of unused connections kept open for reuse.
+    pub max_idle_connections: Option<usize>,
+    /// The interval of TCP keep-alive probes.
+    #[serde(default, deserialize_with = "seconds")]
+    pub tcp_keep_alive: Option<Duration>,
+    /// Whether to speak only HTTP/2.
+    pub http2_only: Option<bool>,
+    /// The maximum size of a response body, in bytes.
+    pub max_response_size: Option<usize>,
+    /// Whether to interrupt a request that times out.
+    pub interrupt_on_timeout: Option<bool>,
+    /// The PEM file of the certificate authorities to trust instead of
+    /// the Mozilla roots.
+    pub ca_bundle: Option<PathBuf>,
+    /// The PEM file of the client certificate chain and private key.
+    pub client_cert: Option<PathBuf>,
+}
+
+/// This function deserializes a duration given as a non-negative
+/// number of seconds.
+fn seconds<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Duration>, D::Error> {
+    let seconds = f64::deserialize(deserializer)?;
+    Duration::try_from_secs_f64(seconds)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("{seconds} is not a number of seconds")))
+}
+
+impl Config {
+    /// This function parses a configuration in TOML.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `toml` is not valid TOML, or
+    /// has a key that is not a setting or a value of the wrong type.
+    pub fn from_toml(toml: &str) -> Result<Config> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// This function reads the configuration file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the file cannot be read or is
+    /// not a valid configuration; see [`from_toml`](Self::from_toml).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)?;
+        Config::from_toml(&toml).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// This function reads the settings given by environment
+    /// variables: the URL from `CRYPTOL_SERVER_URL` and the HTTP
+    /// timeout, in seconds, from `CRYPTOL_SERVER_TIMEOUT`. Empty
+    /// variables are treated as unset.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a variable is not valid
+    /// Unicode, or `CRYPTOL_SERVER_TIMEOUT` is not a number of seconds.
+    pub fn from_env() -> Result<Config> {
+        let var = |name: &str| match env::var(name) {
+            Ok(value) if value.is_empty() => Ok(None),
+            Ok(value) => Ok(Some(value)),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(e) => Err(format!("{name}: {e}")),
+        };
+
+        let mut config = Config {
+            url: var(URL_VAR)?,
+            ..Config::default()
+        };
+        if let Some(timeout) = var(TIMEOUT_VAR)? {
+            let seconds = timeout
+                .parse::<f64>()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or_else(|| format!("{TIMEOUT_VAR}: {timeout:?} is not a number of seconds"))?;
+            config.http.timeout = Some(seconds);
+        }
+        Ok(config)
+    }
+
+    /// This function reads the `cryptol-client.toml` closest to the
+    /// working directory, looking in the working directory and then
+    /// in each of its ancestors. It returns `None` if there is none.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the working directory cannot
+    /// be determined, or if the file found cannot be read or is not a
+    /// valid configuration.
+    pub fn discover() -> Result<Option<Config>> {
+        let dir = env::current_dir()?;
+        for dir in dir.ancestors() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                tracing::debug!(path = %path.display(), "reading configuration");
+                return Config::from_file(path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Where a setting comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The setting was given to the `CryptolClientBuilder`.
+    Builder,
+    /// The setting was read from an environment variable.
+    Environment,
+    /// The setting was read from a configuration file.
+    File,
+    /// The setting was not given, so its default applies.
+    Default,
+}
+
+/// The settings a `CryptolClientBuilder` connects with, as returned
+/// by [`CryptolClientBuilder::resolved_config`].
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::struct_excessive_bools)] // each is an independent setting
+pub struct ResolvedConfig {
+    /// The URL of `cryptol-remote-api`, if one is given anywhere.
+    pub url: Option<String>,
+    /// The URLs to look for a server at if no URL is given, which is
+    /// empty if the client does not look for one.
+    pub discover: Vec<String>,
+    /// The modules loaded upon connection.
+    pub initial_modules: Vec<String>,
+    /// Whether `load_module` skips modules that are already loaded.
+    pub memoize_module_loads: bool,
+    /// Whether `call` checks its arguments first.
+    pub validate_calls: bool,
+    /// Whether every change of state is logged.
+    pub trace_state_transitions: bool,
+    /// Whether decoded answers are checked against their types.
+    pub strict_decoding: bool,
+    /// The keepalive interval, or `None` if keepalive is off.
+    pub keepalive: Option<Duration>,
+    /// The maximum number of requests in flight, or `None` if there is
+    /// no limit.
+    pub max_in_flight: Option<usize>,
+    /// The maximum number of requests started per second, or `None` if
+    /// there is no limit.
+    pub requests_per_second: Option<f64>,
+    /// What is done if the server's version is not known to be
+    /// compatible.
+    pub version_check: VersionCheck,
+    /// Whether a failed version check is only a warning.
+    pub force_version: bool,
+    /// The solver `prove` uses.
+    pub solver: String,
+    /// The settings of the HTTP transport. Those that are `None` keep
+    /// the defaults of the transport.
+    pub http: HttpConfig,
+    sources: Vec<(&'static str, Source)>,
+}
+
+impl ResolvedConfig {
+    /// This function merges `layers`, taking each setting from the
+    /// first layer that gives it.
+    fn resolve(layers: &[(Source, &Config)]) -> Self {
+        let mut sources = Vec::new();
+        macro_rules! pick {
+            ($key:literal, $($field:ident).+) => {{
+                let found = layers.iter().find_map(|(source, config)| {
+                    config.$($field).+.clone().map(|value| (*source, value))
+                });
+                sources.push(($key, found.as_ref().map_or(Source::Default, |(source, _)| *source)));
+                found.map(|(_, value)| value)
+            }};
+        }
+
+        ResolvedConfig {
+            url: pick!("url", url),
+            discover: pick!("discover", discover).unwrap_or_default(),
+            initial_modules: pick!("initial_modules", initial_modules)
+                .unwrap_or_else(|| vec!["Cryptol".to_string()]),
+            memoize_module_loads: pick!("memoize_module_loads", memoize_module_loads)
+                .unwrap_or(true),
+            validate_calls: pick!("validate_calls", validate_calls).unwrap_or(false),
+            trace_state_transitions: pick!("trace_state_transitions", trace_state_transitions)
+                .unwrap_or(false),
+            strict_decoding: pick!("strict_decoding", strict_decoding).unwrap_or(false),
+            keepalive: pick!("keepalive", keepalive).filter(|interval| !interval.is_zero()),
+            max_in_flight: pick!("max_in_flight", max_in_flight).filter(|max| *max > 0),
+            requests_per_second: pick!("requests_per_second", requests_per_second)
+                .filter(|rate| *rate > 0.0),
+            version_check: pick!("version_check", version_check).unwrap_or_default(),
+            force_version: pick!("force_version", force_version).unwrap_or(false),
+            solver: pick!("solver", solver).unwrap_or_else(|| DEFAULT_SOLVER.to_string()),
+            http: HttpConfig {
+                timeout: pick!("http.timeout", http.timeout),
+                accept_compressed: pick!("http.accept_compressed", http.accept_compressed),
+                compress_requests: pick!("http.compress_requests", http.compress_requests),
+                idle_timeout: pick!("http.idle_timeout", http.idle_timeout),
+                max_idle_connections: pick!("http.max_idle_connections", http.max_idle_connections),
+                tcp_keep_alive: pick!("http.tcp_keep_alive", http.tcp_keep_alive),
+                http2_only: pick!("http.http2_only", http.http2_only),
+                max_response_size: pick!("http.max_response_size", http.max_response_size),
+                interrupt_on_timeout: pick!("http.interrupt_on_timeout", http.interrupt_on_timeout),
+                ca_bundle: pick!("http.ca_bundle", http.ca_bundle),
+                client_cert: pick!("http.client_cert", http.client_cert),
+            },
+            sources,
+        }
+    }
+
+    /// This function returns where the setting with the given key
+    /// comes from, or `None` if there is no such setting. Keys are
+    /// written as in a configuration file, e.g. `url` or
+    /// `http.timeout`.
+    #[must_use]
+    pub fn source(&self, key: &str) -> Option<Source> {
+        self.sources
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, source)| *source)
+    }
+}
+
+impl HttpConfig {
+    /// This function applies the settings that are given to `builder`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn apply(&self, mut builder: HttpTransportBuilder) -> HttpTransportBuilder {
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(accept_compressed) = self.accept_compressed {
+            builder = builder.accept_compressed(accept_compressed);
+        }
+        if let Some(compress_requests) = self.compress_requests {
+            builder = builder.compress_requests(compress_requests);
+        }
+        if let Some(idle_timeout) = self.idle_timeout {
+            builder = builder.idle_timeout(Some(idle_timeout));
+        }
+        if let Some(max_idle_connections) = self.max_idle_connections {
+            builder = builder.max_idle_connections(max_idle_connections);
+        }
+        if let Some(tcp_keep_alive) = self.tcp_keep_alive {
+            builder = builder.tcp_keep_alive(Some(tcp_keep_alive));
+        }
+        if let Some(http2_only) = self.http2_only {
+            builder = builder.http2_only(http2_only);
+        }
+        if let Some(max_response_size) = self.max_response_size {
+            builder = builder.max_response_size(Some(max_response_size));
+        }
+        if let Some(interrupt_on_timeout) = self.interrupt_on_timeout {
+            builder = builder.interrupt_on_timeout(interrupt_on_timeout);
+        }
+        if let Some(ca_bundle) = &self.ca_bundle {
+            builder = builder.ca_bundle(ca_bundle);
+        }
+        if let Some(client_cert) = &self.client_cert {
+            builder = builder.client_cert(client_cert);
+        }
+        builder
+    }
+}
+
+impl CryptolClientBuilder {
+    /// Creates a builder with the settings of `config`, which are used
+    /// where neither the builder nor the environment gives a setting.
+    #[must_use]
+    pub fn from_config(config: &Config) -> Self {
+        let mut builder = CryptolClientBuilder::new();
+        builder.file = config.clone();
+        builder
+    }
+
+    /// This function returns the settings the builder connects with,
+    /// and where each comes from.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if an environment variable is
+    /// set to an invalid value. See [`Config::from_env`].
+    pub fn resolved_config(&self) -> Result<ResolvedConfig> {
+        Ok(ResolvedConfig::resolve(&[
+            (Source::Builder, &self.settings),
+            (Source::Environment, &Config::from_env()?),
+            (Source::File, &self.file),
+        ]))
+    }
+}
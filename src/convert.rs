@@ -0,0 +1,461 @@
+//! # Conversions
+//!
+//! `FromCryptolValue` converts decoded Cryptol values into Rust
+//! values, e.g. a `[4]Integer` into a `Vec<i64>` or a `[32]` into a
+//! `u32`. It is used by [`CryptolClient::call_as`](crate::CryptolClient::call_as).
+//!
+//! `ToCryptolValue` goes the other way. Together with
+//! [`CryptolValue::to_expression`] it lets Rust values be spliced into
+//! Cryptol expressions by the [`cryptol!`](crate::cryptol) and
+//! [`eval!`](crate::eval) macros. Fixed-width integers become
+//! bitvectors of the same width, two's complement for signed types;
+//! `usize` and `isize` become `Integer`s.
+//!
+//! ```
+//! use cryptol_client::convert::FromCryptolValue;
+//! use cryptol_client::value::CryptolValue;
+//!
+//! let value = CryptolValue::Tuple(vec![CryptolValue::Bit(true), CryptolValue::Integer(7.into())]);
+//! let (bit, n) = <(bool, u8)>::from_cryptol_value(value).unwrap();
+//! assert!(bit);
+//! assert_eq!(n, 7);
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::value::{BitVector, CryptolValue, Integer};
+
+/// A Rust type that Cryptol values can be converted into.
+pub trait FromCryptolValue: Sized {
+    /// This function converts `value`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if `value` does not have a
+    /// shape that can be represented by `Self`, or does not fit.
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error>;
+}
+
+/// This function reports that `value` cannot be converted.
+pub(crate) fn mismatch(expected: &str, value: &CryptolValue) -> Error {
+    Error::Decode(format!("expected {expected}, found {value:?}"))
+}
+
+impl FromCryptolValue for CryptolValue {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl FromCryptolValue for BitVector {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::BitVector(bits) => Ok(bits),
+            value => Err(mismatch("a bitvector", &value)),
+        }
+    }
+}
+
+impl FromCryptolValue for Integer {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::Integer(n) => Ok(n),
+            value => Err(mismatch("an integer", &value)),
+        }
+    }
+}
+
+impl FromCryptolValue for bool {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::Bit(b) => Ok(b),
+            value => Err(mismatch("a bit", &value)),
+        }
+    }
+}
+
+impl FromCryptolValue for () {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::Unit => Ok(()),
+            value => Err(mismatch("()", &value)),
+        }
+    }
+}
+
+/// This function returns the value of a bitvector as an unsigned
+/// integer, or `None` if it does not fit into 128 bits.
+fn bitvector_value(bits: &BitVector) -> Option<u128> {
+    let bytes = bits.as_bytes();
+    let leading = bytes.iter().take_while(|&&b| b == 0).count();
+    let significant = &bytes[leading..];
+    if significant.len() > 16 {
+        return None;
+    }
+    Some(
+        significant
+            .iter()
+            .fold(0u128, |acc, &b| (acc << 8) | u128::from(b)),
+    )
+}
+
+/// Integers convert from `Integer` and `Z n` values, and from
+/// bitvectors read as unsigned numbers, as long as the value fits. A
+/// bitvector exactly as wide as a signed type is read as two's
+/// complement instead.
+macro_rules! from_integer {
+    ($($t:ty),*) => {$(
+        impl FromCryptolValue for $t {
+            fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+                let fits = match &value {
+                    CryptolValue::Integer(n) => <$t>::try_from(n).ok(),
+                    CryptolValue::IntegerModulo { value: n, .. } => <$t>::try_from(n).ok(),
+                    CryptolValue::BitVector(bits)
+                        if <$t>::MIN != 0 && bits.width() == 8 * std::mem::size_of::<$t>() =>
+                    {
+                        bits.as_bytes().try_into().ok().map(<$t>::from_be_bytes)
+                    }
+                    CryptolValue::BitVector(bits) => {
+                        bitvector_value(bits).and_then(|n| <$t>::try_from(n).ok())
+                    }
+                    _ => return Err(mismatch("an integer or bitvector", &value)),
+                };
+                fits.ok_or_else(|| mismatch(concat!("a value that fits in ", stringify!($t)), &value))
+            }
+        }
+    )*};
+}
+
+from_integer!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: FromCryptolValue> FromCryptolValue for Vec<T> {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::Sequence(elements) => {
+                elements.into_iter().map(T::from_cryptol_value).collect()
+            }
+            value => Err(mismatch("a sequence", &value)),
+        }
+    }
+}
+
+impl<const N: usize> FromCryptolValue for [u8; N] {
+    /// Byte arrays convert from bitvectors of width `8 * N`, most
+    /// significant byte first.
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::BitVector(bits) if bits.width() == 8 * N => {
+                let mut bytes = [0u8; N];
+                bytes.copy_from_slice(bits.as_bytes());
+                Ok(bytes)
+            }
+            value => Err(mismatch(&format!("a bitvector of width {}", 8 * N), &value)),
+        }
+    }
+}
+
+impl<T: FromCryptolValue> FromCryptolValue for BTreeMap<String, T> {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::Record(fields) => fields
+                .into_iter()
+                .map(|(name, v)| Ok((name, T::from_cryptol_value(v)?)))
+                .collect(),
+            value => Err(mismatch("a record", &value)),
+        }
+    }
+}
+
+/// Tuples convert from tuples with the same number of components.
+macro_rules! from_tuple {
+    ($n:literal: $($t:ident),*) => {
+        impl<$($t: FromCryptolValue),*> FromCryptolValue for ($($t,)*) {
+            fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+                match value {
+                    CryptolValue::Tuple(components) if components.len() == $n => {
+                        let mut components = components.into_iter();
+                        Ok(($($t::from_cryptol_value(components.next().unwrap())?,)*))
+                    }
+                    value => Err(mismatch(concat!("a tuple of ", $n, " components"), &value)),
+                }
+            }
+        }
+    };
+}
+
+from_tuple!(1: A);
+from_tuple!(2: A, B);
+from_tuple!(3: A, B, C);
+from_tuple!(4: A, B, C, D);
+from_tuple!(5: A, B, C, D, E);
+from_tuple!(6: A, B, C, D, E, F);
+
+/// With the `num-bigint` feature, unsigned big integers convert from
+/// bitvectors of any width and from non-negative `Integer`s.
+#[cfg(feature = "num-bigint")]
+impl FromCryptolValue for num_bigint::BigUint {
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::BitVector(bits) => Ok(bits.to_biguint()),
+            CryptolValue::Integer(n) if !n.is_negative() => {
+                Ok(num_bigint::BigUint::from_bytes_be(n.magnitude()))
+            }
+            value => Err(mismatch("a bitvector or a non-negative integer", &value)),
+        }
+    }
+}
+
+/// With the `bitvec` feature, bit vectors convert from bitvectors of
+/// any width, and from sequences of bits.
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> FromCryptolValue
+    for bitvec::vec::BitVec<T, O>
+{
+    fn from_cryptol_value(value: CryptolValue) -> Result<Self, Error> {
+        match value {
+            CryptolValue::BitVector(bits) => Ok(Self::from(&bits)),
+            CryptolValue::Sequence(elements) => {
+                elements.into_iter().map(bool::from_cryptol_value).collect()
+            }
+            value => Err(mismatch("a bitvector", &value)),
+        }
+    }
+}
+
+/// A Rust type whose values can be converted into Cryptol values.
+pub trait ToCryptolValue {
+    /// This function converts `self`.
+    fn to_cryptol_value(&self) -> CryptolValue;
+}
+
+impl<T: ToCryptolValue + ?Sized> ToCryptolValue for &T {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        (**self).to_cryptol_value()
+    }
+}
+
+impl ToCryptolValue for CryptolValue {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        self.clone()
+    }
+}
+
+impl ToCryptolValue for BitVector {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::BitVector(self.clone())
+    }
+}
+
+impl ToCryptolValue for Integer {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::Integer(self.clone())
+    }
+}
+
+impl ToCryptolValue for bool {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::Bit(*self)
+    }
+}
+
+impl ToCryptolValue for () {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::Unit
+    }
+}
+
+/// With the `bitvec` feature, bit slices convert to bitvectors of the
+/// same width.
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> ToCryptolValue
+    for bitvec::slice::BitSlice<T, O>
+{
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::BitVector(BitVector::from(self))
+    }
+}
+
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> ToCryptolValue
+    for bitvec::vec::BitVec<T, O>
+{
+    fn to_cryptol_value(&self) -> CryptolValue {
+        self.as_bitslice().to_cryptol_value()
+    }
+}
+
+/// Fixed-width integers convert to bitvectors of the same width.
+macro_rules! to_bitvector {
+    ($($t:ty),*) => {$(
+        impl ToCryptolValue for $t {
+            fn to_cryptol_value(&self) -> CryptolValue {
+                CryptolValue::BitVector(BitVector::from_be_bytes(self.to_be_bytes().to_vec()))
+            }
+        }
+    )*};
+}
+
+to_bitvector!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl ToCryptolValue for usize {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        // A `usize` is at most 128 bits wide on every target.
+        CryptolValue::Integer(u128::try_from(*self).unwrap_or(u128::MAX).into())
+    }
+}
+
+impl ToCryptolValue for isize {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::Integer(i128::try_from(*self).unwrap_or(i128::MAX).into())
+    }
+}
+
+impl<T: ToCryptolValue> ToCryptolValue for [T] {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::Sequence(self.iter().map(T::to_cryptol_value).collect())
+    }
+}
+
+impl<T: ToCryptolValue, const N: usize> ToCryptolValue for [T; N] {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        self.as_slice().to_cryptol_value()
+    }
+}
+
+impl<T: ToCryptolValue> ToCryptolValue for Vec<T> {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        self.as_slice().to_cryptol_value()
+    }
+}
+
+/// Strings convert to sequences of bytes, like Cryptol string
+/// literals.
+impl ToCryptolValue for str {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        self.as_bytes().to_cryptol_value()
+    }
+}
+
+impl ToCryptolValue for String {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        self.as_str().to_cryptol_value()
+    }
+}
+
+impl<T: ToCryptolValue> ToCryptolValue for BTreeMap<String, T> {
+    fn to_cryptol_value(&self) -> CryptolValue {
+        CryptolValue::Record(
+            self.iter()
+                .map(|(name, v)| (name.clone(), v.to_cryptol_value()))
+                .collect(),
+        )
+    }
+}
+
+/// Tuples convert to tuples with the same number of components.
+macro_rules! to_tuple {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: ToCryptolValue),*> ToCryptolValue for ($($t,)*) {
+            fn to_cryptol_value(&self) -> CryptolValue {
+                CryptolValue::Tuple(vec![$(self.$i.to_cryptol_value()),*])
+            }
+        }
+    };
+}
+
+to_tuple!(A 0, B 1);
+to_tuple!(A 0, B 1, C 2);
+to_tuple!(A 0, B 1, C 2, D 3);
+to_tuple!(A 0, B 1, C 2, D 3, E 4);
+to_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// This function returns `s` as a Cryptol expression of type
+/// `[n][8]`, the UTF-8 encoding of `s`, so that arbitrary Rust strings
+/// can be embedded in Cryptol source. Strings of printable ASCII are
+/// written as string literals, with `"` and `\` escaped; any other
+/// string, e.g. one with a newline or a non-ASCII character, is
+/// written as an annotated sequence of bytes, since Cryptol string
+/// literals do not encode characters as UTF-8.
+///
+/// ```
+/// use cryptol_client::convert::string_literal;
+///
+/// assert_eq!(string_literal(r#"say "hi""#), r#""say \"hi\"""#);
+/// assert_eq!(string_literal("\u{e9}"), "([0xc3, 0xa9] : [2][8])");
+/// ```
+#[must_use]
+pub fn string_literal(s: &str) -> String {
+    if s.bytes().all(|b| b == b' ' || b.is_ascii_graphic()) {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+            if matches!(c, '"' | '\\') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('"');
+        return out;
+    }
+
+    format!(
+        "({} : [{}][8])",
+        s.as_bytes().to_cryptol_value().to_expression(),
+        s.len()
+    )
+}
+
+/// This function replaces each `{}` in `template` with the concrete
+/// syntax of the next value in `values`. `{{` and `}}` stand for
+/// literal braces, which Cryptol uses for records and type
+/// applications. This is the function behind the
+/// [`cryptol!`](crate::cryptol) macro.
+///
+/// ```
+/// use cryptol_client::convert::splice;
+/// use cryptol_client::value::CryptolValue;
+///
+/// let expression = splice(
+///     "take`{{2}} {}",
+///     &[CryptolValue::Sequence(vec![CryptolValue::Bit(true)])],
+/// );
+/// assert_eq!(expression.unwrap(), "take`{2} [True]");
+/// ```
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if the number of `{}` does not
+/// match the number of values, or if a brace is not escaped.
+pub fn splice(template: &str, values: &[CryptolValue]) -> Result<String, Error> {
+    let mut out = String::with_capacity(template.len());
+    let mut values = values.iter();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                out.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let value = values.next().ok_or_else(|| {
+                    Error::Decode(format!("too few values for template {template:?}"))
+                })?;
+                out.push_str(&value.to_expression());
+            }
+            ('{' | '}', _) => {
+                return Err(Error::Decode(format!(
+                    "unescaped {c:?} in template {template:?}"
+                )))
+            }
+            _ => out.push(c),
+        }
+    }
+    if values.next().is_some() {
+        return Err(Error::Decode(format!(
+            "too many values for template {template:?}"
+        )));
+    }
+    Ok(out)
+}
@@ -0,0 +1,105 @@
+//! # Definitions
+//!
+//! `cryptol-remote-api` has no way to add a top-level definition to a
+//! session, and a module of definitions could only be loaded from a
+//! file the server can read. Definitions are therefore substituted on
+//! the client: `CryptolClient::define` evaluates an expression once
+//! and remembers its value under a name, and every later expression,
+//! including each expression argument of a call, is sent wrapped in a
+//! `where` block binding the names it refers to to the literals of
+//! their values. An expensive intermediate result is computed only
+//! once, but its literal is sent with every request that uses it, and
+//! the server never sees the names as declarations. Values the server
+//! cannot encode, like functions, are bound to the identifier the
+//! server handed out for them.
+
+use std::collections::HashSet;
+
+use crate::error::Error;
+use crate::expr;
+use crate::value::CryptolValue;
+use crate::{CryptolClient, Result};
+
+impl CryptolClient {
+    /// This function evaluates the given Cryptol expression and names
+    /// its value `name` in later calls of
+    /// [`evaluate`](Self::evaluate),
+    /// [`evaluate_expr`](Self::evaluate_expr),
+    /// [`stream_bytes`](Self::stream_bytes) and in the expression
+    /// arguments of [`call`](Self::call). Defining a name again
+    /// replaces its value. The value is returned.
+    ///
+    /// The definition is kept by the client, which substitutes the
+    /// value into each later expression that refers to it, so it
+    /// shadows a declaration of the same name in the loaded module. It
+    /// cannot be called by name, as the function of a call is looked up
+    /// in the loaded module, and it is not visible to other clients of
+    /// the same session.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// cryptol_client.define("key", "join (repeat`{16} 0x2b)").unwrap();
+    /// let answer = cryptol_client.evaluate("key ^ zero").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if `name` is not an
+    /// unqualified Cryptol identifier, or an error if evaluating the
+    /// expression fails.
+    #[tracing::instrument(skip(self))]
+    pub fn define(&mut self, name: &str, expression: &str) -> Result<CryptolValue> {
+        if !expr::is_identifier(name) {
+            return Err(Error::Decode(format!("{name:?} cannot be defined")).into());
+        }
+
+        let value = self.evaluate(expression)?.decode()?;
+        match self
+            .definitions
+            .iter_mut()
+            .find(|(defined, _)| defined == name)
+        {
+            Some((_, old)) => *old = value.clone(),
+            None => self.definitions.push((name.to_string(), value.clone())),
+        }
+
+        Ok(value)
+    }
+
+    /// This function returns the names defined with
+    /// [`define`](Self::define) and their values, in the order they
+    /// were first defined.
+    #[must_use]
+    pub fn definitions(&self) -> &[(String, CryptolValue)] {
+        &self.definitions
+    }
+
+    /// This function returns `expression` in a `where` block binding
+    /// each definition it refers to to the literal of its value, or
+    /// `expression` itself if it refers to none.
+    ///
+    /// A definition counts as referred to if its name is a token of the
+    /// expression. A name in a string literal, a comment or as a record
+    /// field counts too, which only costs an unneeded binding.
+    pub(crate) fn substitute_definitions(&self, expression: &str) -> String {
+        if self.definitions.is_empty() {
+            return expression.to_string();
+        }
+
+        let tokens: HashSet<&str> = expression
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+            .collect();
+        let bindings: Vec<String> = self
+            .definitions
+            .iter()
+            .filter(|(name, _)| tokens.contains(name.as_str()))
+            .map(|(name, value)| format!("{name} = {}", value.to_expression()))
+            .collect();
+        if bindings.is_empty() {
+            return expression.to_string();
+        }
+        format!("({expression}) where {{ {} }}", bindings.join("; "))
+    }
+}
@@ -0,0 +1,260 @@
+//! # Diagnostics
+//!
+//! Cryptol reports where in the source a parse or type error is, as a
+//! range such as `<interactive>:1:8--1:12` in the message of the
+//! error. [`CryptolError::diagnostic`] renders such an error the way a
+//! compiler would, with the offending lines of source and the range
+//! underlined:
+//!
+//! ```text
+//! error (code 20040): [error] at <interactive>:1:8--1:12:
+//!  --> <interactive>:1:8
+//!   |
+//! 1 | 0x01 + True
+//!   |        ^^^^
+//!   |
+//!     Type mismatch:
+//!       Expected type: [8]
+//!       Inferred type: Bit
+//! ```
+//!
+//! The source of `<interactive>` is the expression of the failed
+//! request, which the client keeps in the error. That of a module
+//! file can be given with [`Diagnostic::source`].
+//!
+//! ```no_run
+//! use cryptol_client::error::Error;
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! if let Err(e) = cryptol_client.evaluate("0x01 + True") {
+//!     match e.downcast_ref::<Error>() {
+//!         Some(Error::Server(error)) => eprintln!("{}", error.diagnostic()),
+//!         _ => eprintln!("{e}"),
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::error::CryptolError;
+
+/// The name Cryptol gives the source of an expression that is not in a
+/// file.
+const INTERACTIVE: &str = "<interactive>";
+
+/// A range of source reported in an error, from `start` up to but not
+/// including `end`. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// The source, e.g. `<interactive>` or the path of a module file.
+    pub source: String,
+    /// The line and column of the first character.
+    pub start: (usize, usize),
+    /// The line and column just past the last character.
+    pub end: (usize, usize),
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}--{}:{}",
+            self.source, self.start.0, self.start.1, self.end.0, self.end.1
+        )
+    }
+}
+
+/// This function returns the source ranges mentioned in `message`, in
+/// order, each written `source:line:column--line:column`.
+#[must_use]
+pub fn spans(message: &str) -> Vec<SourceSpan> {
+    let mut spans = Vec::new();
+    let mut rest = message;
+    while let Some(i) = rest.find("--") {
+        let (before, after) = (&rest[..i], &rest[i + 2..]);
+        rest = after;
+        let Some((start_source, start)) = position(before) else {
+            continue;
+        };
+        let Some(end) = leading_position(after) else {
+            continue;
+        };
+        // The source of a range is the word before its start, which
+        // may follow `at` or an opening bracket.
+        let source = start_source
+            .rsplit(|c: char| c.is_whitespace() || c == '(' || c == '[')
+            .next()
+            .unwrap_or_default();
+        spans.push(SourceSpan {
+            source: source.to_string(),
+            start,
+            end,
+        });
+    }
+    spans
+}
+
+/// This function returns the position `line:column` that `text`
+/// starts with.
+fn leading_position(text: &str) -> Option<(usize, usize)> {
+    let number = |text: &str| {
+        let digits = text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len());
+        Some((text[..digits].parse().ok()?, digits))
+    };
+    let (line, digits) = number(text)?;
+    let rest = text[digits..].strip_prefix(':')?;
+    let (column, _) = number(rest)?;
+    Some((line, column))
+}
+
+/// This function splits `text`, which ends in `source:line:column`,
+/// into the text before the line and the position.
+fn position(text: &str) -> Option<(&str, (usize, usize))> {
+    let (text, column) = text.rsplit_once(':')?;
+    let (text, line) = text.rsplit_once(':')?;
+    Some((text, (line.parse().ok()?, column.parse().ok()?)))
+}
+
+/// A server error rendered with the source it refers to. Created by
+/// [`CryptolError::diagnostic`].
+#[derive(Debug, Clone)]
+pub struct Diagnostic<'a> {
+    error: &'a CryptolError,
+    sources: Vec<(String, String)>,
+}
+
+impl Diagnostic<'_> {
+    /// This function sets the text of the source named `name`, e.g. of
+    /// the module file a type error is in, to show the lines of the
+    /// error from.
+    #[must_use]
+    pub fn source(mut self, name: impl Into<String>, text: impl Into<String>) -> Self {
+        let name = name.into();
+        self.sources.retain(|(source, _)| *source != name);
+        self.sources.push((name, text.into()));
+        self
+    }
+
+    /// This function writes the lines of `text` that `span` covers,
+    /// with the covered characters underlined, after a gutter of
+    /// `gutter` columns for the line numbers.
+    fn write_snippet(
+        f: &mut fmt::Formatter<'_>,
+        span: &SourceSpan,
+        text: &str,
+        gutter: usize,
+    ) -> fmt::Result {
+        writeln!(f, "{:gutter$} |", "")?;
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+            if number < span.start.0 || number > span.end.0 {
+                continue;
+            }
+            let length = line.chars().count();
+            let from = if number == span.start.0 {
+                span.start.1
+            } else {
+                1
+            };
+            let to = if number == span.end.0 {
+                span.end.1
+            } else {
+                length + 1
+            };
+            writeln!(f, "{number:>gutter$} | {line}")?;
+            let width = to.min(length + 1).saturating_sub(from).max(1);
+            writeln!(
+                f,
+                "{:gutter$} | {:indent$}{}",
+                "",
+                "",
+                "^".repeat(width),
+                indent = from.saturating_sub(1)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = self.error.message();
+        let mut lines = message.lines();
+        writeln!(
+            f,
+            "error (code {}): {}",
+            self.error.code(),
+            lines.next().unwrap_or_default()
+        )?;
+        let spans = spans(message);
+        let gutter = spans
+            .iter()
+            .map(|span| span.end.0.to_string().len())
+            .max()
+            .unwrap_or(1);
+        for span in &spans {
+            writeln!(
+                f,
+                "{:gutter$}--> {}:{}:{}",
+                "", span.source, span.start.0, span.start.1
+            )?;
+            if let Some((_, text)) = self.sources.iter().find(|(name, _)| *name == span.source) {
+                Diagnostic::write_snippet(f, span, text, gutter)?;
+            }
+        }
+        let rest: Vec<&str> = lines.collect();
+        if !rest.is_empty() {
+            if !spans.is_empty() {
+                writeln!(f, "{:gutter$} |", "")?;
+            }
+            for line in rest {
+                writeln!(f, "{:gutter$} {line}", "")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CryptolError {
+    /// This function returns the source ranges the error refers to,
+    /// e.g. where a parse error is. It is empty for errors without
+    /// any.
+    #[must_use]
+    pub fn spans(&self) -> Vec<SourceSpan> {
+        spans(self.message())
+    }
+
+    /// This function returns the expression of the request that
+    /// failed, if it had one, which is the source of the
+    /// `<interactive>` ranges of the error.
+    #[must_use]
+    pub fn expression(&self) -> Option<&str> {
+        self.expression.as_deref()
+    }
+
+    /// This function returns the error rendered with the lines of
+    /// source it refers to. See [`diagnostic`](crate::diagnostic).
+    #[must_use]
+    pub fn diagnostic(&self) -> Diagnostic<'_> {
+        let diagnostic = Diagnostic {
+            error: self,
+            sources: Vec::new(),
+        };
+        match &self.expression {
+            Some(expression) => diagnostic.source(INTERACTIVE, expression.as_str()),
+            None => diagnostic,
+        }
+    }
+
+    /// This function keeps the expression of the failed request with
+    /// `params` in the error.
+    pub(crate) fn set_expression(&mut self, params: &Map<String, Value>) {
+        if let Some(Value::String(expression)) = params.get("expression") {
+            self.expression = Some(expression.clone());
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! # Value differences
+//!
+//! [`CryptolValue::diff`] compares two values and describes every
+//! place where they differ: the path to it, written with Cryptol's
+//! selectors, and what differs there. This makes for readable test
+//! failures when a Rust implementation diverges from its
+//! specification, e.g.
+//!
+//! ```text
+//! @1.key: bytes 0, 1 differ: 0x0001 vs 0x0100
+//! ```
+//!
+//! ```
+//! use cryptol_client::value::{BitVector, CryptolValue};
+//!
+//! let expected = CryptolValue::Sequence(vec![
+//!     CryptolValue::BitVector(BitVector::from_be_bytes(vec![0x01, 0x02])),
+//!     CryptolValue::BitVector(BitVector::from_be_bytes(vec![0x03, 0x04])),
+//! ]);
+//! let actual = CryptolValue::Sequence(vec![
+//!     CryptolValue::BitVector(BitVector::from_be_bytes(vec![0x01, 0x02])),
+//!     CryptolValue::BitVector(BitVector::from_be_bytes(vec![0x03, 0xff])),
+//! ]);
+//!
+//! let differences = expected.diff(&actual);
+//! assert_eq!(differences.len(), 1);
+//! assert_eq!(differences[0].to_string(), "@1: byte 1 differs: 0x0304 vs 0x03ff");
+//! ```
+//!
+//! [`assert_cryptol_eq!`](crate::assert_cryptol_eq) uses these
+//! differences to report an expression whose value is not the expected
+//! Rust value.
+
+use std::error;
+use std::fmt;
+
+use crate::value::{BitVector, CryptolValue};
+use crate::{CryptolClient, Result};
+
+/// A step from a value into one of its parts, written as the Cryptol
+/// selector that takes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// An element of a sequence, `@i`.
+    Element(usize),
+    /// A component of a tuple, `.i`.
+    Component(usize),
+    /// A field of a record, `.name`.
+    Field(String),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Element(i) => write!(f, "@{i}"),
+            PathSegment::Component(i) => write!(f, ".{i}"),
+            PathSegment::Field(name) => write!(f, ".{name}"),
+        }
+    }
+}
+
+/// What differs between two values at some path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// The values differ as a whole, e.g. two integers, or values of
+    /// different shapes.
+    Value {
+        /// The value on the left.
+        left: CryptolValue,
+        /// The value on the right.
+        right: CryptolValue,
+    },
+    /// Two bitvectors of the same width differ in some bytes.
+    Bytes {
+        /// The offsets of the differing bytes, most significant first.
+        offsets: Vec<usize>,
+        /// The bitvector on the left.
+        left: BitVector,
+        /// The bitvector on the right.
+        right: BitVector,
+    },
+    /// Two sequences or tuples have different lengths. The elements
+    /// they both have are compared as well.
+    Length {
+        /// The length on the left.
+        left: usize,
+        /// The length on the right.
+        right: usize,
+    },
+    /// Two records have different fields. The fields they both have
+    /// are compared as well.
+    Fields {
+        /// The fields only the left record has.
+        left_only: Vec<String>,
+        /// The fields only the right record has.
+        right_only: Vec<String>,
+    },
+}
+
+impl fmt::Display for DifferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DifferenceKind::Value { left, right } => write!(f, "{left:?} vs {right:?}"),
+            DifferenceKind::Bytes {
+                offsets,
+                left,
+                right,
+            } => {
+                let noun = if offsets.len() == 1 { "byte" } else { "bytes" };
+                let verb = if offsets.len() == 1 {
+                    "differs"
+                } else {
+                    "differ"
+                };
+                let offsets: Vec<_> = offsets.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "{noun} {} {verb}: {left:?} vs {right:?}",
+                    offsets.join(", ")
+                )
+            }
+            DifferenceKind::Length { left, right } => {
+                write!(f, "{left} elements vs {right} elements")
+            }
+            DifferenceKind::Fields {
+                left_only,
+                right_only,
+            } => write!(
+                f,
+                "fields {{{}}} vs {{{}}}",
+                left_only.join(", "),
+                right_only.join(", ")
+            ),
+        }
+    }
+}
+
+/// A place where two values differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// The path from the outermost value to the differing part, empty
+    /// if the outermost values differ.
+    pub path: Vec<PathSegment>,
+    /// What differs there.
+    pub kind: DifferenceKind,
+}
+
+impl Difference {
+    /// This function creates a difference found at `path`.
+    fn at(path: &[PathSegment], kind: DifferenceKind) -> Self {
+        Difference {
+            path: path.to_vec(),
+            kind,
+        }
+    }
+
+    /// This function returns the path as Cryptol selectors, e.g.
+    /// `@1.key`, or an empty string for the outermost value.
+    #[must_use]
+    pub fn path_string(&self) -> String {
+        self.path.iter().map(ToString::to_string).collect()
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{}: {}", self.path_string(), self.kind)
+        }
+    }
+}
+
+impl CryptolValue {
+    /// This function compares the value with `other` and returns every
+    /// place where they differ, in the order the parts appear. It
+    /// returns no differences if and only if the values are equal.
+    ///
+    /// Sequences, tuples and records are compared part by part, and
+    /// bitvectors of the same width byte by byte; any other pair of
+    /// unequal values is reported as a whole.
+    #[must_use]
+    pub fn diff(&self, other: &CryptolValue) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        diff_into(self, other, &mut Vec::new(), &mut differences);
+        differences
+    }
+}
+
+/// This function adds the differences between `left` and `right`,
+/// found at `path`, to `out`.
+fn diff_into(
+    left: &CryptolValue,
+    right: &CryptolValue,
+    path: &mut Vec<PathSegment>,
+    out: &mut Vec<Difference>,
+) {
+    match (left, right) {
+        (CryptolValue::BitVector(l), CryptolValue::BitVector(r)) if l.width() == r.width() => {
+            let offsets: Vec<_> = l
+                .as_bytes()
+                .iter()
+                .zip(r.as_bytes())
+                .enumerate()
+                .filter(|(_, (l, r))| l != r)
+                .map(|(i, _)| i)
+                .collect();
+            if !offsets.is_empty() {
+                out.push(Difference::at(
+                    path,
+                    DifferenceKind::Bytes {
+                        offsets,
+                        left: l.clone(),
+                        right: r.clone(),
+                    },
+                ));
+            }
+        }
+        (CryptolValue::Sequence(l), CryptolValue::Sequence(r)) => {
+            diff_elements(l, r, PathSegment::Element, path, out);
+        }
+        (CryptolValue::Tuple(l), CryptolValue::Tuple(r)) => {
+            diff_elements(l, r, PathSegment::Component, path, out);
+        }
+        (CryptolValue::Record(l), CryptolValue::Record(r)) => {
+            let left_only: Vec<_> = l.keys().filter(|k| !r.contains_key(*k)).cloned().collect();
+            let right_only: Vec<_> = r.keys().filter(|k| !l.contains_key(*k)).cloned().collect();
+            if !left_only.is_empty() || !right_only.is_empty() {
+                out.push(Difference::at(
+                    path,
+                    DifferenceKind::Fields {
+                        left_only,
+                        right_only,
+                    },
+                ));
+            }
+            for (name, l) in l {
+                if let Some(r) = r.get(name) {
+                    path.push(PathSegment::Field(name.clone()));
+                    diff_into(l, r, path, out);
+                    path.pop();
+                }
+            }
+        }
+        _ if left != right => out.push(Difference::at(
+            path,
+            DifferenceKind::Value {
+                left: left.clone(),
+                right: right.clone(),
+            },
+        )),
+        _ => {}
+    }
+}
+
+/// This function adds the differences between the sequences or tuples
+/// `left` and `right`, found at `path`, to `out`, stepping into their
+/// elements with `segment`.
+fn diff_elements(
+    left: &[CryptolValue],
+    right: &[CryptolValue],
+    segment: fn(usize) -> PathSegment,
+    path: &mut Vec<PathSegment>,
+    out: &mut Vec<Difference>,
+) {
+    if left.len() != right.len() {
+        out.push(Difference::at(
+            path,
+            DifferenceKind::Length {
+                left: left.len(),
+                right: right.len(),
+            },
+        ));
+    }
+    for (i, (l, r)) in left.iter().zip(right).enumerate() {
+        path.push(segment(i));
+        diff_into(l, r, path, out);
+        path.pop();
+    }
+}
+
+/// This error reports that a Cryptol expression does not evaluate to
+/// the expected value.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    expression: String,
+    cryptol: CryptolValue,
+    expected: CryptolValue,
+    differences: Vec<Difference>,
+}
+
+impl Mismatch {
+    /// This function returns the expression that was evaluated.
+    #[must_use]
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// This function returns the value of the expression.
+    #[must_use]
+    pub fn cryptol(&self) -> &CryptolValue {
+        &self.cryptol
+    }
+
+    /// This function returns the value the expression was expected to
+    /// have.
+    #[must_use]
+    pub fn expected(&self) -> &CryptolValue {
+        &self.expected
+    }
+
+    /// This function returns where the two values differ, with the
+    /// value of the expression on the left.
+    #[must_use]
+    pub fn differences(&self) -> &[Difference] {
+        &self.differences
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "assertion `cryptol == rust` failed")?;
+        writeln!(f, "  expression: {}", self.expression)?;
+        writeln!(f, "     cryptol: {:?}", self.cryptol)?;
+        writeln!(f, "        rust: {:?}", self.expected)?;
+        write!(f, " differences (cryptol vs rust):")?;
+        for difference in &self.differences {
+            write!(f, "\n  {difference}")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Mismatch {}
+
+impl CryptolClient {
+    /// This function evaluates `expression` and compares its value with
+    /// `expected`. See [`assert_cryptol_eq!`](crate::assert_cryptol_eq).
+    ///
+    /// # Errors
+    ///
+    /// The function returns a [`Mismatch`] if the values differ, or an
+    /// error if the expression cannot be evaluated or its value cannot
+    /// be decoded.
+    pub fn check_eq(&mut self, expression: &str, expected: &CryptolValue) -> Result<()> {
+        let cryptol = self.evaluate(expression)?.decode()?;
+        let differences = cryptol.diff(expected);
+        if differences.is_empty() {
+            return Ok(());
+        }
+        Err(Box::new(Mismatch {
+            expression: expression.to_string(),
+            cryptol,
+            expected: expected.clone(),
+            differences,
+        }))
+    }
+}
@@ -0,0 +1,116 @@
+//! # Differential testing
+//!
+//! With the `proptest` feature, [`assert_agrees`] checks that a Rust
+//! function agrees with a Cryptol function on inputs generated by a
+//! `proptest` strategy. Each input is converted with
+//! [`ToCryptolValue`], passed to the Cryptol function, and the result
+//! converted back with [`FromCryptolValue`] and compared with the
+//! result of the Rust function. When they differ, the input is shrunk
+//! and the test panics with the smallest input found and both results.
+//!
+//! ```no_run
+//! use cryptol_client::differential::assert_agrees;
+//! use cryptol_client::CryptolClient;
+//! use proptest::prelude::*;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! assert_agrees(
+//!     &mut cryptol_client,
+//!     "reverse",
+//!     (proptest::collection::vec(any::<u8>(), 0..16),),
+//!     |(xs,)| xs.iter().rev().copied().collect::<Vec<u8>>(),
+//! );
+//! ```
+//!
+//! The number of cases is taken from the `PROPTEST_CASES` environment
+//! variable, as for `proptest!` tests, and defaults to 256.
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+
+use crate::convert::{FromCryptolValue, ToCryptolValue};
+use crate::value::CryptolValue;
+use crate::CryptolClient;
+
+/// The arguments of a Cryptol function, as a tuple of values that
+/// convert with [`ToCryptolValue`], one per argument.
+pub trait Arguments: Debug {
+    /// This function converts each argument.
+    fn to_arguments(&self) -> Vec<CryptolValue>;
+}
+
+macro_rules! arguments {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: ToCryptolValue + Debug),*> Arguments for ($($t,)*) {
+            fn to_arguments(&self) -> Vec<CryptolValue> {
+                vec![$(self.$i.to_cryptol_value()),*]
+            }
+        }
+    };
+}
+
+arguments!(A 0);
+arguments!(A 0, B 1);
+arguments!(A 0, B 1, C 2);
+arguments!(A 0, B 1, C 2, D 3);
+arguments!(A 0, B 1, C 2, D 3, E 4);
+arguments!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// This function checks that calling the Cryptol function `function`
+/// of the loaded module with arguments generated by `strategy` gives
+/// the same result as calling `rust` with them.
+///
+/// # Panics
+///
+/// The function panics if `function` cannot be bound, or if there is
+/// an input on which the functions disagree or the call fails. The
+/// panic message shows the smallest such input found, the Cryptol
+/// expression that was evaluated, and both results.
+pub fn assert_agrees<S, R, F>(client: &mut CryptolClient, function: &str, strategy: S, rust: F)
+where
+    S: Strategy,
+    S::Value: Arguments,
+    R: FromCryptolValue + PartialEq + Debug,
+    F: Fn(&S::Value) -> R,
+{
+    // The test runner only takes `Fn` closures.
+    let handle = RefCell::new(
+        client
+            .bind(function)
+            .unwrap_or_else(|e| panic!("cannot bind {function}: {e}")),
+    );
+
+    // Failures cannot be persisted, since there is no test source file
+    // to record them next to.
+    let config = Config {
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let result = TestRunner::new(config).run(&strategy, |input| {
+        let arguments = input.to_arguments();
+        let call = std::iter::once(function.to_string())
+            .chain(arguments.iter().map(CryptolValue::to_expression))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let expected = rust(&input);
+        let actual = handle.borrow_mut().invoke_as::<R>(&arguments);
+        match actual {
+            Ok(actual) if actual == expected => Ok(()),
+            Ok(actual) => Err(TestCaseError::fail(format!(
+                "{call} = {actual:?}, but the Rust function returned {expected:?}"
+            ))),
+            Err(e) => Err(TestCaseError::fail(format!("{call} failed: {e}"))),
+        }
+    });
+
+    match result {
+        Ok(()) => {}
+        Err(TestError::Fail(reason, input)) => {
+            panic!("{function} disagrees with the Rust function on {input:?}: {reason}")
+        }
+        Err(TestError::Abort(reason)) => panic!("testing {function} aborted: {reason}"),
+    }
+}
@@ -0,0 +1,118 @@
+//! # Server discovery
+//!
+//! When no URL of `cryptol-remote-api` is given, a client can look for
+//! a server at a list of candidate URLs and connect to the first that
+//! accepts connections. The candidates are given with
+//! [`CryptolClientBuilder::discover`](crate::CryptolClientBuilder::discover)
+//! or the `discover` key of a configuration file. The port of a
+//! candidate may be a range, e.g. `http://localhost:49352-49360`, whose
+//! ports are tried in turn.
+//!
+//! ```no_run
+//! use cryptol_client::discover::DEFAULT_URLS;
+//! use cryptol_client::CryptolClientBuilder;
+//!
+//! // Uses `CRYPTOL_SERVER_URL` if set, and looks around otherwise.
+//! let cryptol_client = CryptolClientBuilder::new()
+//!     .discover(DEFAULT_URLS.iter().copied())
+//!     .connect();
+//! ```
+//!
+//! Each candidate tried is logged at debug level, and the server found
+//! at info level.
+
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// The URLs `cryptol-remote-api` is commonly found at: the port used
+/// throughout its documentation, and the default port of many HTTP
+/// servers.
+pub const DEFAULT_URLS: &[&str] = &["http://localhost:49352", "http://localhost:8080"];
+
+/// How long each candidate is given to accept a connection.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// This function returns the first of `candidates` at which a server
+/// accepts connections.
+///
+/// # Errors
+///
+/// The function returns `Error::Transport`, listing the candidates
+/// tried, if no server is found.
+pub fn discover<S: AsRef<str>>(candidates: &[S]) -> Result<String, Error> {
+    let mut tried = Vec::new();
+    for candidate in candidates {
+        for url in expand(candidate.as_ref()) {
+            if responds(&url) {
+                tracing::info!(url, "discovered cryptol-remote-api");
+                return Ok(url);
+            }
+            tried.push(url);
+        }
+    }
+    Err(Error::Transport(
+        format!("no cryptol-remote-api found; tried {}", tried.join(", ")).into(),
+    ))
+}
+
+/// This function expands a port range in `url` into one URL per port.
+/// A URL without a range is returned as it is.
+fn expand(url: &str) -> Vec<String> {
+    let authority_start = url.find("://").map_or(0, |i| i + 3);
+    let authority_end = url[authority_start..]
+        .find('/')
+        .map_or(url.len(), |i| authority_start + i);
+    let authority = &url[authority_start..authority_end];
+    let range = authority.rsplit_once(':').and_then(|(host, ports)| {
+        let (first, last) = ports.split_once('-')?;
+        Some((host, first.parse::<u16>().ok()?, last.parse::<u16>().ok()?))
+    });
+    match range {
+        Some((host, first, last)) => (first..=last)
+            .map(|port| {
+                format!(
+                    "{}{host}:{port}{}",
+                    &url[..authority_start],
+                    &url[authority_end..]
+                )
+            })
+            .collect(),
+        None => vec![url.to_string()],
+    }
+}
+
+/// This function returns whether a server accepts connections at the
+/// host and port of `url`.
+fn responds(url: &str) -> bool {
+    let Ok(uri) = url.parse::<hyper::Uri>() else {
+        tracing::debug!(url, "skipping candidate: not a URL");
+        return false;
+    };
+    let Some(host) = uri.host() else {
+        tracing::debug!(url, "skipping candidate: no host");
+        return false;
+    };
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("https") {
+            443
+        } else {
+            80
+        });
+    let addrs = match (host.trim_matches(['[', ']']), port).to_socket_addrs() {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            tracing::debug!(url, error = %e, "skipping candidate: cannot resolve host");
+            return false;
+        }
+    };
+    for addr in addrs {
+        if TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok() {
+            return true;
+        }
+    }
+    tracing::debug!(url, "no server at candidate");
+    false
+}
@@ -0,0 +1,288 @@
+//! # Disk caching
+//!
+//! A `DiskCache` memoizes the responses of `call` and `evaluate` in a
+//! file, so that they survive the process. Unlike a
+//! [`Cache`](crate::cache::Cache), it cannot key requests by the token
+//! of the state they are issued from, since tokens do not outlive the
+//! server. Requests are keyed instead by what determines their answer:
+//!
+//! - the method and its parameters, other than the state;
+//! - the version of `cryptol-remote-api`, as reported by its `version`
+//!   method;
+//! - the modules loaded in the session, in order, with the fingerprints
+//!   (SHA-256 hashes) of their source files and those of the modules
+//!   they import, as reported by its `file-deps` method.
+//!
+//! Editing a module, or upgrading the server, therefore makes the cached
+//! responses of its calls unreachable rather than wrong. The version
+//! and fingerprints are requested once per state. A server without
+//! these methods is not cached for.
+//!
+//! This is useful for benchmarks and differential tests that evaluate
+//! the same specification on the same inputs every run.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use cryptol_client::disk_cache::DiskCache;
+//! use cryptol_client::CryptolClient;
+//!
+//! let cache = Arc::new(DiskCache::open("target/cryptol-cache.jsonl").unwrap());
+//! let mut cryptol_client = CryptolClient::builder()
+//!     .disk_cache(cache.clone())
+//!     .connect()
+//!     .unwrap();
+//! cryptol_client.load_module("SuiteB").unwrap();
+//! cryptol_client.call("sha384", &["0x0001"]).unwrap();
+//! println!("{} responses were reused", cache.hits());
+//! ```
+//!
+//! The file holds one JSON object per line, with the key and the
+//! response of a request, and is only ever appended to. Delete it to
+//! empty the cache.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::cache::CACHEABLE;
+use crate::{CryptolClient, CryptolResult, Result};
+
+/// A line of the cache file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    response: CryptolResult,
+}
+
+/// A memoization layer for responses from `cryptol-remote-api` that is
+/// kept in a file. It may be shared between clients, and the file
+/// between processes. Only successful responses are cached.
+#[derive(Debug)]
+pub struct DiskCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, CryptolResult>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl DiskCache {
+    /// This function opens the cache kept in the file at `path`,
+    /// creating the file if it does not exist. Lines that cannot be
+    /// read, e.g. one cut short by a crash, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the file cannot be created or
+    /// read.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        // Make sure the file can be appended to before relying on it.
+        OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut entries = HashMap::new();
+        for line in fs::read_to_string(&path)?.lines() {
+            match serde_json::from_str::<Entry>(line) {
+                Ok(entry) => {
+                    entries.insert(entry.key, entry.response);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "skipping cache entry");
+                }
+            }
+        }
+        Ok(DiskCache {
+            path,
+            entries: Mutex::new(entries),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    /// This function returns the path of the cache file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// This function returns the number of cached responses.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the cache was poisoned by a panic in
+    /// another thread.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// This function returns whether no responses are cached.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the cache was poisoned by a panic in
+    /// another thread.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This function returns the number of requests that were
+    /// answered from the cache.
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// This function returns the number of cacheable requests that
+    /// had to be sent to the server.
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// This function returns the key of a request issued in
+    /// `context`, or `None` if the request must not be cached.
+    fn key(method: &str, params: &Map<String, Value>, context: &str) -> Option<String> {
+        if !CACHEABLE.contains(&method) {
+            return None;
+        }
+        let mut params = params.clone();
+        params.remove("state");
+        serde_json::to_string(&json!({
+            "method": method,
+            "params": params,
+            "context": context,
+        }))
+        .ok()
+    }
+
+    /// This function looks up a request, counting a hit or a miss.
+    fn get(&self, key: &str) -> Option<CryptolResult> {
+        let entry = self.entries.lock().unwrap().get(key).cloned();
+        let counter = if entry.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        entry
+    }
+
+    /// This function records the response to a request, in memory and
+    /// in the file.
+    fn insert(&self, key: String, response: CryptolResult) -> io::Result<()> {
+        let entry = Entry { key, response };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        let mut entries = self.entries.lock().unwrap();
+        // The line is written at once so that processes sharing the
+        // file do not interleave their entries.
+        File::options()
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        entries.insert(entry.key, entry.response);
+        Ok(())
+    }
+}
+
+impl CryptolClient {
+    /// This function sends a request, answering it from the disk
+    /// cache if one is installed and has its response. A response from
+    /// the cache leaves the client in its current state, which is
+    /// where a query like `call` would leave it.
+    pub(crate) fn send_through_disk_cache(
+        &mut self,
+        action: &str,
+        params: &Map<String, Value>,
+    ) -> Result<CryptolResult> {
+        let Some(cache) = self.disk_cache.clone() else {
+            return self.send(action, params);
+        };
+        let key = if CACHEABLE.contains(&action) {
+            self.cache_context()
+                .and_then(|context| DiskCache::key(action, params, &context))
+        } else {
+            None
+        };
+        let Some(key) = key else {
+            return self.send(action, params);
+        };
+
+        if let Some(mut response) = cache.get(&key) {
+            tracing::debug!(method = action, "answered from disk cache");
+            response.state = self.state_key();
+            response.elapsed = Duration::ZERO;
+            return Ok(response);
+        }
+        let response = self.send(action, params)?;
+        if let Err(e) = cache.insert(key, response.clone()) {
+            tracing::warn!(path = %cache.path().display(), error = %e, "cannot write cache entry");
+        }
+        Ok(response)
+    }
+
+    /// This function returns the version of the server and the
+    /// fingerprints of the files of the loaded modules, rendered as a
+    /// string, or `None` if the server cannot report them. It is
+    /// requested once per state.
+    fn cache_context(&mut self) -> Option<String> {
+        let state = self.session.state()?.to_string();
+        if let Some(context) = self.cache_contexts.get(&state) {
+            return context.clone();
+        }
+        let context = match self.request_cache_context() {
+            Ok(context) => Some(context),
+            Err(e) => {
+                tracing::warn!(error = %e, "not caching on disk: the server cannot report its version and module fingerprints");
+                None
+            }
+        };
+        self.cache_contexts.insert(state, context.clone());
+        context
+    }
+
+    /// This function asks the server for its version and the
+    /// dependencies of the loaded modules and their imports.
+    fn request_cache_context(&mut self) -> Result<String> {
+        let version = self
+            .send("version", &self.session.params())?
+            .answer
+            .map(|answer| serde_json::from_str::<Value>(answer.get()))
+            .transpose()?;
+
+        let mut files = BTreeMap::new();
+        let mut pending = self.modules.clone();
+        while let Some(module) = pending.pop() {
+            if files.contains_key(&module) {
+                continue;
+            }
+            let mut params = self.session.params();
+            params.insert("name".into(), json!(module));
+            params.insert("is-file".into(), json!(false));
+            let mut deps: Map<String, Value> = match self.send("file-deps", &params)?.answer {
+                Some(answer) => serde_json::from_str(answer.get())?,
+                None => Map::new(),
+            };
+            // Where the checkout lives does not change the module.
+            deps.remove("source");
+            if let Some(Value::Array(imports)) = deps.get("imports") {
+                pending.extend(imports.iter().filter_map(Value::as_str).map(String::from));
+            }
+            files.insert(module, deps);
+        }
+
+        Ok(serde_json::to_string(&json!({
+            "version": version,
+            "modules": self.modules,
+            "files": files,
+        }))?)
+    }
+}
@@ -0,0 +1,253 @@
+//! # Errors
+//!
+//! This module defines the errors reported by `cryptol_client` itself
+//! and the structure of error responses from `cryptol-remote-api`.
+//!
+//! Functions of `CryptolClient` return a boxed
+//! `std::error::Error`. Errors originating in this crate can be
+//! recovered with `downcast_ref::<Error>()`.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::trace::{self, TraceEvent};
+
+/// An error reported by `cryptol_client`.
+#[derive(Debug)]
+pub enum Error {
+    /// `cryptol-remote-api` answered a request with an error.
+    Server(Box<CryptolError>),
+    /// A response from `cryptol-remote-api` could not be decoded.
+    Decode(String),
+    /// No response arrived before the request timed out. What the
+    /// server had written while handling the request is kept if it
+    /// could be retrieved, see
+    /// [`HttpTransportBuilder::interrupt_on_timeout`](crate::transport::HttpTransportBuilder::interrupt_on_timeout).
+    Timeout(Option<PartialOutput>),
+    /// The response is larger than the transport accepts, e.g. because
+    /// an expression evaluated to far more data than expected.
+    ResponseTooLarge {
+        /// The maximum size of a response, in bytes.
+        limit: usize,
+    },
+    /// The request could not be delivered or its response could not
+    /// be received.
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// The server's version is not one the client is known to work
+    /// with. See [`compat`](crate::compat).
+    IncompatibleVersion {
+        /// The protocol version the server reported, or `None` if it
+        /// does not report one.
+        version: Option<String>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Server(e) => write!(f, "{e}"),
+            Error::Decode(msg) => write!(f, "failed to decode response: {msg}"),
+            Error::Timeout(_) => write!(f, "request timed out"),
+            Error::ResponseTooLarge { limit } => {
+                write!(f, "response is larger than the limit of {limit} bytes")
+            }
+            Error::Transport(e) => write!(f, "transport error: {e}"),
+            Error::IncompatibleVersion {
+                version: Some(version),
+            } => write!(
+                f,
+                "cryptol-remote-api version {version} is not known to be compatible"
+            ),
+            Error::IncompatibleVersion { version: None } => {
+                write!(f, "cryptol-remote-api does not report its version")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Server(e) => Some(e),
+            Error::Transport(e) => Some(e.as_ref()),
+            Error::Decode(_)
+            | Error::Timeout(_)
+            | Error::ResponseTooLarge { .. }
+            | Error::IncompatibleVersion { .. } => None,
+        }
+    }
+}
+
+impl From<CryptolError> for Error {
+    fn from(e: CryptolError) -> Self {
+        Error::Server(Box::new(e))
+    }
+}
+
+/// This structure represents the JSON blob returned by
+/// `cryptol-remote-api` on error.
+///
+/// Example JSON blob:
+///   `{"code":20500,"data":{"data":{"path":["client","//.cryptol","/usr/local/share/cryptol"],"source":"Floataboat","warnings":[]},"stderr":"","stdout":""},"message":"[error] Could not find module NoModule\nSearched paths:\n    //.cryptol\n    /usr/local/share/cryptol\nSet the CRYPTOLPATH environment variable to search more directories"}`
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptolError {
+    code: i64,
+    #[serde(default, deserialize_with = "lenient")]
+    data: CryptolErrorData,
+    message: String,
+    /// The expression of the request that failed, kept to render the
+    /// error with. See [`diagnostic`](crate::diagnostic).
+    #[serde(skip)]
+    pub(crate) expression: Option<String>,
+}
+
+/// The `data` of a `CryptolError`: the error-specific details along
+/// with anything the server printed while handling the request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CryptolErrorData {
+    #[serde(default, deserialize_with = "lenient")]
+    data: CryptolDataData,
+    #[serde(default, deserialize_with = "lenient")]
+    stderr: String,
+    #[serde(default, deserialize_with = "lenient")]
+    stdout: String,
+}
+
+/// The error-specific details of a `CryptolError`. Which fields are
+/// present depends on the error; for example, a failure to find a
+/// module reports the `path` that was searched.
+///
+/// Details of a shape the client does not expect, e.g. a `null`
+/// `path`, are left empty rather than hiding the error behind one
+/// about decoding it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CryptolDataData {
+    #[serde(default, deserialize_with = "lenient")]
+    path: Vec<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    source: String,
+    #[serde(default, deserialize_with = "lenient")]
+    warnings: Vec<Option<serde_json::Value>>,
+}
+
+/// This function deserializes a detail of an error, or its default if
+/// the detail does not have the expected shape.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned + Default,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(T::deserialize(value).unwrap_or_default())
+}
+
+impl CryptolError {
+    /// This function creates an error without details, as a server
+    /// would report it.
+    pub(crate) fn new(code: i64, message: &str) -> Self {
+        CryptolError {
+            code,
+            data: CryptolErrorData::default(),
+            message: message.to_string(),
+            expression: None,
+        }
+    }
+
+    /// This function returns the JSON-RPC error code.
+    #[must_use]
+    pub fn code(&self) -> i64 {
+        self.code
+    }
+
+    /// This function returns the error message reported by
+    /// `cryptol-remote-api`.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// This function returns the directories that were searched for
+    /// a module that could not be found. It is empty for other
+    /// errors.
+    #[must_use]
+    pub fn search_path(&self) -> &[String] {
+        &self.data.data.path
+    }
+
+    /// This function returns what the server wrote to standard output
+    /// while handling the failed request.
+    #[must_use]
+    pub fn stdout(&self) -> &str {
+        &self.data.stdout
+    }
+
+    /// This function returns what the server wrote to standard error
+    /// while handling the failed request.
+    #[must_use]
+    pub fn stderr(&self) -> &str {
+        &self.data.stderr
+    }
+
+    /// This function returns what Cryptol's `trace` and `traceVal`
+    /// printed before the request failed, e.g. how far an interrupted
+    /// evaluation got. See [`trace`].
+    #[must_use]
+    pub fn trace(&self) -> Vec<TraceEvent> {
+        trace::events(&self.data.stdout)
+    }
+}
+
+impl fmt::Display for CryptolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for CryptolError {}
+
+/// What the server wrote while handling a request that did not
+/// complete, kept in an [`Error::Timeout`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartialOutput {
+    stdout: String,
+    stderr: String,
+}
+
+impl PartialOutput {
+    /// This function returns the output kept in a JSON-RPC `response`
+    /// that arrived too late, whether it is an answer or an error, or
+    /// `None` if it has none.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_response(response: &serde_json::Value) -> Option<Self> {
+        let output = response
+            .get("result")
+            .or_else(|| response.get("error")?.get("data"))?;
+        Some(PartialOutput {
+            stdout: output.get("stdout")?.as_str()?.to_string(),
+            stderr: output.get("stderr")?.as_str()?.to_string(),
+        })
+    }
+
+    /// This function returns what the server wrote to standard output
+    /// before the request timed out.
+    #[must_use]
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// This function returns what the server wrote to standard error
+    /// before the request timed out.
+    #[must_use]
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+
+    /// This function returns what Cryptol's `trace` and `traceVal`
+    /// printed before the request timed out.
+    #[must_use]
+    pub fn trace(&self) -> Vec<TraceEvent> {
+        trace::events(&self.stdout)
+    }
+}
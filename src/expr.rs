@@ -0,0 +1,282 @@
+//! # Expressions
+//!
+//! `CryptolExpr` builds Cryptol expressions programmatically instead of
+//! by string concatenation. Names are checked and subexpressions are
+//! parenthesized as needed when the expression is rendered.
+//!
+//! ```
+//! use cryptol_client::expr::CryptolExpr;
+//!
+//! let expr = CryptolExpr::name("SuiteB::sha384")
+//!     .apply([CryptolExpr::name("join").apply([CryptolExpr::name("msg")])])
+//!     .bind("msg", CryptolExpr::value("abc"));
+//! assert_eq!(
+//!     expr.render().unwrap(),
+//!     "(SuiteB::sha384 (join msg) where { msg = [0x61, 0x62, 0x63] })"
+//! );
+//! ```
+
+use crate::convert::{string_literal, ToCryptolValue};
+use crate::error::Error;
+use crate::value::CryptolValue;
+
+/// A Cryptol expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptolExpr {
+    /// A possibly qualified name, e.g. `SuiteB::sha384`, or an
+    /// operator such as `+`.
+    Name(String),
+    /// A literal value.
+    Value(CryptolValue),
+    /// Cryptol source, e.g. `"join msg"`, written in parentheses. It
+    /// is not checked.
+    Source(String),
+    /// A possibly qualified name with explicit type arguments, e.g.
+    /// ``take`{n = 4}``. The types are written in Cryptol syntax and not
+    /// checked.
+    Instantiate(String, Vec<(String, String)>),
+    /// A function applied to arguments.
+    Apply(Box<CryptolExpr>, Vec<CryptolExpr>),
+    /// A tuple.
+    Tuple(Vec<CryptolExpr>),
+    /// A sequence literal.
+    Sequence(Vec<CryptolExpr>),
+    /// An expression annotated with a type, e.g. `(x : [8])`. The type
+    /// is written in Cryptol syntax and not checked.
+    Typed(Box<CryptolExpr>, String),
+    /// An expression with local definitions, i.e. `e where { x = ... }`.
+    Where(Box<CryptolExpr>, Vec<(String, CryptolExpr)>),
+}
+
+impl CryptolExpr {
+    /// This function returns a reference to the given name.
+    pub fn name(name: impl Into<String>) -> Self {
+        CryptolExpr::Name(name.into())
+    }
+
+    /// This function returns the given Cryptol source as an
+    /// expression.
+    pub fn source(source: impl Into<String>) -> Self {
+        CryptolExpr::Source(source.into())
+    }
+
+    /// This function returns the given string as an `[n][8]`, quoted
+    /// so that any string is safe to embed. See
+    /// [`string_literal`].
+    #[must_use]
+    pub fn string(s: &str) -> Self {
+        CryptolExpr::Source(string_literal(s))
+    }
+
+    /// This function returns a reference to the given name with the
+    /// given type arguments, each a type parameter of the named
+    /// function and its type, e.g. `("n", "8")`.
+    pub fn instantiate<N: Into<String>, T: Into<String>>(
+        name: impl Into<String>,
+        types: impl IntoIterator<Item = (N, T)>,
+    ) -> Self {
+        CryptolExpr::Instantiate(
+            name.into(),
+            types
+                .into_iter()
+                .map(|(param, ty)| (param.into(), ty.into()))
+                .collect(),
+        )
+    }
+
+    /// This function returns a literal for the given value.
+    pub fn value<T: ToCryptolValue + ?Sized>(value: &T) -> Self {
+        CryptolExpr::Value(value.to_cryptol_value())
+    }
+
+    /// This function returns a tuple of the given expressions.
+    pub fn tuple(elements: impl IntoIterator<Item = CryptolExpr>) -> Self {
+        CryptolExpr::Tuple(elements.into_iter().collect())
+    }
+
+    /// This function returns a sequence literal of the given
+    /// expressions.
+    pub fn sequence(elements: impl IntoIterator<Item = CryptolExpr>) -> Self {
+        CryptolExpr::Sequence(elements.into_iter().collect())
+    }
+
+    /// This function applies `self` to the given arguments.
+    #[must_use]
+    pub fn apply(self, arguments: impl IntoIterator<Item = CryptolExpr>) -> Self {
+        CryptolExpr::Apply(Box::new(self), arguments.into_iter().collect())
+    }
+
+    /// This function annotates `self` with the given type.
+    #[must_use]
+    pub fn typed(self, ty: impl Into<String>) -> Self {
+        CryptolExpr::Typed(Box::new(self), ty.into())
+    }
+
+    /// This function adds the local definition `name = definition` to
+    /// `self`, in a `where` block.
+    #[must_use]
+    pub fn bind(self, name: impl Into<String>, definition: CryptolExpr) -> Self {
+        match self {
+            CryptolExpr::Where(body, mut bindings) => {
+                bindings.push((name.into(), definition));
+                CryptolExpr::Where(body, bindings)
+            }
+            body => CryptolExpr::Where(Box::new(body), vec![(name.into(), definition)]),
+        }
+    }
+
+    /// This function renders the expression in Cryptol's concrete
+    /// syntax.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if a name is not a valid
+    /// Cryptol identifier or operator, if a local definition is
+    /// qualified, or if type arguments are given to an operator.
+    pub fn render(&self) -> Result<String, Error> {
+        let mut out = String::new();
+        self.write(&mut out)?;
+        Ok(out)
+    }
+
+    fn write(&self, out: &mut String) -> Result<(), Error> {
+        let list = |out: &mut String, open, close, elements: &[CryptolExpr]| {
+            out.push(open);
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                element.write(out)?;
+            }
+            out.push(close);
+            Ok(())
+        };
+
+        match self {
+            CryptolExpr::Name(name) => write_name(name, out),
+            CryptolExpr::Value(value) => {
+                out.push_str(&value.to_expression());
+                Ok(())
+            }
+            CryptolExpr::Source(source) => {
+                out.push('(');
+                out.push_str(source);
+                out.push(')');
+                Ok(())
+            }
+            CryptolExpr::Instantiate(name, types) => {
+                if is_operator(name.rsplit("::").next().unwrap_or(name)) {
+                    return Err(Error::Decode(format!(
+                        "type arguments cannot be given to {name}"
+                    )));
+                }
+                write_name(name, out)?;
+                out.push_str("`{");
+                for (i, (param, ty)) in types.iter().enumerate() {
+                    if !is_identifier(param) {
+                        return Err(Error::Decode(format!("{param:?} is not a type parameter")));
+                    }
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    out.push_str(param);
+                    out.push_str(" = ");
+                    out.push_str(ty);
+                }
+                out.push('}');
+                Ok(())
+            }
+            CryptolExpr::Apply(function, arguments) => {
+                function.write_atom(out)?;
+                for argument in arguments {
+                    out.push(' ');
+                    argument.write_atom(out)?;
+                }
+                Ok(())
+            }
+            CryptolExpr::Tuple(elements) => list(out, '(', ')', elements),
+            CryptolExpr::Sequence(elements) => list(out, '[', ']', elements),
+            CryptolExpr::Typed(expr, ty) => {
+                out.push('(');
+                expr.write(out)?;
+                out.push_str(" : ");
+                out.push_str(ty);
+                out.push(')');
+                Ok(())
+            }
+            CryptolExpr::Where(body, bindings) => {
+                out.push('(');
+                body.write(out)?;
+                out.push_str(" where {");
+                for (i, (name, definition)) in bindings.iter().enumerate() {
+                    if !is_identifier(name) {
+                        return Err(Error::Decode(format!("{name:?} cannot be defined locally")));
+                    }
+                    out.push_str(if i > 0 { "; " } else { " " });
+                    out.push_str(name);
+                    out.push_str(" = ");
+                    definition.write(out)?;
+                }
+                out.push_str(" })");
+                Ok(())
+            }
+        }
+    }
+
+    /// This function writes the expression so that it can be used as a
+    /// function or argument. Only applications need parentheses, since
+    /// type annotations and `where` blocks are always written with
+    /// them.
+    fn write_atom(&self, out: &mut String) -> Result<(), Error> {
+        if let CryptolExpr::Apply(..) = self {
+            out.push('(');
+            self.write(out)?;
+            out.push(')');
+            Ok(())
+        } else {
+            self.write(out)
+        }
+    }
+}
+
+impl From<CryptolValue> for CryptolExpr {
+    fn from(value: CryptolValue) -> Self {
+        CryptolExpr::Value(value)
+    }
+}
+
+/// This function returns whether `name` is an unqualified identifier.
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+}
+
+/// This function returns whether `name` is an operator, e.g. `+` or
+/// `@@`.
+fn is_operator(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| "!#$%&*+-./<=>?@\\^|~".contains(c))
+}
+
+/// This function writes a possibly qualified name, wrapping operators
+/// in parentheses so they can be used like functions.
+fn write_name(name: &str, out: &mut String) -> Result<(), Error> {
+    let (module, base) = match name.rsplit_once("::") {
+        Some((module, base)) => (Some(module), base),
+        None => (None, name),
+    };
+    let valid = module.is_none_or(|m| m.split("::").all(is_identifier))
+        && (is_identifier(base) || is_operator(base));
+    if !valid {
+        return Err(Error::Decode(format!("{name:?} is not a Cryptol name")));
+    }
+
+    if is_operator(base) {
+        out.push('(');
+        out.push_str(name);
+        out.push(')');
+    } else {
+        out.push_str(name);
+    }
+    Ok(())
+}
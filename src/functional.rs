@@ -0,0 +1,154 @@
+//! # Persistent sessions
+//!
+//! `cryptol-remote-api` never changes a state once it has produced it:
+//! loading a module from a state produces a new state and leaves the
+//! old one as it was. A [`Session`] mirrors this model on the client.
+//! Its operations take `&self`; queries return their answer, and
+//! commands return a new `Session` in the state they produced. Any
+//! number of historical sessions can be kept, and a session can be
+//! shared between threads without a lock.
+//!
+//! ```no_run
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! let prelude = cryptol_client.session().unwrap();
+//! let suite_b = prelude.load_module("SuiteB").unwrap();
+//!
+//! // Both sessions stay usable.
+//! suite_b.call("sha384", &["0x0001"]).unwrap();
+//! prelude.evaluate("reverse [1, 2, 3] : [3][8]").unwrap();
+//! ```
+
+use crate::check::{CheckResult, TestCount};
+use crate::prove::ProveResult;
+use crate::types::TypeSchema;
+use crate::value::CryptolValue;
+use crate::{Answer, Args, CryptolClient, Result};
+
+/// A client in a fixed state of `cryptol-remote-api`, created with
+/// [`CryptolClient::session`]. See the [module
+/// documentation](crate::functional).
+///
+/// A session has the settings, caches and transport of the client it
+/// was created from, and requests through it are made as the client
+/// would make them.
+#[derive(Debug, Clone)]
+pub struct Session {
+    client: CryptolClient,
+}
+
+impl CryptolClient {
+    /// This function returns a session in the current state of the
+    /// client. The client itself is unaffected by what is done with
+    /// the session.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the initial modules of a lazy
+    /// connection cannot be loaded.
+    pub fn session(&mut self) -> Result<Session> {
+        self.load_pending_modules()?;
+        Ok(Session {
+            client: self.clone(),
+        })
+    }
+}
+
+impl Session {
+    /// This function returns the state token of the session, or `None`
+    /// if no request has been made to the server yet.
+    #[must_use]
+    pub fn state(&self) -> Option<&str> {
+        self.client.session.state()
+    }
+
+    /// This function returns the modules loaded in the session. See
+    /// [`CryptolClient::loaded_modules`].
+    #[must_use]
+    pub fn loaded_modules(&self) -> &[String] {
+        self.client.loaded_modules()
+    }
+
+    /// This function returns a client that continues from the state
+    /// of the session.
+    #[must_use]
+    pub fn into_client(self) -> CryptolClient {
+        self.client
+    }
+
+    /// This function returns a session in which `module` has been
+    /// loaded. See [`CryptolClient::load_module`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::load_module`] does.
+    pub fn load_module(&self, module: &str) -> Result<Session> {
+        let mut client = self.client.clone();
+        client.load_module(module)?;
+        Ok(Session { client })
+    }
+
+    /// This function returns a session in which `name` is defined as
+    /// the value of `expression`, together with the value. See
+    /// [`CryptolClient::define`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::define`] does.
+    pub fn define(&self, name: &str, expression: &str) -> Result<(Session, CryptolValue)> {
+        let mut client = self.client.clone();
+        let value = client.define(name, expression)?;
+        Ok((Session { client }, value))
+    }
+
+    /// This function calls the given function in the state of the
+    /// session. See [`CryptolClient::call`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::call`] does.
+    pub fn call(&self, function: &str, arguments: impl Into<Args>) -> Result<Answer> {
+        self.client.clone().call(function, arguments)
+    }
+
+    /// This function evaluates the given expression in the state of
+    /// the session. See [`CryptolClient::evaluate`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::evaluate`] does.
+    pub fn evaluate(&self, expression: &str) -> Result<Answer> {
+        self.client.clone().evaluate(expression)
+    }
+
+    /// This function returns the type of the given expression in the
+    /// state of the session. See [`CryptolClient::type_of`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::type_of`] does.
+    pub fn type_of(&self, expression: &str) -> Result<TypeSchema> {
+        self.client.clone().type_of(expression)
+    }
+
+    /// This function tests the given property in the state of the
+    /// session. See [`CryptolClient::check`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::check`] does.
+    pub fn check(&self, property: &str, tests: TestCount) -> Result<CheckResult> {
+        self.client.clone().check(property, tests)
+    }
+
+    /// This function proves the given property in the state of the
+    /// session. See [`CryptolClient::prove`].
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`CryptolClient::prove`] does.
+    pub fn prove(&self, property: &str) -> Result<ProveResult> {
+        self.client.clone().prove(property)
+    }
+}
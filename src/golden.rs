@@ -0,0 +1,177 @@
+//! # Golden files
+//!
+//! Helpers for regression tests that compare answers from
+//! `cryptol-remote-api` against previously recorded golden files.
+//!
+//! An answer is recorded as canonical JSON: its type schema, type
+//! string, and value, pretty-printed with object keys sorted, so that
+//! golden files are stable and diff well under version control. When
+//! the environment variable `CRYPTOL_UPDATE_GOLDEN` is set, golden
+//! files are (re)written instead of compared.
+//!
+//! ```no_run
+//! use cryptol_client::{golden, CryptolClient};
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! let answer = cryptol_client.call("reverse", &["[1, 2, 3, 4]"]).unwrap();
+//! golden::assert_golden("tests/golden/reverse.json", &answer);
+//! ```
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Result;
+
+/// The environment variable that switches from comparing against
+/// golden files to rewriting them.
+pub const UPDATE_VAR: &str = "CRYPTOL_UPDATE_GOLDEN";
+
+/// This error reports that a golden file does not match.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    path: PathBuf,
+    expected: String,
+    actual: String,
+}
+
+impl Mismatch {
+    /// This function returns the path of the golden file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// This function returns the contents of the golden file.
+    #[must_use]
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// This function returns the canonical JSON that was compared
+    /// against the golden file.
+    #[must_use]
+    pub fn actual(&self) -> &str {
+        &self.actual
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "golden file {} does not match (set {UPDATE_VAR}=1 to update it)",
+            self.path.display()
+        )?;
+
+        // Show the first line that differs.
+        let mut expected = self.expected.lines();
+        let mut actual = self.actual.lines();
+        for line in 1.. {
+            match (expected.next(), actual.next()) {
+                (None, None) => break,
+                (e, a) if e == a => {}
+                (e, a) => {
+                    writeln!(f, "first difference at line {line}:")?;
+                    writeln!(f, "  expected: {}", e.unwrap_or("<end of file>"))?;
+                    write!(f, "  actual:   {}", a.unwrap_or("<end of file>"))?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// This function renders `value` (typically an `Answer` or a
+/// collection of them) as canonical JSON: pretty-printed, with object
+/// keys sorted, and ending in a newline.
+///
+/// # Errors
+///
+/// The function returns an error if `value` cannot be serialized as
+/// JSON.
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let value = canonicalize(serde_json::to_value(value)?);
+    let mut json = serde_json::to_string_pretty(&value)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// This function compares `value` against the golden file at `path`.
+/// When `CRYPTOL_UPDATE_GOLDEN` is set, the golden file is written
+/// instead.
+///
+/// # Errors
+///
+/// The function returns a `Mismatch` if the golden file differs from
+/// the canonical JSON of `value`, and an I/O error if the golden file
+/// cannot be read or written.
+pub fn check_golden<T: Serialize + ?Sized>(path: impl AsRef<Path>, value: &T) -> Result<()> {
+    let path = path.as_ref();
+    let actual = to_canonical_json(value)?;
+
+    if env::var_os(UPDATE_VAR).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, actual)?;
+        return Ok(());
+    }
+
+    let expected = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "could not read golden file {} ({e}); set {UPDATE_VAR}=1 to create it",
+            path.display()
+        )
+    })?;
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Mismatch {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+        }
+        .into())
+    }
+}
+
+/// This function asserts that `value` matches the golden file at
+/// `path`. See [`check_golden`].
+///
+/// # Panics
+///
+/// The function panics if the golden file does not match or cannot be
+/// read or written.
+pub fn assert_golden<T: Serialize + ?Sized>(path: impl AsRef<Path>, value: &T) {
+    if let Err(e) = check_golden(path, value) {
+        panic!("{e}");
+    }
+}
+
+/// This function sorts the keys of every object in `value`, so that the
+/// output does not depend on whether `serde_json` preserves insertion
+/// order.
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Array(elements) => Value::Array(elements.into_iter().map(canonicalize).collect()),
+        Value::Object(object) => {
+            let mut fields: Vec<_> = object.into_iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(k, v)| (k, canonicalize(v)))
+                    .collect(),
+            )
+        }
+        value => value,
+    }
+}
@@ -0,0 +1,249 @@
+//! # Function handles
+//!
+//! A `FunctionHandle` looks up the type of a function once, when it is
+//! bound, and then checks the number and shape of the arguments of
+//! each call before anything is sent. A call that could not type check
+//! fails without a round trip to `cryptol-remote-api`.
+//!
+//! ```no_run
+//! use cryptol_client::convert::ToCryptolValue;
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! cryptol_client.load_module("SuiteB").unwrap();
+//! let mut sha384 = cryptol_client.bind("sha384").unwrap();
+//! for message in ["abc", "abd"] {
+//!     let digest: [u8; 48] = sha384.invoke_as(&[message.to_cryptol_value()]).unwrap();
+//! }
+//! ```
+
+use serde_json::Value;
+
+use crate::convert::FromCryptolValue;
+use crate::error::Error;
+use crate::expr::CryptolExpr;
+use crate::value::{CryptolValue, Integer};
+use crate::{parse, Answer, Args, CryptolClient, Result};
+
+/// A function of the loaded Cryptol module, bound by
+/// [`CryptolClient::bind`].
+///
+/// The handle calls the function from the state the client was in
+/// when it was bound, so loading other modules in the client later
+/// does not affect it.
+#[derive(Debug, Clone)]
+pub struct FunctionHandle {
+    client: CryptolClient,
+    function: String,
+    /// The types of the parameters, as encoded by `cryptol-remote-api`.
+    parameters: Vec<Value>,
+}
+
+impl CryptolClient {
+    /// This function looks up the type of the given function in the
+    /// loaded Cryptol module and returns a handle for calling it.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if `function` is not a
+    /// Cryptol name or was introduced with
+    /// [`define`](Self::define), or an error if the POST request to
+    /// `cryptol-remote-api` fails, e.g. because the function is not in
+    /// scope.
+    #[tracing::instrument(skip(self))]
+    pub fn bind(&mut self, function: &str) -> Result<FunctionHandle> {
+        CryptolExpr::name(function).render()?;
+        if self.definitions.iter().any(|(name, _)| name == function) {
+            return Err(
+                Error::Decode(format!("{function} is a definition and cannot be called")).into(),
+            );
+        }
+
+        let parameters = parameter_types(&self.type_schema(function)?);
+
+        let mut client = self.clone();
+        client.answer = None;
+        Ok(FunctionHandle {
+            client,
+            function: function.to_string(),
+            parameters,
+        })
+    }
+}
+
+impl FunctionHandle {
+    /// This function returns the name of the bound function.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.function
+    }
+
+    /// This function returns the number of arguments the bound
+    /// function takes.
+    #[must_use]
+    pub fn arity(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// This function calls the bound function with the given
+    /// arguments.
+    ///
+    /// Each argument is checked against the type of its parameter as
+    /// far as the type is known: a polymorphic parameter accepts any
+    /// value, and a parameter of type `[n][8]` accepts any sequence of
+    /// bytes.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` without contacting
+    /// `cryptol-remote-api` if the number of arguments is not the
+    /// arity of the function or an argument does not have the type of
+    /// its parameter, and otherwise fails as
+    /// [`CryptolClient::call`] does.
+    #[tracing::instrument(skip(self, arguments), fields(function = self.function))]
+    pub fn invoke(&mut self, arguments: &[CryptolValue]) -> Result<Answer> {
+        check_arguments(
+            &self.function,
+            &self.parameters,
+            &arguments.iter().map(Some).collect::<Vec<_>>(),
+        )?;
+
+        let arguments = arguments.iter().fold(Args::new(), Args::cryptol_value);
+        self.client.call(&self.function, arguments)
+    }
+
+    /// This function calls the bound function with the given arguments
+    /// and converts the resulting value into a `T`.
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`invoke`](Self::invoke) does, or returns
+    /// `Error::Decode` if the value cannot be converted into a `T`.
+    pub fn invoke_as<T: FromCryptolValue>(&mut self, arguments: &[CryptolValue]) -> Result<T> {
+        let answer = self.invoke(arguments)?;
+        Ok(T::from_cryptol_value(answer.decode()?)?)
+    }
+}
+
+/// This function returns the types of the parameters of a function
+/// with the given type schema, as encoded by `cryptol-remote-api`.
+pub(crate) fn parameter_types(schema: &Value) -> Vec<Value> {
+    let mut parameters = Vec::new();
+    let mut ty = &schema["type"];
+    while ty["type"] == "function" {
+        parameters.push(ty["domain"].clone());
+        ty = &ty["range"];
+    }
+    parameters
+}
+
+/// This function checks the arguments of a call of `function`, whose
+/// parameters have the given types. An argument whose value is not
+/// known, e.g. because it was given as an expression, only counts
+/// towards the number of arguments.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if the number of arguments is
+/// not the number of parameters or an argument does not have the type
+/// of its parameter.
+pub(crate) fn check_arguments(
+    function: &str,
+    parameters: &[Value],
+    arguments: &[Option<&CryptolValue>],
+) -> std::result::Result<(), Error> {
+    if arguments.len() != parameters.len() {
+        let types: Vec<String> = parameters
+            .iter()
+            .map(|ty| format!("`{}`", render(ty)))
+            .collect();
+        let expected = match types.as_slice() {
+            [] => "no arguments".to_string(),
+            [ty] => format!("1 argument of type {ty}"),
+            [init @ .., last] => format!(
+                "{} arguments of types {} and {last}",
+                types.len(),
+                init.join(", ")
+            ),
+        };
+        return Err(Error::Decode(format!(
+            "`{function}` expects {expected}, got {}",
+            arguments.len()
+        )));
+    }
+
+    for (i, (argument, ty)) in arguments.iter().zip(parameters).enumerate() {
+        if let Some(argument) = argument {
+            if !conforms(argument, ty) {
+                return Err(Error::Decode(format!(
+                    "argument {} of `{function}` does not have type `{}`: {argument:?}",
+                    i + 1,
+                    render(ty)
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// This function renders a type encoded by `cryptol-remote-api` in
+/// Cryptol syntax, or as JSON if it cannot be decoded.
+fn render(ty: &Value) -> String {
+    parse::cryptol_type(ty).map_or_else(|_| ty.to_string(), |ty| ty.to_string())
+}
+
+/// This function returns whether `value` could have the type `ty`, as
+/// encoded by `cryptol-remote-api`. Type variables, type-level
+/// arithmetic and types the client does not know about match anything.
+fn conforms(value: &CryptolValue, ty: &Value) -> bool {
+    let integer = |ty: &Value| match (ty["type"].as_str(), &ty["value"]) {
+        (Some("number"), Value::Number(n)) => n.to_string().parse::<Integer>().ok(),
+        // Numbers too wide for a JSON number are given as their digits.
+        (Some("number"), Value::String(n)) => n.parse::<Integer>().ok(),
+        _ => None,
+    };
+    let number = |ty: &Value| integer(ty).and_then(|n| u64::try_from(&n).ok());
+    let length = |len: usize| {
+        let expected = number(&ty["length"]).or_else(|| number(&ty["width"]));
+        expected.is_none_or(|expected| u64::try_from(len) == Ok(expected))
+    };
+
+    match (value, ty["type"].as_str()) {
+        (CryptolValue::Opaque(_), _)
+        | (CryptolValue::Bit(_), Some("Bit"))
+        | (CryptolValue::Integer(_), Some("Integer"))
+        | (CryptolValue::Unit, Some("unit")) => true,
+        (CryptolValue::IntegerModulo { modulus, .. }, Some("Z")) => {
+            integer(&ty["modulus"]).is_none_or(|m| m == *modulus)
+        }
+        (CryptolValue::BitVector(bits), Some("bitvector")) => length(bits.width()),
+        (CryptolValue::Sequence(elements), Some("bitvector")) => {
+            length(elements.len()) && elements.iter().all(|e| matches!(e, CryptolValue::Bit(_)))
+        }
+        (CryptolValue::Sequence(elements), Some("sequence")) => {
+            length(elements.len()) && elements.iter().all(|e| conforms(e, &ty["contents"]))
+        }
+        (CryptolValue::Tuple(components), Some("tuple")) => {
+            ty["contents"].as_array().is_some_and(|types| {
+                types.len() == components.len()
+                    && components.iter().zip(types).all(|(c, t)| conforms(c, t))
+            })
+        }
+        (CryptolValue::Record(fields), Some("record")) => {
+            ty["fields"].as_object().is_some_and(|types| {
+                types.len() == fields.len()
+                    && fields
+                        .iter()
+                        .all(|(name, v)| types.get(name).is_some_and(|t| conforms(v, t)))
+            })
+        }
+        (
+            _,
+            Some(
+                "Bit" | "Integer" | "Z" | "bitvector" | "sequence" | "tuple" | "record" | "unit"
+                | "function",
+            ),
+        ) => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,461 @@
+//! # REPL batch scripts
+//!
+//! The Cryptol REPL runs batch scripts, conventionally `.icry` files,
+//! with `cryptol --batch`: one command or expression per line, as it
+//! would be typed at the prompt. [`export`] turns the
+//! [transcript](crate::transcript) of a client into such a script, so
+//! that an interaction driven from Rust can be reproduced and debugged
+//! in the REPL.
+//!
+//! ```no_run
+//! use std::fs;
+//!
+//! use cryptol_client::{icry, CryptolClient};
+//!
+//! let mut cryptol_client = CryptolClient::builder()
+//!     .record_transcript(true)
+//!     .connect()
+//!     .unwrap();
+//! cryptol_client.load_module("SuiteB").unwrap();
+//! cryptol_client.call("sha384", &["0x0001"]).unwrap();
+//! fs::write("session.icry", icry::export(cryptol_client.transcript())).unwrap();
+//! ```
+//!
+//! which writes
+//!
+//! ```text
+//! :module Cryptol
+//! :module SuiteB
+//! sha384 (0x0001)
+//! ```
+//!
+//! Conversely, [`CryptolClient::run_icry`] runs a simple batch script
+//! through the client, line by line, so that existing Cryptol test
+//! scripts can be run from Rust without the `cryptol` binary:
+//!
+//! ```no_run
+//! use std::fs;
+//!
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! let script = fs::read_to_string("tests/sha.icry").unwrap();
+//! for result in cryptol_client.run_icry(&script) {
+//!     assert!(!result.outcome.is_failure(), "line {}: {:?}", result.line, result.outcome);
+//! }
+//! ```
+
+use std::fmt::Write;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::check::{CheckResult, TestCount};
+use crate::parse;
+use crate::prove::ProveResult;
+use crate::transcript::TranscriptEntry;
+use crate::types::TypeSchema;
+use crate::value::CryptolValue;
+use crate::{CryptolClient, EvalResult};
+
+/// This function returns a REPL batch script doing what the requests
+/// of `transcript` did, one line per request:
+///
+/// - a module load becomes `:module`, and a file load `:load`;
+/// - an evaluation becomes the expression, and a call the function
+///   applied to its arguments, each in parentheses;
+/// - a type query becomes `:type`;
+/// - a property test becomes `:check`, after `:set tests` to the number
+///   of tests, or `:exhaust` for exhaustive tests;
+/// - a proof becomes `:prove`, after `:set prover` to the solver;
+/// - a query of the names in scope becomes `:browse`.
+///
+/// Requests that failed are preceded by a comment with their error, as
+/// the REPL should fail on them too. Requests without a REPL
+/// equivalent, and those whose parameters were redacted beyond
+/// recognition, become comments. Since the script runs in a single
+/// state, a request made from another state than the one the previous
+/// request produced, e.g. after a snapshot was restored, is preceded
+/// by a comment saying so. Expressions spanning several lines are
+/// joined into one.
+#[must_use]
+pub fn export(transcript: &[TranscriptEntry]) -> String {
+    let mut script = String::new();
+    let mut state: Option<&str> = None;
+    for (i, entry) in transcript.iter().enumerate() {
+        if i > 0 && entry.state.as_deref() != state {
+            let from = entry.state.as_deref().unwrap_or("the initial state");
+            // Writing to a `String` cannot fail.
+            let _ = writeln!(script, "// the next request is made from {from}");
+        }
+        state = entry.new_state.as_deref().or(entry.state.as_deref());
+        if let Some(error) = &entry.error {
+            let error = error.lines().next().unwrap_or_default();
+            let _ = writeln!(script, "// failed: {error}");
+        }
+        if let Some(command) = command(&entry.method, &entry.params) {
+            script.push_str(&command);
+        } else {
+            let params = Value::Object(entry.params.clone());
+            let _ = writeln!(script, "// {}: {params}", entry.method);
+        }
+    }
+    script
+}
+
+/// This function returns the lines a request for `method` with
+/// `params` becomes, or `None` if it has no REPL equivalent.
+fn command(method: &str, params: &Map<String, Value>) -> Option<String> {
+    let string = |name: &str| params.get(name).and_then(Value::as_str).map(one_line);
+    match method {
+        "load module" => Some(format!(":module {}\n", string("module name")?)),
+        "load file" => Some(format!(":load {}\n", string("file")?)),
+        "evaluate expression" => Some(format!("{}\n", expression(params.get("expression")?)?)),
+        "check type" => Some(format!(
+            ":type {}\n",
+            expression(params.get("expression")?)?
+        )),
+        "call" => {
+            let mut line = string("function")?;
+            for argument in params.get("arguments")?.as_array()? {
+                let _ = write!(line, " ({})", expression(argument)?);
+            }
+            line.push('\n');
+            Some(line)
+        }
+        "check" => {
+            let property = expression(params.get("expression")?)?;
+            match params.get("number of tests") {
+                Some(Value::String(all)) if all == "all" => Some(format!(":exhaust {property}\n")),
+                Some(Value::Number(tests)) => {
+                    Some(format!(":set tests={tests}\n:check {property}\n"))
+                }
+                None => Some(format!(":check {property}\n")),
+                Some(_) => None,
+            }
+        }
+        "prove or satisfy" if params.get("query type")?.as_str()? == "prove" => Some(format!(
+            ":set prover={}\n:prove {}\n",
+            string("prover")?,
+            expression(params.get("expression")?)?
+        )),
+        "visible names" => Some(":browse\n".to_string()),
+        _ => None,
+    }
+}
+
+/// This function returns the Cryptol source of an expression parameter,
+/// which is either source text or the JSON encoding of a value.
+fn expression(expression: &Value) -> Option<String> {
+    match expression {
+        Value::String(source) => Some(one_line(source)),
+        value => parse::value(value).ok().map(|value| value.to_expression()),
+    }
+}
+
+/// This function joins the lines of `source` with spaces.
+fn one_line(source: &str) -> String {
+    source.lines().collect::<Vec<_>>().join(" ")
+}
+
+/// What running a line of a script did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineOutcome {
+    /// `:module` loaded the module.
+    Loaded {
+        /// The module loaded.
+        module: String,
+    },
+    /// `:load` loaded the module in the file.
+    LoadedFile {
+        /// The path of the file, as given in the script.
+        path: String,
+    },
+    /// An expression, or the right-hand side of a `let`, evaluated to
+    /// the value.
+    Value(CryptolValue),
+    /// `:type` found the type of the expression.
+    Type(TypeSchema),
+    /// `:check` or `:exhaust` tested the property.
+    Checked(CheckResult),
+    /// `:prove` proved the property, or found a counterexample.
+    Proved(ProveResult),
+    /// `:set` changed a setting the client keeps track of.
+    Set,
+    /// The line was not run, because the client has no equivalent of
+    /// the command.
+    Skipped {
+        /// Why the line was not run.
+        reason: String,
+    },
+    /// The line failed, e.g. because the module does not exist or the
+    /// expression does not type check.
+    Error {
+        /// The error that occurred.
+        message: String,
+    },
+}
+
+impl LineOutcome {
+    /// This function returns whether the line failed, or tested or
+    /// proved a property that does not hold.
+    #[must_use]
+    pub fn is_failure(&self) -> bool {
+        match self {
+            LineOutcome::Checked(result) => !result.passed(),
+            LineOutcome::Proved(result) => !result.proved(),
+            LineOutcome::Error { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// A line of a script and what running it did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineResult {
+    /// The number of the line in the script, starting at 1. A line
+    /// continued onto the next ones with a trailing `\` is numbered
+    /// after its first line.
+    pub line: usize,
+    /// The source of the line, with continuations joined.
+    pub source: String,
+    /// What running the line did.
+    pub outcome: LineOutcome,
+}
+
+impl CryptolClient {
+    /// This function runs the given REPL batch script through the
+    /// client and returns what running each line did, in order. Blank
+    /// lines and `//` comments are skipped, and a line ending in `\`
+    /// continues onto the next one. The lines are mapped onto client
+    /// calls as follows:
+    ///
+    /// - `:module M` loads `M`, and `:load path/M.cry` loads the file,
+    ///   which the server reads, with [`load_file`](Self::load_file);
+    /// - an expression is evaluated, and `let x = e` is a
+    ///   [definition](Self::define);
+    /// - `:type e` asks for the type of `e`;
+    /// - `:check p` tests `p`, on as many random arguments as the last
+    ///   `:set tests=N` asked for, 100 by default, and `:exhaust p` on
+    ///   every argument;
+    /// - `:prove p` [proves](Self::prove_with) `p` with the solver the
+    ///   last `:set prover=S` asked for, the client's
+    ///   [solver](Self::solver) by default.
+    ///
+    /// Other commands, and `:check` and `:prove` without a property,
+    /// are skipped. A line that fails does not stop the script, as in
+    /// the REPL.
+    pub fn run_icry(&mut self, script: &str) -> Vec<LineResult> {
+        let mut settings = Settings {
+            tests: TestCount::default(),
+            solver: self.solver.clone(),
+        };
+        let mut results = Vec::new();
+        for (line, source) in lines(script) {
+            let outcome = self.run_icry_line(&source, &mut settings);
+            tracing::debug!(line, ?outcome, "ran script line");
+            results.push(LineResult {
+                line,
+                source,
+                outcome,
+            });
+        }
+        results
+    }
+
+    /// This function runs the REPL batch script in the file at `path`
+    /// through the client. See [`run_icry`](Self::run_icry).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the file cannot be read.
+    pub fn run_icry_file(&mut self, path: impl AsRef<Path>) -> crate::Result<Vec<LineResult>> {
+        let script = std::fs::read_to_string(path)?;
+        Ok(self.run_icry(&script))
+    }
+
+    /// This function evaluates the Cryptol expressions in the file at
+    /// `path`, one per line, in order, and returns the number of the
+    /// line of each, starting at 1, with the result of evaluating it.
+    /// Blank lines and `//` comments are skipped, and a line ending in
+    /// `\` continues onto the next one. Each evaluation moves the
+    /// client to the state token the server answers with, as every
+    /// request does, and the next one is made from it. Evaluating an
+    /// expression does not change what is in scope, so every
+    /// expression sees the same names.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// cryptol_client.load_module("SuiteB").unwrap();
+    /// for (line, result) in cryptol_client.eval_file("queries.txt").unwrap() {
+    ///     match result {
+    ///         Ok(result) => println!("{line}: {} : {}", result.value, result.type_string),
+    ///         Err(e) => println!("{line}: {e}"),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the file cannot be read. An
+    /// expression that fails to evaluate does not stop the others, and
+    /// its error is returned in its place.
+    pub fn eval_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> crate::Result<Vec<(usize, crate::Result<EvalResult>)>> {
+        let expressions = std::fs::read_to_string(path)?;
+        Ok(lines(&expressions)
+            .into_iter()
+            .map(|(line, expression)| {
+                let result = self
+                    .evaluate(&expression)
+                    .and_then(|answer| Ok(answer.into_eval_result()?));
+                (line, result)
+            })
+            .collect())
+    }
+
+    /// This function runs a line of a script with the settings the
+    /// lines before it made.
+    fn run_icry_line(&mut self, source: &str, settings: &mut Settings) -> LineOutcome {
+        let (command, argument) = match source.strip_prefix(':') {
+            Some(command) => command
+                .split_once(char::is_whitespace)
+                .map_or((command, ""), |(command, argument)| {
+                    (command, argument.trim())
+                }),
+            None => return self.run_icry_expression(source),
+        };
+        let outcome = match (command, argument) {
+            ("m" | "module" | "l" | "load" | "t" | "type", "") => {
+                return LineOutcome::Error {
+                    message: format!(":{command} needs an argument"),
+                }
+            }
+            ("m" | "module", module) => self.load_module(module).map(|()| LineOutcome::Loaded {
+                module: module.to_string(),
+            }),
+            ("l" | "load", path) => {
+                let path = path.trim_matches('"');
+                self.load_file(path).map(|()| LineOutcome::LoadedFile {
+                    path: path.to_string(),
+                })
+            }
+            ("t" | "type", expression) => self.type_of(expression).map(LineOutcome::Type),
+            ("check" | "exhaust", "") => {
+                return LineOutcome::Skipped {
+                    reason: "checking every property of a module is not supported".to_string(),
+                }
+            }
+            ("check", property) => self
+                .check(property, settings.tests)
+                .map(LineOutcome::Checked),
+            ("exhaust", property) => self
+                .check(property, TestCount::All)
+                .map(LineOutcome::Checked),
+            ("prove", "") => {
+                return LineOutcome::Skipped {
+                    reason: "proving every property of a module is not supported".to_string(),
+                }
+            }
+            ("prove", property) => self
+                .prove_with(property, &settings.solver)
+                .map(LineOutcome::Proved),
+            ("s" | "set", setting) => return set(setting, settings),
+            (command, _) => {
+                return LineOutcome::Skipped {
+                    reason: format!(":{command} has no equivalent in the client"),
+                }
+            }
+        };
+        outcome.unwrap_or_else(|e| LineOutcome::Error {
+            message: e.to_string(),
+        })
+    }
+
+    /// This function evaluates an expression line of a script, or
+    /// defines the name a `let` line binds.
+    fn run_icry_expression(&mut self, source: &str) -> LineOutcome {
+        let value = match source
+            .strip_prefix("let ")
+            .and_then(|binding| binding.split_once('='))
+        {
+            Some((name, expression)) => self.define(name.trim(), expression.trim()),
+            None => self
+                .evaluate(source)
+                .and_then(|answer| Ok(answer.decode()?)),
+        };
+        value.map_or_else(
+            |e| LineOutcome::Error {
+                message: e.to_string(),
+            },
+            LineOutcome::Value,
+        )
+    }
+}
+
+/// The settings of the REPL a script runs with, changed by its `:set`
+/// lines.
+struct Settings {
+    /// The number of tests `:check` runs.
+    tests: TestCount,
+    /// The solver `:prove` uses.
+    solver: String,
+}
+
+/// This function applies a `:set` line of a script, of which only the
+/// number of tests and the prover are kept track of.
+fn set(setting: &str, settings: &mut Settings) -> LineOutcome {
+    let Some((name, value)) = setting.split_once('=') else {
+        return LineOutcome::Skipped {
+            reason: "showing settings has no equivalent in the client".to_string(),
+        };
+    };
+    match (name.trim(), value.trim()) {
+        ("tests", value) => match value.parse() {
+            Ok(count) => {
+                settings.tests = TestCount::Random(count);
+                LineOutcome::Set
+            }
+            Err(_) => LineOutcome::Error {
+                message: format!("invalid number of tests {value}"),
+            },
+        },
+        ("prover", solver) => {
+            settings.solver = solver.to_string();
+            LineOutcome::Set
+        }
+        (name, _) => LineOutcome::Skipped {
+            reason: format!("the setting {name} is not kept track of"),
+        },
+    }
+}
+
+/// This function returns the lines of `script` to run, numbered from 1,
+/// without blank lines and comments and with continuations joined.
+fn lines(script: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (i, text) in script.lines().enumerate() {
+        let (number, mut source) = pending.take().unwrap_or((i + 1, String::new()));
+        let text = text.trim();
+        if let Some(text) = text.strip_suffix('\\') {
+            source.push_str(text.trim_end());
+            source.push(' ');
+            pending = Some((number, source));
+            continue;
+        }
+        source.push_str(text);
+        let source = source.trim();
+        if !source.is_empty() && !source.starts_with("//") {
+            lines.push((number, source.to_string()));
+        }
+    }
+    if let Some((number, source)) = pending {
+        lines.push((number, source.trim().to_string()));
+    }
+    lines
+}
@@ -0,0 +1,141 @@
+//! # Keepalive
+//!
+//! Servers and proxies may drop connections and states that have been
+//! idle for a while. With
+//! [`CryptolClientBuilder::keepalive`](crate::CryptolClientBuilder::keepalive),
+//! a background thread issues a `check type` query from the state of
+//! the most recent request whenever the client has been idle for the
+//! given interval. A clone of the client shares the thread, which then
+//! keeps the current state of each of them alive. The thread stops when
+//! the client and all its clones have been dropped.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Map};
+
+use crate::transport::Transport;
+
+/// What the keepalive thread knows about the client and its clones.
+struct Activity {
+    /// The state of the most recent request of each client, keyed by
+    /// the id of its handle. The thread stops when it is empty.
+    states: BTreeMap<u64, Option<String>>,
+    /// The id of the next handle.
+    next_id: u64,
+    /// When the most recent request or ping was made.
+    last: Instant,
+}
+
+struct Shared {
+    activity: Mutex<Activity>,
+    wake: Condvar,
+}
+
+/// The handle of a client to a keepalive thread. Cloning it adds a
+/// client whose state is kept alive, and dropping the last handle stops
+/// the thread.
+pub(crate) struct Keepalive {
+    shared: Arc<Shared>,
+    id: u64,
+}
+
+impl fmt::Debug for Keepalive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keepalive").finish_non_exhaustive()
+    }
+}
+
+impl Keepalive {
+    /// This function starts a thread that pings `transport` whenever
+    /// no request has been made for `interval`.
+    pub(crate) fn start(transport: Arc<dyn Transport>, interval: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            activity: Mutex::new(Activity {
+                states: BTreeMap::from([(0, None)]),
+                next_id: 1,
+                last: Instant::now(),
+            }),
+            wake: Condvar::new(),
+        });
+
+        let thread_shared = Arc::clone(&shared);
+        thread::spawn(move || run(&thread_shared, transport.as_ref(), interval));
+
+        Keepalive { shared, id: 0 }
+    }
+
+    /// This function records that the client made a request that left
+    /// it in `state`, which restarts the idle interval.
+    pub(crate) fn touch(&self, state: Option<&str>) {
+        let mut activity = lock(&self.shared);
+        activity.states.insert(self.id, state.map(str::to_string));
+        activity.last = Instant::now();
+    }
+}
+
+impl Clone for Keepalive {
+    fn clone(&self) -> Self {
+        let mut activity = lock(&self.shared);
+        let id = activity.next_id;
+        activity.next_id += 1;
+        let state = activity.states.get(&self.id).cloned().flatten();
+        activity.states.insert(id, state);
+        Keepalive {
+            shared: Arc::clone(&self.shared),
+            id,
+        }
+    }
+}
+
+impl Drop for Keepalive {
+    fn drop(&mut self) {
+        lock(&self.shared).states.remove(&self.id);
+        self.shared.wake.notify_all();
+    }
+}
+
+/// This function locks the activity, which a panicking ping cannot
+/// leave inconsistent.
+fn lock(shared: &Shared) -> std::sync::MutexGuard<'_, Activity> {
+    shared
+        .activity
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// This function is the body of the keepalive thread.
+fn run(shared: &Shared, transport: &dyn Transport, interval: Duration) {
+    let mut activity = lock(shared);
+    while !activity.states.is_empty() {
+        let remaining = interval.saturating_sub(activity.last.elapsed());
+        if !remaining.is_zero() {
+            activity = shared
+                .wake
+                .wait_timeout(activity, remaining)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+            continue;
+        }
+
+        let mut states: Vec<Option<String>> = activity.states.values().cloned().collect();
+        states.sort_unstable();
+        states.dedup();
+        activity.last = Instant::now();
+        drop(activity);
+
+        for state in states {
+            let mut params = Map::new();
+            params.insert("state".into(), json!(state));
+            params.insert("expression".into(), json!("True"));
+            match transport.request("check type", &params) {
+                Ok(_) => tracing::debug!(state, "keepalive ping"),
+                Err(e) => tracing::warn!(state, error = %e, "keepalive ping failed"),
+            }
+        }
+        activity = lock(shared);
+    }
+}
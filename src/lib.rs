@@ -2,19 +2,137 @@
 //!
 //! `cryptol_client` is a collection of utilities for connecting
 //! to and interacting with a running `cryptol-remote-api` instance.
+//!
+//! ## Logging
+//!
+//! The crate never writes to stdout or stderr. Diagnostics, such as
+//! connection attempts, requests and failed keepalive pings, are
+//! reported as [`tracing`] spans and events at the appropriate level,
+//! and are only displayed if the application installs a subscriber.
+//! Applications that use the `log` facade instead can enable the
+//! `log` feature, which also emits every event as a `log` record.
+//!
+//! With the `opentelemetry` feature, the span of every request to the
+//! server also carries the fields of the OpenTelemetry conventions for
+//! RPC client spans, so that an application exporting its spans with
+//! `tracing-opentelemetry` shows Cryptol calls in its distributed
+//! traces, named after their method and with their status.
+//!
+//! ## Zeroization
+//!
+//! Clients often pass real key material to cryptographic
+//! specifications. With the `zeroize` feature, the client overwrites
+//! such data with zeros when it is dropped rather than leaving it in
+//! freed memory:
+//!
+//! - [`BitVector`](value::BitVector)s and [`Integer`](value::Integer)s,
+//!   and so the [`CryptolValue`]s built from them;
+//! - [`Args`], and the parameters of the requests made from them;
+//! - the parameters of requests recorded in a
+//!   [`transcript`](transcript::TranscriptEntry);
+//! - the request and response bodies of the HTTP transport.
+//!
+//! `CryptolValue` also implements `zeroize::Zeroize`, for wiping a
+//! value that is kept. Copies the application makes, and answers
+//! other than decoded bitvectors and integers, are the application's
+//! to wipe. Buffers internal to `hyper`, `flate2` and the operating
+//! system are not wiped.
 
 #![forbid(unsafe_code)]
 
-use std::env;
+mod args;
+mod batch;
+pub mod bignum;
+mod builder;
+pub mod cache;
+pub mod check;
+pub mod compat;
+pub mod config;
+pub mod convert;
+mod define;
+pub mod diagnostic;
+pub mod diff;
+#[cfg(feature = "proptest")]
+pub mod differential;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod discover;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod disk_cache;
+pub mod error;
+pub mod expr;
+pub mod functional;
+pub mod golden;
+mod handle;
+pub mod icry;
+#[cfg(not(target_arch = "wasm32"))]
+mod keepalive;
+mod macros;
+pub mod metrics;
+pub mod names;
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel;
+pub mod parse;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod progress;
+pub mod properties;
+pub mod prove;
+#[cfg(feature = "pyo3")]
+pub mod python;
+pub mod ser;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+pub mod session;
+mod snapshot;
+mod stream;
+mod strict;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
+pub mod testvec;
+pub mod trace;
+pub mod transcript;
+pub mod transport;
+pub mod types;
+pub mod value;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
+#[cfg(feature = "zeroize")]
+mod wipe;
+
+pub use args::Args;
+pub use batch::Batch;
+pub use builder::CryptolClientBuilder;
+pub use error::{CryptolDataData, CryptolError, CryptolErrorData};
+pub use handle::FunctionHandle;
+pub use snapshot::Snapshot;
+
+#[cfg(all(feature = "macros", not(target_arch = "wasm32")))]
+pub use cryptol_client_macros::cryptol_test;
 
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
 
-use jsonrpsee::core::client::ClientT;
-use jsonrpsee::core::params::ObjectParams;
-use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuilder};
+use tracing::{field, Span};
 
 use std::time::Duration;
+use web_time::Instant;
+
+use cache::Cache;
+use convert::FromCryptolValue;
+use error::Error;
+use metrics::{Metrics, Outcome, RequestEvent};
+use session::RemoteSession;
+use trace::TraceEvent;
+use value::CryptolValue;
+
+/// The name of a module that is loaded to find the search path of
+/// `cryptol-remote-api`, chosen so that it does not exist.
+const SEARCH_PATH_PROBE: &str = "CryptolClientSearchPathProbe";
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -22,183 +140,1266 @@ type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 /// For example:
 ///   `{"answer":[],"state":"a4909ccf-3ef9-45cc-913b-57e58da75788","stderr":"","stdout":""}`
 
-#[derive(Debug, Serialize, Deserialize)]
-struct CryptolResult {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptolResult {
     #[serde(default)]
-    answer: serde_json::Value,
+    answer: Option<Box<RawValue>>,
+    #[serde(deserialize_with = "parse::null_as_default")]
     state: String,
     stderr: String,
     stdout: String,
+    /// The round-trip time of the request that produced this result.
+    #[serde(skip)]
+    elapsed: Duration,
 }
 
+impl CryptolResult {
+    /// This function returns the `answer` of the response as raw,
+    /// undecoded JSON, or `None` if the response has no answer.
+    #[must_use]
+    pub fn answer(&self) -> Option<&RawValue> {
+        self.answer.as_deref()
+    }
+
+    /// This function returns the token of the state the request
+    /// produced.
+    #[must_use]
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// This function returns what the server wrote to standard output
+    /// while handling the request.
+    #[must_use]
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// This function returns what the server wrote to standard error
+    /// while handling the request.
+    #[must_use]
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+
+    /// This function returns the round-trip time of the request that
+    /// produced this result. See [`Answer::elapsed`].
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// This structure represents the `answer` of a successful `call`,
+/// together with metadata about the request that produced it.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Answer {
     #[serde(rename = "type")]
     answer_type: serde_json::Value,
     #[serde(rename = "type string")]
     type_string: String,
+    /// The value in the JSON encoding of `cryptol-remote-api`. Integers
+    /// that do not fit in 64 bits are given as strings of their digits,
+    /// which the server reads as the same literal; [`decode`](Self::decode)
+    /// reads them in full.
     pub value: serde_json::Value,
+    /// The text of the answer, kept when `value` or the type holds a
+    /// number too wide for a `Value`.
+    #[serde(skip)]
+    text: Option<String>,
+    #[serde(skip)]
+    elapsed: Duration,
+    #[serde(skip)]
+    trace: Vec<TraceEvent>,
+    #[serde(skip)]
+    stdout: String,
+    #[serde(skip)]
+    stderr: String,
+    /// Whether decoded values are checked against the type.
+    #[serde(skip)]
+    strict: bool,
 }
 
-/// This structure represents the JSON blob returned by
-/// `cryptol-remote-api` on error.  However, there is currently no way
-/// to access this information using the `jsonrpsee` crate.
-///
-/// Example JSON blob:
-///   `{"code":20500,"data":{"data":{"path":["client","//.cryptol","/usr/local/share/cryptol"],"source":"Floataboat","warnings":[]},"stderr":"","stdout":""},"message":"[error] Could not find module NoModule\nSearched paths:\n    //.cryptol\n    /usr/local/share/cryptol\nSet the CRYPTOLPATH environment variable to search more directories"}`
-
-#[derive(Serialize, Deserialize)]
-pub struct CryptolError {
-    code: i64,
-    data: CryptolErrorData,
-    message: String,
-}
+impl Answer {
+    /// This function returns the round-trip time of the request that
+    /// produced this answer, measured from just before the request was
+    /// sent until its response was received. This includes network
+    /// and serialization overhead as well as evaluation time on the
+    /// server; `cryptol-remote-api` does not report evaluation time
+    /// separately. Answers served from a [`Cache`] report zero.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
 
-#[derive(Serialize, Deserialize)]
-pub struct CryptolErrorData {
-    data: CryptolDataData,
-    stderr: String,
-    stdout: String,
+    /// This function returns the JSON encoding of the value of this
+    /// answer, without copying it.
+    #[must_use]
+    pub fn into_value(self) -> serde_json::Value {
+        self.value
+    }
+
+    /// This function returns what Cryptol's `trace` and `traceVal`
+    /// printed while the request that produced this answer was
+    /// evaluated. Answers served from a [`Cache`] replay the trace of
+    /// the original request.
+    #[must_use]
+    pub fn trace(&self) -> &[TraceEvent] {
+        &self.trace
+    }
+
+    /// This function returns the type of the value of this answer as
+    /// printed by Cryptol, e.g. `[384]`.
+    #[must_use]
+    pub fn type_string(&self) -> &str {
+        &self.type_string
+    }
+
+    /// This function returns the type schema of the value of this
+    /// answer as encoded by `cryptol-remote-api`, e.g.
+    /// `{"forall": [], "propositions": [], "type": {"type": "bitvector",
+    /// "width": {"type": "number", "value": 384}}}`.
+    #[must_use]
+    pub fn type_schema(&self) -> &serde_json::Value {
+        &self.answer_type
+    }
+
+    /// This function returns the width of the value of this answer if
+    /// it is a bitvector of known width, and `None` otherwise.
+    #[must_use]
+    pub fn bit_width(&self) -> Option<usize> {
+        let ty = &self.answer_type["type"];
+        if ty["type"] != "bitvector" || ty["width"]["type"] != "number" {
+            return None;
+        }
+        ty["width"]["value"]
+            .as_u64()
+            .and_then(|width| usize::try_from(width).ok())
+    }
+
+    /// This function returns the type of the value of this answer.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the type is not a valid
+    /// encoding of a Cryptol type.
+    pub fn cryptol_type(&self) -> std::result::Result<types::CryptolType, Error> {
+        parse::decode_cryptol_type(&self.json("type")?["type"])
+    }
+
+    /// This function returns the number of elements of the value of
+    /// this answer if it is a sequence of known length, e.g. 16 for a
+    /// `[16][8]`, and `None` otherwise. The elements of a bitvector
+    /// are its bits.
+    #[must_use]
+    pub fn sequence_length(&self) -> Option<usize> {
+        self.cryptol_type()
+            .ok()?
+            .sequence_length()
+            .and_then(|length| usize::try_from(length).ok())
+    }
+
+    /// This function returns the width of the elements of the value
+    /// of this answer if it is a sequence of bitvectors of known
+    /// width, e.g. 8 for a `[16][8]`, and `None` otherwise.
+    #[must_use]
+    pub fn element_width(&self) -> Option<usize> {
+        match self.cryptol_type().ok()? {
+            types::CryptolType::Sequence { contents, .. } => contents
+                .bit_width()
+                .and_then(|width| usize::try_from(width).ok()),
+            _ => None,
+        }
+    }
+
+    /// This function decodes the `value` of this answer. With
+    /// [`CryptolClientBuilder::strict_decoding`], the value is checked
+    /// against the type of the answer.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the value is not a
+    /// valid encoding of a Cryptol value, or in strict mode if it does
+    /// not have the type of the answer.
+    pub fn decode(&self) -> std::result::Result<CryptolValue, Error> {
+        let value = parse::decode_value(&self.json("value")?)?;
+        if self.strict {
+            value.check_type(&self.cryptol_type()?)?;
+        }
+        Ok(value)
+    }
+
+    /// This function returns an iterator over the elements of the value
+    /// of this answer, which must be a sequence other than a bitvector,
+    /// decoding each element only when it is reached. Unlike
+    /// [`decode`](Self::decode), it never holds more than one decoded
+    /// element, which matters for long sequences of large elements.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let answer = cryptol_client.evaluate("[0 .. 99999] : [_][32]").unwrap();
+    /// for element in answer.iter_elements().unwrap() {
+    ///     println!("{}", element.unwrap());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the value is not the
+    /// encoding of a sequence. The iterator yields `Error::Decode` for
+    /// an element that is not a valid encoding of a Cryptol value, or
+    /// in strict mode for one that does not have the element type of
+    /// the answer.
+    pub fn iter_elements(
+        &self,
+    ) -> std::result::Result<
+        impl ExactSizeIterator<Item = std::result::Result<CryptolValue, Error>> + '_,
+        Error,
+    > {
+        let elements: Box<dyn ExactSizeIterator<Item = parse::Json> + '_> = match &self.text {
+            Some(_) => match self.json("value")?.take("data") {
+                parse::Json::Array(elements) if self.value["expression"] == "sequence" => {
+                    Box::new(elements.into_iter())
+                }
+                _ => return Err(self.not_a_sequence()),
+            },
+            None => match (&self.value["expression"], &self.value["data"]) {
+                (Value::String(tag), Value::Array(elements)) if tag == "sequence" => {
+                    Box::new(elements.iter().map(parse::Json::from))
+                }
+                _ => return Err(self.not_a_sequence()),
+            },
+        };
+        let contents = match self.strict.then(|| self.cryptol_type()).transpose()? {
+            Some(types::CryptolType::Sequence { contents, .. }) => Some(*contents),
+            Some(ty) => {
+                return Err(Error::Decode(format!(
+                    "the value is a sequence, which does not match its type {ty}"
+                )))
+            }
+            None => None,
+        };
+        Ok(elements.map(move |element| {
+            let element = parse::decode_value(&element)?;
+            if let Some(contents) = &contents {
+                element.check_type(contents)?;
+            }
+            Ok(element)
+        }))
+    }
+
+    /// This function returns the error for an answer that is not a
+    /// sequence of values.
+    fn not_a_sequence(&self) -> Error {
+        Error::Decode(format!(
+            "the answer is not a sequence of values, but a {}",
+            self.type_string
+        ))
+    }
+
+    /// This function returns the field `name` of this answer, `value`
+    /// or `type`, with its numbers in full.
+    fn json(&self, name: &str) -> std::result::Result<parse::Json, Error> {
+        match &self.text {
+            Some(text) => Ok(parse::Json::parse(text)?.take(name)),
+            None if name == "type" => Ok(parse::Json::from(&self.answer_type)),
+            None => Ok(parse::Json::from(&self.value)),
+        }
+    }
+
+    /// This function decodes the value and the type of this answer
+    /// into an [`EvalResult`], along with what the server wrote while
+    /// producing it.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let result = cryptol_client
+    ///     .evaluate("traceVal \"x\" 0x2a")
+    ///     .unwrap()
+    ///     .into_eval_result()
+    ///     .unwrap();
+    /// assert_eq!(result.type_string, "[8]");
+    /// assert_eq!(result.stdout, "x 0x2a\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the value or the type
+    /// is not a valid encoding.
+    pub fn into_eval_result(self) -> std::result::Result<EvalResult, Error> {
+        Ok(EvalResult {
+            value: self.decode()?,
+            schema: parse::decode_type_schema(&self.json("type")?)?,
+            type_string: self.type_string,
+            stdout: self.stdout,
+            stderr: self.stderr,
+        })
+    }
+
+    /// This function attaches the output of the request that produced
+    /// this answer, whose response was `response`.
+    fn attach_output(&mut self, response: &CryptolResult) {
+        self.trace = trace::events(&response.stdout);
+        self.stdout.clone_from(&response.stdout);
+        self.stderr.clone_from(&response.stderr);
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct CryptolDataData {
-    path: Vec<String>,
-    source: String,
-    warnings: Vec<Option<serde_json::Value>>,
+/// The decoded outcome of an evaluation or call, created with
+/// [`Answer::into_eval_result`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalResult {
+    /// The value.
+    pub value: CryptolValue,
+    /// The type schema of the value.
+    pub schema: types::TypeSchema,
+    /// The type of the value as printed by Cryptol, e.g. `[384]`.
+    pub type_string: String,
+    /// What the server wrote to standard output while evaluating, e.g.
+    /// the output of Cryptol's `trace`.
+    pub stdout: String,
+    /// What the server wrote to standard error while evaluating.
+    pub stderr: String,
 }
 
 /// Cryptol client struct. Contains the active client connection and
 /// state attribute.
+///
+/// Cloning a client forks it. The server never changes a state once it
+/// has produced it, but answers each request with a new one, so the
+/// clone and the original start from the same state and then advance
+/// independently: requests of one never affect the state, loaded
+/// modules or definitions of the other. Both share the transport and
+/// the caches, and a keepalive thread keeps the state of each alive.
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)] // each is an independent setting
 pub struct CryptolClient {
-    client: HttpClient,
-    state: String,
-    answer: serde_json::Value,
+    session: RemoteSession,
+    answer: Option<Box<RawValue>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    cache: Option<Arc<Cache>>,
+    /// Whether `load_module` skips modules that are already loaded.
+    memoize_loads: bool,
+    /// Whether `call` checks its arguments against the type of the
+    /// function first.
+    validate_calls: bool,
+    /// Whether every change of state is logged.
+    trace_states: bool,
+    /// Whether decoded answers are checked against their types.
+    strict: bool,
+    /// The parameter types of the functions checked by `call`, keyed by
+    /// state and function.
+    signatures: HashMap<(String, String), Vec<Value>>,
+    /// The last module loaded, with the state its load produced.
+    loaded: Option<(String, String)>,
+    /// The modules loaded in this session, most recently loaded last.
+    modules: Vec<String>,
+    /// Values named with `define`, in the order they were defined.
+    definitions: Vec<(String, CryptolValue)>,
+    /// The initial modules of a lazy connection that have not been
+    /// loaded yet.
+    pending_modules: Vec<String>,
+    /// The version check of a lazy connection that has not been made
+    /// yet, and whether it is forced.
+    pending_version_check: Option<(compat::VersionCheck, bool)>,
+    /// The solver `prove` uses.
+    solver: String,
+    /// The requests made so far, if they are recorded.
+    transcript: Option<transcript::Transcript>,
+    /// The cache kept on disk, shared with clones of this client.
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<Arc<disk_cache::DiskCache>>,
+    /// What keys requests in the disk cache, keyed by state, or `None`
+    /// for states the server cannot describe.
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_contexts: HashMap<String, Option<String>>,
+    /// The handle of the keepalive thread, which is shared with clones
+    /// of this client but keeps the state of each alive.
+    #[cfg(not(target_arch = "wasm32"))]
+    keepalive: Option<keepalive::Keepalive>,
+    /// The progress callback and the interval it is called at.
+    #[cfg(not(target_arch = "wasm32"))]
+    progress: Option<(Duration, Arc<progress::ProgressCallback>)>,
 }
 
-/// Cryptol client implementation.
+impl fmt::Debug for CryptolClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("CryptolClient");
+        f.field("session", &self.session)
+            .field("answer", &self.answer)
+            .field("metrics", &self.metrics.is_some())
+            .field("cache", &self.cache.is_some())
+            .field("memoize_loads", &self.memoize_loads)
+            .field("validate_calls", &self.validate_calls)
+            .field("trace_states", &self.trace_states)
+            .field("strict", &self.strict)
+            .field("signatures", &self.signatures)
+            .field("loaded", &self.loaded)
+            .field("modules", &self.modules)
+            .field("definitions", &self.definitions)
+            .field("pending_modules", &self.pending_modules)
+            .field("pending_version_check", &self.pending_version_check)
+            .field("solver", &self.solver)
+            .field("transcript", &self.transcript);
+        #[cfg(not(target_arch = "wasm32"))]
+        f.field("disk_cache", &self.disk_cache.is_some())
+            .field("cache_contexts", &self.cache_contexts)
+            .field("keepalive", &self.keepalive.is_some())
+            .field(
+                "progress",
+                &self.progress.as_ref().map(|(interval, _)| interval),
+            );
+        f.finish()
+    }
+}
 
+/// Cryptol client implementation.
 impl CryptolClient {
     /// This function establishes an HTTP connection with
     /// `cryptol-remote-api` located at `CRYPTOL_SERVER_URL`. Upon
     /// connection, `cryptol-remote-api` will load the Cryptol prelude
     /// and return a token representing the state of the connection.
     ///
-    /// This function has asynchronous behavior due to the POST
-    /// request to `cryptol-remote-api`. #[tokio::main] waits for the
-    /// request to complete.
-    #[tokio::main]
-    pub async fn connect() -> Result<CryptolClient> {
-        // Deduce whether or not `CRYPTOL_SERVER_URL` is defined.
-        let cryptol_server_url = match env::var("CRYPTOL_SERVER_URL") {
-            Ok(val) => {
-                println!("Attempting to connect to cryptol-remote-api at {val}.");
-                val
+    /// Use [`CryptolClient::builder`] to configure the connection.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `CRYPTOL_SERVER_URL` is not
+    /// set or if the initial POST request to `cryptol-remote-api`
+    /// fails.
+    pub fn connect() -> Result<CryptolClient> {
+        CryptolClientBuilder::new().connect()
+    }
+
+    /// This function creates a client for `cryptol-remote-api`
+    /// located at `CRYPTOL_SERVER_URL` without contacting it. The
+    /// Cryptol prelude is loaded before the first request the client
+    /// makes, so a client that is never used makes no requests.
+    ///
+    /// See [`CryptolClientBuilder::connect_lazy`].
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `CRYPTOL_SERVER_URL` is not
+    /// set.
+    pub fn connect_lazy() -> Result<CryptolClient> {
+        CryptolClientBuilder::new().connect_lazy()
+    }
+
+    /// This function returns a builder for configuring a connection
+    /// to `cryptol-remote-api`.
+    #[must_use]
+    pub fn builder() -> CryptolClientBuilder {
+        CryptolClientBuilder::new()
+    }
+
+    /// This function returns the time since `start`, or the fixed
+    /// latency of the transport if it reports one.
+    pub(crate) fn elapsed_since(&self, start: Instant) -> Duration {
+        self.session
+            .transport()
+            .fixed_latency()
+            .unwrap_or_else(|| start.elapsed())
+    }
+
+    /// This function returns the `request` span of a round trip for
+    /// `action`. See [`send`](Self::send).
+    ///
+    /// With the `opentelemetry` feature, the span also carries the
+    /// fields of the OpenTelemetry conventions for RPC client spans,
+    /// which `tracing-opentelemetry` exports: `otel.name`, `otel.kind`,
+    /// `rpc.system`, `rpc.service`, `rpc.method`, `otel.status_code`
+    /// and, for server errors, `rpc.jsonrpc.error_code` and
+    /// `otel.status_message`.
+    fn request_span(&self, action: &str) -> tracing::Span {
+        #[cfg(not(feature = "opentelemetry"))]
+        let span = tracing::info_span!(
+            "request",
+            method = action,
+            state = self.session.state(),
+            new_state = field::Empty,
+            duration_ms = field::Empty,
+            result_size = field::Empty,
+        );
+        #[cfg(feature = "opentelemetry")]
+        let span = tracing::info_span!(
+            "request",
+            method = action,
+            state = self.session.state(),
+            new_state = field::Empty,
+            duration_ms = field::Empty,
+            result_size = field::Empty,
+            otel.name = format!("cryptol-remote-api/{action}"),
+            otel.kind = "client",
+            otel.status_code = field::Empty,
+            otel.status_message = field::Empty,
+            rpc.system = "jsonrpc",
+            rpc.service = "cryptol-remote-api",
+            rpc.method = action,
+            rpc.jsonrpc.version = "2.0",
+            rpc.jsonrpc.error_code = field::Empty,
+        );
+        span
+    }
+
+    /// This function performs a single JSON-RPC round trip inside a
+    /// `request` tracing span. The span records the RPC `method`, the
+    /// `state` token the request was issued from, the resulting
+    /// `new_state`, the round-trip `duration_ms`, and the
+    /// `result_size` of the response in bytes. The same information
+    /// is reported to the installed `Metrics` hook, if any.
+    fn send(&self, action: &str, params: &Map<String, Value>) -> Result<CryptolResult> {
+        let span = self.request_span(action);
+
+        let start = Instant::now();
+        let (size, result) = span.in_scope(|| {
+            self.session.exchange(|transport| {
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some((interval, callback)) = &self.progress {
+                    return progress::watch(
+                        transport,
+                        action,
+                        *interval,
+                        callback.as_ref(),
+                        || transport.request(action, params),
+                    );
+                }
+                transport.request(action, params)
+            })
+        });
+        let latency = self.elapsed_since(start);
+        span.record("duration_ms", latency.as_secs_f64() * 1000.0);
+
+        let record = |outcome, payload_size| {
+            if let Some(metrics) = &self.metrics {
+                metrics.record(&RequestEvent {
+                    method: action,
+                    outcome,
+                    latency,
+                    payload_size,
+                });
             }
-            Err(e) => return Err(e.into()),
         };
 
-        // Insert a 'keep-alive' command into the initial packet
-        // header. Perhaps unnecessary?
-        let mut headers = HeaderMap::new();
-        headers.insert("Connection", HeaderValue::from_static("keep-alive"));
-
-        // Build client
-        let client = HttpClientBuilder::default()
-            .set_headers(headers)
-            .request_timeout(Duration::from_secs(60 * 60)) // Set longer request timeout
-            .build(cryptol_server_url)?;
-
-        // Create parameters for loading the Cryptol prelude.
-        let mut params = ObjectParams::new();
-        params.insert("state", json!(null))?;
-        params.insert("module name", "Cryptol")?;
-
-        // Make a request to `cryptol-remote-api` to load the Cryptol prelude
-        let response: CryptolResult = client.request("load module", params).await?;
-
-        // Create and return a new CryptolClient object to represent the
-        // stateful connection
-        Ok(CryptolClient {
-            client,
-            state: response.state.clone(),
-            answer: response.answer,
-        })
+        let mut response = match result {
+            Ok(response) => response,
+            // A response that cannot be decoded was still received.
+            Err(e) if size.is_some() => {
+                span.record("result_size", size);
+                #[cfg(feature = "opentelemetry")]
+                {
+                    span.record("otel.status_code", "error");
+                    span.record("otel.status_message", e.to_string());
+                }
+                record(Outcome::TransportError, size);
+                return Err(e.into());
+            }
+            Err(mut e) => {
+                if let Error::Server(error) = &mut e {
+                    error.set_expression(params);
+                }
+                tracing::warn!(parent: &span, error = %e, "request failed");
+                #[cfg(feature = "opentelemetry")]
+                {
+                    span.record("otel.status_code", "error");
+                    span.record("otel.status_message", e.to_string());
+                    if let Error::Server(e) = &e {
+                        span.record("rpc.jsonrpc.error_code", e.code());
+                    }
+                }
+                // What was traced before the failure shows how far the
+                // request got.
+                let partial = match &e {
+                    Error::Server(e) => e.trace(),
+                    Error::Timeout(Some(output)) => output.trace(),
+                    _ => Vec::new(),
+                };
+                span.in_scope(|| trace::emit(&partial));
+                record(
+                    match e {
+                        Error::Server(_) => Outcome::ServerError,
+                        Error::Timeout(_) => Outcome::Timeout,
+                        _ => Outcome::TransportError,
+                    },
+                    None,
+                );
+                return Err(e.into());
+            }
+        };
+        span.record("result_size", size);
+        span.record("new_state", response.state.as_str());
+        #[cfg(feature = "opentelemetry")]
+        span.record("otel.status_code", "ok");
+        record(Outcome::Success, size);
+        span.in_scope(|| trace::emit(&trace::events(&response.stdout)));
+        response.elapsed = latency;
+
+        Ok(response)
     }
 
     /// This function sends requests to `cryptol-remote-api` in the form
     /// of a given action and parameters.
     ///
-    /// This function has asynchronous behavior due to the POST
-    /// request to `cryptol-remote-api`. #[tokio::main] waits for the
-    /// request to complete.
-    ///
     /// Sample JSON for this:
     ///   `{"function": "sha384", "arguments": ["1 : [16]"], "state": "7dc51618-e655-49a3-9a72-880eeb8e16dd"}`
     ///
     ///   `{"answer":{"type":{"forall":[],"propositions":[],"type":{"type":"bitvector","width":{"type":"number","value":384}}},"type string":"[384]","value":{"data":"5d13bb39a64c4ee16e0e8d2e1c13ec4731ff1ac69652c072d0cdc355eb9e0ec41b08aef3dd6fe0541e9fa9e3dcc80f7b","encoding":"hex","expression":"bits","width":384}},"state":"fa57d2ec-afa8-4d7a-b1f2-f3b47412f13d","stderr":"","stdout":""}`
-    #[tokio::main]
-    async fn request(&mut self, action: &str, params: ObjectParams) -> Result<()> {
-        // Make a request to `cryptol-remote-api` to load the Cryptol prelude
-        let response: CryptolResult = self.client.request(action, params).await?;
-
-        // It would be nice to parse out any failure from this
-        // response.  See the `CryptolError` struct above -- Cryptol
-        // does return a nice `message` with pertinent inforamtion
-        // about the failure. To do this we would need to access the
-        // resulting JSON blob when `request` returns `Err`. The `Err`
-        // message does not contain much information.
+    ///
+    /// On success the response is returned, without its answer, which
+    /// is stored in the client instead, and the client moves to the
+    /// state the response names. If a `Cache` is installed, cacheable
+    /// requests are answered from it when possible.
+    fn request(&mut self, action: &str, params: &Map<String, Value>) -> Result<CryptolResult> {
+        self.round_trip(action, params, true)
+    }
+
+    /// This function makes a request like [`request`](Self::request).
+    /// Unless `moves_state` is set, the request is a query like
+    /// `version`, which leaves the client in its current state and is
+    /// made without loading the pending initial modules first.
+    pub(crate) fn round_trip(
+        &mut self,
+        action: &str,
+        params: &Map<String, Value>,
+        moves_state: bool,
+    ) -> Result<CryptolResult> {
+        // The parameters of the first request of a lazy connection are
+        // created before the initial modules are loaded, so the request
+        // is moved to the state the loads produce.
+        let reissued;
+        let params = if !moves_state
+            || (self.pending_modules.is_empty() && self.pending_version_check.is_none())
+        {
+            params
+        } else {
+            self.load_pending_modules()?;
+            let mut moved = params.clone();
+            moved.insert("state".into(), json!(self.session.state()));
+            reissued = moved;
+            &reissued
+        };
+
+        // The answer of an earlier request must not be mistaken for
+        // the answer of this one.
+        self.answer = None;
+
+        let key = self.cache.as_ref().and_then(|_| Cache::key(action, params));
+        let cached = match (&self.cache, &key) {
+            (Some(cache), Some(key)) => cache.get(key),
+            _ => None,
+        };
+
+        let mut response = if let Some(mut response) = cached {
+            tracing::debug!(method = action, "answered from cache");
+            response.elapsed = Duration::ZERO;
+            response
+        } else {
+            // Make a request to `cryptol-remote-api`.
+            #[cfg(not(target_arch = "wasm32"))]
+            let sent = self.send_through_disk_cache(action, params);
+            #[cfg(target_arch = "wasm32")]
+            let sent = self.send(action, params);
+            let response = match sent {
+                Ok(response) => response,
+                Err(e) => {
+                    self.record_request(action, params, Err(e.as_ref()));
+                    return Err(e);
+                }
+            };
+            if let (Some(cache), Some(key)) = (&self.cache, key) {
+                cache.insert(key, response.clone());
+            }
+            response
+        };
 
         // Update the CryptolClient state.
-        self.state = response.state.clone();
+        if moves_state {
+            if self.trace_states {
+                tracing::debug!(
+                    method = action,
+                    old_state = self.session.state(),
+                    new_state = response.state.as_str(),
+                    "state transition",
+                );
+            }
+            self.session.advance(&response);
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(keepalive) = &self.keepalive {
+                keepalive.touch(self.session.state());
+            }
+        }
+        let state = self.session.state().map(str::to_string);
+        self.record_request(action, params, Ok(state.as_deref()));
 
         // Update the CryptolClient answer.
-        self.answer = response.answer;
+        self.answer = response.answer.take();
 
+        Ok(response)
+    }
+
+    /// This function loads the initial modules of a lazy connection
+    /// that have not been loaded yet. If a load fails, it and the
+    /// modules after it are tried again before the next request.
+    pub(crate) fn load_pending_modules(&mut self) -> Result<()> {
+        self.check_pending_version()?;
+        let pending = std::mem::take(&mut self.pending_modules);
+        for (i, module) in pending.iter().enumerate() {
+            if let Err(e) = self.load_module(module) {
+                self.pending_modules = pending[i..].to_vec();
+                return Err(e);
+            }
+        }
         Ok(())
     }
 
     /// This function loads the given Cryptol module existing in the
     /// `CRYPTOL_PATH` of `cryptol-remote-api`.
     ///
+    /// If `module` is the module most recently loaded and the client
+    /// is still in the state that load produced, no request is made.
+    /// This can be turned off with
+    /// [`CryptolClientBuilder::memoize_module_loads`].
+    ///
     /// # Errors
     ///
     /// The function returns an error if the POST request to
     /// `cryptol-remote-api` fails.
+    #[tracing::instrument(skip(self))]
     pub fn load_module(&mut self, module: &str) -> Result<()> {
+        if self.memoize_loads
+            && self.loaded.as_ref().is_some_and(|(state, loaded)| {
+                Some(state.as_str()) == self.session.state() && loaded == module
+            })
+        {
+            tracing::debug!("module already loaded");
+            return Ok(());
+        }
+
         // Create parameters for loading the given Cryptol module.
-        let mut params = ObjectParams::new();
-        params.insert("state", json!(self.state))?;
-        params.insert("module name", module)?;
+        let mut params = self.session.params();
+        params.insert("module name".into(), json!(module));
 
         // Make a request to `cryptol-remote-api` to load the given module
-        self.request("load module", params)?;
+        self.request("load module", &params)?;
+        self.loaded = self
+            .session
+            .state()
+            .map(|state| (state.to_string(), module.to_string()));
+        self.modules.retain(|loaded| loaded != module);
+        self.modules.push(module.to_string());
 
         Ok(())
     }
 
-    /// This function calls the given function in the loaded Cryptol
-    /// module.
+    /// This function loads the Cryptol module in the file at `path`,
+    /// which is read by `cryptol-remote-api`, so a relative path is
+    /// relative to the working directory of the server.
+    ///
+    /// The module is not one of the
+    /// [`loaded_modules`](Self::loaded_modules), so it is not loaded
+    /// again when the state of the session has to be recreated.
     ///
     /// # Errors
     ///
     /// The function returns an error if the POST request to
     /// `cryptol-remote-api` fails.
-    pub fn call<P: Serialize>(&mut self, function: &str, arguments: &[P]) -> Result<Answer> {
-        // Create parameters for loading the given Cryptol module.
-        let mut params = ObjectParams::new();
-        params.insert("state", json!(self.state))?;
-        params.insert("function", json!(function))?;
-        params.insert("arguments", json!(arguments))?;
+    #[tracing::instrument(skip(self))]
+    pub fn load_file(&mut self, path: &str) -> Result<()> {
+        let mut params = self.session.params();
+        params.insert("file".into(), json!(path));
 
-        // Make a request to `cryptol-remote-api` to call the given function
-        self.request("call", params)?;
+        self.request("load file", &params)?;
+        self.loaded = None;
+
+        Ok(())
+    }
+
+    /// This function returns the modules loaded in this session, in
+    /// the order they were last loaded. The last one is the module in
+    /// whose context calls and expressions are evaluated.
+    #[must_use]
+    pub fn loaded_modules(&self) -> &[String] {
+        &self.modules
+    }
+
+    /// This function returns the directories `cryptol-remote-api`
+    /// searches for modules, in the order it searches them. This is
+    /// the path shown when a module cannot be found, so it can be used
+    /// to explain such a failure.
+    ///
+    /// The server has no method reporting its search path, so the path
+    /// is taken from the error of loading a module that does not
+    /// exist. The state of the client is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the server does not
+    /// report the path it searched, or an error if the POST request to
+    /// `cryptol-remote-api` fails.
+    #[tracing::instrument(skip(self))]
+    pub fn search_path(&mut self) -> Result<Vec<String>> {
+        let mut params = self.session.params();
+        params.insert("module name".into(), json!(SEARCH_PATH_PROBE));
+
+        let Err(e) = self.request("load module", &params) else {
+            return Err(Error::Decode(format!("module {SEARCH_PATH_PROBE} exists")).into());
+        };
+        match e.downcast_ref::<Error>() {
+            Some(Error::Server(error)) if !error.search_path().is_empty() => {
+                Ok(error.search_path().to_vec())
+            }
+            Some(Error::Server(error)) => {
+                Err(Error::Decode(format!("no search path in error: {}", error.message())).into())
+            }
+            _ => Err(e),
+        }
+    }
+
+    /// This function calls the given function in the loaded Cryptol
+    /// module. The arguments are given as [`Args`], or as a slice of
+    /// Cryptol expressions such as `&["[1, 2, 3]"]`.
+    ///
+    /// A function qualified with a module that has not been loaded in
+    /// this session, e.g. `SuiteB::sha384` before `SuiteB` is loaded,
+    /// is likely not in scope, and a warning is logged. The call is
+    /// made regardless, since the name may refer to an import alias.
+    ///
+    /// With [`CryptolClientBuilder::validate_calls`], the arguments are
+    /// first checked against the type of the function.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the arguments are
+    /// checked and do not fit the type of the function, or an error if
+    /// a POST request to `cryptol-remote-api` fails.
+    #[tracing::instrument(skip(self, arguments), fields(arguments = field::Empty))]
+    pub fn call(&mut self, function: &str, arguments: impl Into<Args>) -> Result<Answer> {
+        let response = self.call_request(function, &arguments.into())?;
 
         // Let `call` return the result as an Answer struct.
-        let answer: Answer = serde_json::from_value(self.answer.clone())?;
+        self.decode_answer(&response)
+    }
+
+    /// This function calls the given function like
+    /// [`call`](Self::call), but returns the complete response of
+    /// `cryptol-remote-api`, with the undecoded answer, the new state
+    /// token and what the server wrote to stdout and stderr, e.g. for
+    /// custom decoding or for debugging the protocol. The answer is
+    /// not kept for [`take_last_answer`](Self::take_last_answer).
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`call`](Self::call) does, but does not
+    /// decode the answer.
+    #[tracing::instrument(skip(self, arguments), fields(arguments = field::Empty))]
+    pub fn call_raw(
+        &mut self,
+        function: &str,
+        arguments: impl Into<Args>,
+    ) -> Result<CryptolResult> {
+        let response = self.call_request(function, &arguments.into())?;
+        Ok(self.with_answer(response))
+    }
+
+    /// This function makes the request behind `call`.
+    fn call_request(&mut self, function: &str, arguments: &Args) -> Result<CryptolResult> {
+        Span::current().record("arguments", arguments.len());
+        if self.validate_calls {
+            self.check_call(function, arguments)?;
+        }
+        let params = self.call_params(function, arguments);
+
+        // Make a request to `cryptol-remote-api` to call the given function
+        let response = self.request("call", &params);
+        #[cfg(feature = "zeroize")]
+        wipe::map(&mut { params });
+        response
+    }
+
+    /// This function calls the given function in the loaded Cryptol
+    /// module and converts the resulting value into a `T`.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let reversed: Vec<u32> = cryptol_client.call_as("reverse", &["[1, 2, 3]"]).unwrap();
+    /// assert_eq!(reversed, [3, 2, 1]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the POST request to
+    /// `cryptol-remote-api` fails, or `Error::Decode` if the value
+    /// cannot be converted into a `T`.
+    pub fn call_as<T: FromCryptolValue>(
+        &mut self,
+        function: &str,
+        arguments: impl Into<Args>,
+    ) -> Result<T> {
+        let answer = self.call(function, arguments)?;
+        Ok(T::from_cryptol_value(answer.decode()?)?)
+    }
+
+    /// This function calls the given function in the loaded Cryptol
+    /// module and deserializes the JSON encoding of the resulting
+    /// value into a `T`, for results whose encoding maps directly onto
+    /// a serde type.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Bits {
+    ///     data: String,
+    ///     width: usize,
+    /// }
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// cryptol_client.load_module("SuiteB").unwrap();
+    /// let digest: Bits = cryptol_client.call_into("sha384", &["0x0001"]).unwrap();
+    /// assert_eq!(digest.width, 384);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the POST request to
+    /// `cryptol-remote-api` fails, or `Error::Decode` if the value
+    /// cannot be deserialized into a `T`.
+    pub fn call_into<T: DeserializeOwned>(
+        &mut self,
+        function: &str,
+        arguments: impl Into<Args>,
+    ) -> Result<T> {
+        let answer = self.call(function, arguments)?;
+        serde_json::from_value(answer.into_value()).map_err(|e| {
+            Error::Decode(format!("cannot deserialize the result of {function}: {e}")).into()
+        })
+    }
+
+    /// This function calls the given polymorphic function in the
+    /// loaded Cryptol module with explicit type arguments, each a type
+    /// parameter of the function and its type in Cryptol syntax. For
+    /// example, `&[("n", "4")]` calls ``reverse`{n = 4}``, so a size
+    /// is picked deliberately instead of by defaulting.
+    ///
+    /// The type parameters are checked against the type of the
+    /// function first. The call is then made by evaluating the
+    /// application, so the arguments must be Cryptol expressions, or
+    /// booleans or numbers in their JSON encoding, as given by
+    /// [`Args::expression`] and [`Args::value`].
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if a type parameter is not
+    /// one of the function's or is given twice, or if an argument is
+    /// not an expression, and otherwise fails as
+    /// [`evaluate`](Self::evaluate) does.
+    #[tracing::instrument(skip(self, arguments))]
+    pub fn call_with_types(
+        &mut self,
+        function: &str,
+        types: &[(&str, &str)],
+        arguments: impl Into<Args>,
+    ) -> Result<Answer> {
+        let schema = self.type_schema(function)?;
+        let parameters: Vec<&str> = schema["forall"]
+            .as_array()
+            .map(|forall| forall.iter().filter_map(|p| p["name"].as_str()).collect())
+            .unwrap_or_default();
+        for (i, (parameter, _)) in types.iter().enumerate() {
+            if !parameters.contains(parameter) {
+                return Err(Error::Decode(format!(
+                    "{function} has no type parameter {parameter}, only {parameters:?}"
+                ))
+                .into());
+            }
+            if types[..i].iter().any(|(earlier, _)| earlier == parameter) {
+                return Err(
+                    Error::Decode(format!("type parameter {parameter} is given twice")).into(),
+                );
+            }
+        }
+
+        let arguments = arguments
+            .into()
+            .values()
+            .iter()
+            .map(|argument| match argument {
+                Value::String(source) => Ok(expr::CryptolExpr::source(source.as_str())),
+                Value::Bool(true) => Ok(expr::CryptolExpr::source("True")),
+                Value::Bool(false) => Ok(expr::CryptolExpr::source("False")),
+                Value::Number(n) => Ok(expr::CryptolExpr::source(n.to_string())),
+                _ => Err(Error::Decode(format!(
+                    "argument {argument} is not a Cryptol expression"
+                ))),
+            })
+            .collect::<std::result::Result<Vec<_>, Error>>()?;
+        let application =
+            expr::CryptolExpr::instantiate(function, types.iter().copied()).apply(arguments);
+
+        self.evaluate_expr(&application)
+    }
+
+    /// This function evaluates the given Cryptol expression in the
+    /// context of the loaded Cryptol module and the names introduced
+    /// with [`define`](Self::define).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the POST request to
+    /// `cryptol-remote-api` fails, e.g. because the expression does
+    /// not parse or type check.
+    #[tracing::instrument(skip(self))]
+    pub fn evaluate(&mut self, expression: &str) -> Result<Answer> {
+        let response = self.evaluate_request(expression)?;
+
+        self.decode_answer(&response)
+    }
+
+    /// This function evaluates the given Cryptol expression like
+    /// [`evaluate`](Self::evaluate), but returns the complete response
+    /// of `cryptol-remote-api`. See [`call_raw`](Self::call_raw).
+    ///
+    /// # Errors
+    ///
+    /// The function fails as [`evaluate`](Self::evaluate) does, but
+    /// does not decode the answer.
+    #[tracing::instrument(skip(self))]
+    pub fn evaluate_raw(&mut self, expression: &str) -> Result<CryptolResult> {
+        let response = self.evaluate_request(expression)?;
+        Ok(self.with_answer(response))
+    }
+
+    /// This function makes the request behind `evaluate`.
+    fn evaluate_request(&mut self, expression: &str) -> Result<CryptolResult> {
+        let mut params = self.session.params();
+        params.insert(
+            "expression".into(),
+            json!(self.substitute_definitions(expression)),
+        );
+
+        self.request("evaluate expression", &params)
+    }
+
+    /// This function evaluates the given expression, built with
+    /// [`CryptolExpr`](expr::CryptolExpr), in the context of the
+    /// loaded Cryptol module.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` without contacting
+    /// `cryptol-remote-api` if the expression cannot be rendered, and
+    /// otherwise fails as [`evaluate`](Self::evaluate) does.
+    pub fn evaluate_expr(&mut self, expr: &expr::CryptolExpr) -> Result<Answer> {
+        let expression = expr.render()?;
+        self.evaluate(&expression)
+    }
+
+    /// This function evaluates `body` with the given local definitions,
+    /// each a name and the Cryptol source of its value, as `body where
+    /// { ... }`. A definition can refer to the others, so a computation
+    /// can be written in steps:
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// cryptol_client.load_module("SuiteB").unwrap();
+    /// let answer = cryptol_client
+    ///     .evaluate_where(
+    ///         "sha384 (join [ipad, msg])",
+    ///         [("ipad", "repeat`{16} 0x36"), ("msg", "\"abc\"")],
+    ///     )
+    ///     .unwrap();
+    /// ```
+    ///
+    /// The body and the definitions are parenthesized, so each can be
+    /// any expression, including one with a `where` block of its own.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` without contacting
+    /// `cryptol-remote-api` if a name is not an unqualified Cryptol
+    /// identifier, and otherwise fails as [`evaluate`](Self::evaluate)
+    /// does.
+    pub fn evaluate_where<N: Into<String>, S: Into<String>>(
+        &mut self,
+        body: &str,
+        definitions: impl IntoIterator<Item = (N, S)>,
+    ) -> Result<Answer> {
+        let expr = definitions
+            .into_iter()
+            .fold(expr::CryptolExpr::source(body), |expr, (name, source)| {
+                expr.bind(name, expr::CryptolExpr::source(source))
+            });
+        self.evaluate_expr(&expr)
+    }
+
+    /// This function returns the type of the given Cryptol expression
+    /// in the context of the loaded Cryptol module.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// cryptol_client.load_module("SuiteB").unwrap();
+    /// let schema = cryptol_client.type_of("sha384").unwrap();
+    /// let (parameters, result) = schema.ty.parameters();
+    /// assert_eq!((parameters.len(), result.bit_width()), (1, Some(384)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the POST request to
+    /// `cryptol-remote-api` fails, e.g. because the expression does
+    /// not type check, or `Error::Decode` if the type cannot be
+    /// decoded.
+    #[tracing::instrument(skip(self))]
+    pub fn type_of(&mut self, expression: &str) -> Result<types::TypeSchema> {
+        let schema = self.type_schema_json(expression)?;
+        Ok(parse::decode_type_schema(&schema)?)
+    }
+
+    /// This function returns the type schema of the given Cryptol
+    /// expression, as encoded by `cryptol-remote-api`.
+    fn type_schema(&mut self, expression: &str) -> Result<Value> {
+        Ok(self.type_schema_json(expression)?.to_value())
+    }
+
+    /// This function returns the type schema of the given Cryptol
+    /// expression with its numbers in full.
+    fn type_schema_json(&mut self, expression: &str) -> Result<parse::Json> {
+        let mut params = self.session.params();
+        params.insert(
+            "expression".into(),
+            json!(self.substitute_definitions(expression)),
+        );
+
+        self.request("check type", &params)?;
+
+        let mut answer = parse::Json::parse(self.answer.as_deref().map_or("null", RawValue::get))?;
+        match answer.take("type schema") {
+            parse::Json::Null => Err(Error::Decode("missing type schema".to_string()).into()),
+            schema => Ok(schema),
+        }
+    }
+
+    /// This function returns the answer of the most recent request as
+    /// raw JSON, e.g. to decode it differently than `call` does,
+    /// leaving no answer stored in the client. It returns `None` if
+    /// the answer was already taken or the request failed.
+    pub fn take_last_answer(&mut self) -> Option<Box<RawValue>> {
+        self.answer.take()
+    }
+
+    /// This function moves the answer of the last request, whose
+    /// response was `response`, back into the response.
+    fn with_answer(&mut self, mut response: CryptolResult) -> CryptolResult {
+        response.answer = self.answer.take();
+        response
+    }
+
+    /// This function decodes the answer of the last request, whose
+    /// response was `response`, as the result of an evaluation.
+    fn decode_answer(&self, response: &CryptolResult) -> Result<Answer> {
+        let answer = self.answer.as_deref().map_or("null", RawValue::get);
+        let mut answer = parse::answer(answer)?;
+        answer.elapsed = response.elapsed;
+        answer.strict = self.strict;
+        answer.attach_output(response);
 
         Ok(answer)
     }
+
+    /// This function returns a handle that interrupts the requests
+    /// pending on `cryptol-remote-api`, which can be moved to another
+    /// thread, e.g. to cancel a long proof from a Ctrl-C handler. See
+    /// [`progress`].
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn interrupter(&self) -> progress::Interrupter {
+        progress::Interrupter::new(Arc::clone(self.session.transport()))
+    }
+
+    /// This function makes sure the transport has a connection open to
+    /// `cryptol-remote-api`, e.g. before timing a sequence of calls
+    /// after the client has been idle. See
+    /// [`Transport::warm_up`](transport::Transport::warm_up).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `cryptol-remote-api` cannot be
+    /// reached.
+    pub fn warm_up(&self) -> Result<()> {
+        Ok(self.session.transport().warm_up()?)
+    }
+
+    /// This function returns a batch for submitting several
+    /// independent requests from the current state in a single round
+    /// trip. See [`Batch`].
+    ///
+    /// A lazily connected client must have made a request before its
+    /// batches see its initial modules.
+    #[must_use]
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+
+    /// This function checks the arguments of a call against the type
+    /// of the function, which is looked up once per state.
+    fn check_call(&mut self, function: &str, arguments: &Args) -> Result<()> {
+        let key = (self.state_key(), function.to_string());
+        let parameters = if let Some(parameters) = self.signatures.get(&key) {
+            parameters.clone()
+        } else {
+            let parameters = handle::parameter_types(&self.type_schema(function)?);
+            // Looking up the type may have loaded the initial modules.
+            let key = (self.state_key(), function.to_string());
+            self.signatures.insert(key, parameters.clone());
+            parameters
+        };
+        let decoded: Vec<_> = arguments.decoded().collect();
+        Ok(handle::check_arguments(function, &parameters, &decoded)?)
+    }
+
+    /// This function returns the current state token, or an empty
+    /// string before the first request, for keying per-state caches.
+    fn state_key(&self) -> String {
+        self.session.state().unwrap_or_default().to_string()
+    }
+
+    /// This function creates the parameters for calling the given
+    /// function from the current state, warning if the function is
+    /// qualified with a module that has not been loaded. Definitions
+    /// are substituted into the arguments given as expressions.
+    fn call_params(&self, function: &str, arguments: &Args) -> Map<String, Value> {
+        if let Some((module, _)) = function.rsplit_once("::") {
+            if !self.modules.iter().any(|loaded| loaded == module) {
+                tracing::warn!(module, function, "module has not been loaded");
+            }
+        }
+
+        let mut params = self.session.params();
+        params.insert("function".into(), json!(function));
+        let arguments: Vec<Value> = arguments
+            .values()
+            .iter()
+            .zip(arguments.decoded())
+            .map(|(argument, decoded)| match (argument, decoded) {
+                (Value::String(expression), None) => {
+                    json!(self.substitute_definitions(expression))
+                }
+                _ => argument.clone(),
+            })
+            .collect();
+        params.insert("arguments".into(), json!(arguments));
+        params
+    }
 }
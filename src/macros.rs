@@ -0,0 +1,160 @@
+//! # Macros
+//!
+//! Macros for building Cryptol expressions out of Rust values without
+//! formatting them by hand, for converting Rust structs to and from
+//! Cryptol records, and for testing Rust code against Cryptol.
+
+/// Builds a Cryptol expression by splicing Rust values into a
+/// template. Each `{}` is replaced by the concrete syntax of the next
+/// argument, which must implement
+/// [`ToCryptolValue`](crate::convert::ToCryptolValue); `{{` and `}}`
+/// stand for literal braces. See [`splice`](crate::convert::splice).
+///
+/// Strings are spliced as sequences of bytes, never as source, so any
+/// string is safe to splice; see
+/// [`string_literal`](crate::convert::string_literal) for writing one
+/// as a string literal instead.
+///
+/// ```
+/// use cryptol_client::cryptol;
+///
+/// let key: [u8; 2] = [0xde, 0xad];
+/// let expression = cryptol!("aes {} {{a = {}}}", key, 1u8).unwrap();
+/// assert_eq!(expression, "aes [0xde, 0xad] {a = 0x01}");
+/// ```
+#[macro_export]
+macro_rules! cryptol {
+    ($template:expr $(, $arg:expr)* $(,)?) => {
+        $crate::convert::splice(
+            $template,
+            &[$($crate::convert::ToCryptolValue::to_cryptol_value(&$arg)),*],
+        )
+    };
+}
+
+/// Evaluates a Cryptol expression built by [`cryptol!`](crate::cryptol)
+/// using the given client. See
+/// [`CryptolClient::evaluate`](crate::CryptolClient::evaluate).
+///
+/// ```no_run
+/// use cryptol_client::{eval, CryptolClient};
+///
+/// let mut cryptol_client = CryptolClient::connect().unwrap();
+/// cryptol_client.load_module("SuiteB").unwrap();
+/// let bytes = b"Hello World";
+/// let answer = eval!(cryptol_client, "sha384 (join {})", bytes).unwrap();
+/// ```
+#[macro_export]
+macro_rules! eval {
+    ($client:expr, $template:expr $(, $arg:expr)* $(,)?) => {
+        match $crate::cryptol!($template $(, $arg)*) {
+            Ok(expression) => $client.evaluate(&expression),
+            Err(e) => Err(e.into()),
+        }
+    };
+}
+
+/// Implements [`ToCryptolValue`](crate::convert::ToCryptolValue) and
+/// [`FromCryptolValue`](crate::convert::FromCryptolValue) for a struct
+/// with named fields, converting it to and from a Cryptol record with
+/// the same field names. The fields must implement both traits.
+///
+/// ```
+/// use cryptol_client::convert::ToCryptolValue;
+/// use cryptol_client::cryptol_record;
+///
+/// struct Point {
+///     x: u8,
+///     y: u8,
+/// }
+///
+/// cryptol_record!(Point { x, y });
+///
+/// let point = Point { x: 1, y: 2 };
+/// assert_eq!(point.to_cryptol_value().to_expression(), "{x = 0x01, y = 0x02}");
+/// ```
+///
+/// A struct can then be passed as a record-typed argument with
+/// [`Args::value`](crate::Args::value), and returned by
+/// [`CryptolClient::call_as`](crate::CryptolClient::call_as).
+#[macro_export]
+macro_rules! cryptol_record {
+    ($ty:ty { $($field:ident),+ $(,)? }) => {
+        impl $crate::convert::ToCryptolValue for $ty {
+            fn to_cryptol_value(&self) -> $crate::value::CryptolValue {
+                $crate::value::CryptolValue::Record(
+                    [$((
+                        stringify!($field).to_string(),
+                        $crate::convert::ToCryptolValue::to_cryptol_value(&self.$field),
+                    )),+]
+                    .into_iter()
+                    .collect(),
+                )
+            }
+        }
+
+        impl $crate::convert::FromCryptolValue for $ty {
+            fn from_cryptol_value(
+                value: $crate::value::CryptolValue,
+            ) -> ::std::result::Result<Self, $crate::error::Error> {
+                let $crate::value::CryptolValue::Record(mut fields) = value else {
+                    return Err($crate::error::Error::Decode(format!(
+                        "expected a record, found {value:?}"
+                    )));
+                };
+                Ok(Self {
+                    $($field: $crate::convert::FromCryptolValue::from_cryptol_value(
+                        fields.remove(stringify!($field)).ok_or_else(|| {
+                            $crate::error::Error::Decode(format!(
+                                "missing field {} in record",
+                                stringify!($field)
+                            ))
+                        })?,
+                    )?,)+
+                })
+            }
+        }
+    };
+}
+
+/// Asserts that a Cryptol expression evaluates to a Rust value, using
+/// the given client. The value is converted with
+/// [`ToCryptolValue`](crate::convert::ToCryptolValue) and compared with
+/// [`CryptolValue::diff`](crate::value::CryptolValue::diff); on failure
+/// the macro panics with both values in Cryptol syntax and the places
+/// where they differ. Like `assert_eq!`, it takes an optional message.
+/// See [`CryptolClient::check_eq`](crate::CryptolClient::check_eq).
+///
+/// ```no_run
+/// use cryptol_client::{assert_cryptol_eq, cryptol, CryptolClient};
+///
+/// let mut cryptol_client = CryptolClient::connect().unwrap();
+/// let xs: [u8; 3] = [1, 2, 3];
+/// let expression = cryptol!("reverse {}", xs).unwrap();
+/// assert_cryptol_eq!(cryptol_client, expression, [3u8, 2, 1]);
+/// assert_cryptol_eq!(cryptol_client, "0x01 + 0x02", 3u8, "addition of {}", "bytes");
+/// ```
+///
+/// # Panics
+///
+/// The macro panics if the values differ, or if the expression cannot
+/// be evaluated or decoded.
+#[macro_export]
+macro_rules! assert_cryptol_eq {
+    ($client:expr, $expression:expr, $expected:expr $(,)?) => {
+        if let Err(e) = $client.check_eq(
+            &$expression,
+            &$crate::convert::ToCryptolValue::to_cryptol_value(&$expected),
+        ) {
+            panic!("{e}");
+        }
+    };
+    ($client:expr, $expression:expr, $expected:expr, $($message:tt)+) => {
+        if let Err(e) = $client.check_eq(
+            &$expression,
+            &$crate::convert::ToCryptolValue::to_cryptol_value(&$expected),
+        ) {
+            panic!("{e}\n{}", format_args!($($message)+));
+        }
+    };
+}
@@ -0,0 +1,91 @@
+//! # Request metrics
+//!
+//! This module defines the hook through which a `CryptolClient`
+//! reports every request it makes to `cryptol-remote-api`. Operators
+//! implement [`Metrics`] to feed counters (requests by method and
+//! outcome) and histograms (latency and payload size) into whatever
+//! metrics system their service already uses.
+
+use std::fmt;
+use std::time::Duration;
+
+/// The outcome of a single request to `cryptol-remote-api`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// The server answered the request successfully.
+    Success,
+    /// The server answered the request with a JSON-RPC error, for
+    /// example because a module could not be found or an expression
+    /// did not type check.
+    ServerError,
+    /// The request did not complete before the request timeout.
+    Timeout,
+    /// The request failed before an answer was received, for example
+    /// because the connection was refused or the response could not
+    /// be decoded.
+    TransportError,
+}
+
+impl Outcome {
+    /// A short, stable name for this outcome, suitable for use as a
+    /// metric label.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::ServerError => "server_error",
+            Outcome::Timeout => "timeout",
+            Outcome::TransportError => "transport_error",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A description of one completed request, handed to
+/// [`Metrics::record`].
+#[derive(Debug, Clone, Copy)]
+pub struct RequestEvent<'a> {
+    /// The JSON-RPC method, e.g. `"load module"` or `"call"`.
+    pub method: &'a str,
+    /// How the request ended.
+    pub outcome: Outcome,
+    /// The round-trip time of the request.
+    pub latency: Duration,
+    /// The size in bytes of the response body, if one was received.
+    pub payload_size: Option<usize>,
+}
+
+/// A sink for request metrics.
+///
+/// `record` is called once per request, after the request has
+/// completed (successfully or not), on the thread that issued the
+/// request. Implementations should be cheap and must not panic.
+///
+/// For example, a counter of timeouts per method:
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::sync::Mutex;
+///
+/// use cryptol_client::metrics::{Metrics, Outcome, RequestEvent};
+///
+/// #[derive(Default)]
+/// struct Timeouts(Mutex<HashMap<String, u64>>);
+///
+/// impl Metrics for Timeouts {
+///     fn record(&self, event: &RequestEvent<'_>) {
+///         if event.outcome == Outcome::Timeout {
+///             *self.0.lock().unwrap().entry(event.method.to_string()).or_default() += 1;
+///         }
+///     }
+/// }
+/// ```
+pub trait Metrics: Send + Sync {
+    /// Record one completed request.
+    fn record(&self, event: &RequestEvent<'_>);
+}
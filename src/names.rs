@@ -0,0 +1,120 @@
+//! # Names
+//!
+//! `cryptol-remote-api` reports the names in scope in the current
+//! state, with their types and documentation, through its
+//! `visible names` method. `CryptolClient::describe` looks up a single
+//! name there, e.g. to show it in an editor tooltip or a `:help`
+//! command, and `CryptolClient::module_declarations` lists the names
+//! a module declares, e.g. to generate bindings for them.
+
+use serde_json::value::RawValue;
+
+use crate::error::Error;
+use crate::types::TypeSchema;
+use crate::{parse, CryptolClient, Result};
+
+/// What kind of declaration a name is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationKind {
+    /// A declaration with a function type.
+    Function,
+    /// A declaration marked with the `property` keyword.
+    Property,
+    /// Any other declaration, e.g. a constant.
+    Value,
+}
+
+/// A name in scope, as reported by `cryptol-remote-api`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Description {
+    /// The name, e.g. `sha384`.
+    pub name: String,
+    /// The module that defines the name, e.g. `SuiteB`.
+    pub module: String,
+    /// The type of the name as printed by Cryptol.
+    pub type_string: String,
+    /// The type of the name.
+    pub schema: TypeSchema,
+    /// The documentation comment of the name, if it has one.
+    pub documentation: Option<String>,
+    /// What kind of declaration the name is.
+    pub kind: DeclarationKind,
+}
+
+impl Description {
+    /// This function returns the name qualified with its module, e.g.
+    /// `SuiteB::sha384`.
+    #[must_use]
+    pub fn qualified_name(&self) -> String {
+        format!("{}::{}", self.module, self.name)
+    }
+}
+
+impl CryptolClient {
+    /// This function describes the given name, which may be qualified
+    /// with its module, as it is in scope in the loaded Cryptol module.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let reverse = cryptol_client.describe("reverse").unwrap();
+    /// println!("{} : {}", reverse.qualified_name(), reverse.type_string);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if the name is not in
+    /// scope, or an error if the POST request to `cryptol-remote-api`
+    /// fails.
+    #[tracing::instrument(skip(self))]
+    pub fn describe(&mut self, name: &str) -> Result<Description> {
+        let (module, base) = match name.rsplit_once("::") {
+            Some((module, base)) => (Some(module), base),
+            None => (None, name),
+        };
+
+        self.visible_names()?
+            .into_iter()
+            .find(|description| {
+                description.name == base && module.is_none_or(|m| m == description.module)
+            })
+            .ok_or_else(|| Error::Decode(format!("{name} is not in scope")).into())
+    }
+
+    /// This function lists the names declared in the given Cryptol
+    /// module, in the order `cryptol-remote-api` reports them. Names
+    /// the module imports are not included.
+    ///
+    /// The module is loaded in a copy of the client, so the state of
+    /// this client is unchanged.
+    ///
+    /// `cryptol-remote-api` only reports the values in scope, so type
+    /// synonyms and the types of newtypes are not listed. The
+    /// constructor of a newtype is listed as a function.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `module` cannot be loaded, or
+    /// if a POST request to `cryptol-remote-api` fails.
+    #[tracing::instrument(skip(self))]
+    pub fn module_declarations(&mut self, module: &str) -> Result<Vec<Description>> {
+        let mut client = self.clone();
+        client.load_module(module)?;
+
+        Ok(client
+            .visible_names()?
+            .into_iter()
+            .filter(|description| description.module == module)
+            .collect())
+    }
+
+    /// This function returns the names in scope in the current state.
+    pub(crate) fn visible_names(&mut self) -> Result<Vec<Description>> {
+        let params = self.session.params();
+        self.request("visible names", &params)?;
+
+        let answer = parse::Json::parse(self.answer.as_deref().map_or("null", RawValue::get))?;
+        Ok(parse::decode_visible_names(&answer)?)
+    }
+}
@@ -0,0 +1,87 @@
+//! # Parallel calls
+//!
+//! `CryptolClient::par_map` calls a Cryptol function on many inputs at
+//! once, the common shape of a differential test. The calls are spread
+//! over a fixed number of worker threads, each with its own fork of the
+//! client. The forks share the transport, whose pool of connections
+//! carries their requests concurrently.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::error::Error;
+use crate::{Answer, Args, CryptolClient, Result};
+
+impl CryptolClient {
+    /// This function calls the given function on each of `inputs`, with
+    /// at most `concurrency` calls in flight at a time, and returns the
+    /// result of each call in the order of the inputs. A call that
+    /// fails does not stop the others. See [`call`](Self::call).
+    ///
+    /// Every call is made from the current state, and the state of the
+    /// client is unchanged afterwards.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let inputs = (0..1000).map(|i| vec![format!("[{i}, 0, 0] : [3][16]")]);
+    /// let results = cryptol_client.par_map("reverse", inputs, 8).unwrap();
+    /// assert_eq!(results.len(), 1000);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the initial modules of a lazy
+    /// connection cannot be loaded. Errors of the calls themselves are
+    /// returned in their place among the results.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if a worker thread panics.
+    pub fn par_map<A: Into<Args>>(
+        &mut self,
+        function: &str,
+        inputs: impl IntoIterator<Item = A>,
+        concurrency: usize,
+    ) -> Result<Vec<std::result::Result<Answer, Error>>> {
+        // Load the initial modules once rather than in every fork.
+        self.load_pending_modules()?;
+
+        let inputs: Vec<Args> = inputs.into_iter().map(Into::into).collect();
+        let results = Mutex::new(Vec::with_capacity(inputs.len()));
+        let next = AtomicUsize::new(0);
+        let workers = concurrency.clamp(1, inputs.len().max(1));
+        tracing::debug!(
+            function,
+            inputs = inputs.len(),
+            workers,
+            "calling in parallel"
+        );
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let mut client = self.clone();
+                let (inputs, results, next) = (&inputs, &results, &next);
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(arguments) = inputs.get(i) else {
+                        break;
+                    };
+                    let result = client.call(function, arguments.clone()).map_err(|e| {
+                        // Errors of the client are all of the crate's
+                        // type, except for those decoding its answer.
+                        e.downcast::<Error>()
+                            .map_or_else(|e| Error::Decode(e.to_string()), |e| *e)
+                    });
+                    results.lock().unwrap().push((i, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_unstable_by_key(|(i, _)| *i);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+}
@@ -0,0 +1,717 @@
+//! # Parsing
+//!
+//! Pure functions that decode the JSON produced by
+//! `cryptol-remote-api`. They perform no I/O, never panic, and report
+//! malformed input as `Error::Decode`, so they can be exercised
+//! directly by fuzz targets and property tests.
+//!
+//! ```
+//! use cryptol_client::parse;
+//! use cryptol_client::value::CryptolValue;
+//!
+//! let value = serde_json::json!({"expression": "sequence", "data": [true, false]});
+//! assert_eq!(
+//!     parse::value(&value).unwrap(),
+//!     CryptolValue::Sequence(vec![CryptolValue::Bit(true), CryptolValue::Bit(false)])
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::Index;
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::check::{CheckOutcome, CheckResult};
+use crate::error::{CryptolError, Error};
+use crate::names::{DeclarationKind, Description};
+use crate::prove::ProveOutcome;
+use crate::types::{CryptolType, TypeParameter, TypeSchema};
+use crate::value::{BitVector, CryptolValue, Integer};
+use crate::{Answer, CryptolResult};
+
+/// A JSON-RPC response, before its `result` is decoded.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(default)]
+    result: Option<Box<RawValue>>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+impl Envelope {
+    fn into_result(self) -> Result<Box<RawValue>, Error> {
+        match (self.result, self.error) {
+            (_, Some(error)) => Err(server_error(&error)?.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(Error::Decode(
+                "response has neither a result nor an error".to_string(),
+            )),
+        }
+    }
+}
+
+/// This function decodes a complete JSON-RPC response, e.g.
+///   `{"jsonrpc":"2.0","id":0,"result":{"answer":[],"state":"a4909ccf-3ef9-45cc-913b-57e58da75788","stderr":"","stdout":""}}`
+/// and returns its `result`, undecoded.
+///
+/// # Errors
+///
+/// The function returns `Error::Server` if the response carries an
+/// `error` instead of a `result`, and `Error::Decode` if `text` is not
+/// a JSON-RPC response.
+pub fn rpc_response(text: &str) -> Result<Box<RawValue>, Error> {
+    serde_json::from_str::<Envelope>(text)
+        .map_err(|e| Error::Decode(e.to_string()))?
+        .into_result()
+}
+
+/// This function decodes the response to a JSON-RPC batch of `len`
+/// requests with ids `0` to `len - 1`, and returns the outcome of each
+/// request in the order of the ids.
+///
+/// # Errors
+///
+/// The function returns an error if `text` is not a batch response,
+/// e.g. if the server rejected the whole batch with a single error.
+/// A missing or failed response is reported in the corresponding
+/// entry.
+#[allow(clippy::type_complexity)]
+pub fn rpc_batch_response(
+    text: &str,
+    len: usize,
+) -> Result<Vec<Result<Box<RawValue>, Error>>, Error> {
+    let envelopes: Vec<Envelope> = match serde_json::from_str(text) {
+        Ok(envelopes) => envelopes,
+        Err(e) => {
+            return Err(match rpc_response(text) {
+                Err(error @ Error::Server(_)) => error,
+                _ => Error::Decode(e.to_string()),
+            })
+        }
+    };
+
+    let mut outcomes: Vec<Option<Result<Box<RawValue>, Error>>> = (0..len).map(|_| None).collect();
+    for envelope in envelopes {
+        let slot = envelope
+            .id
+            .as_ref()
+            .and_then(Value::as_u64)
+            .and_then(|id| usize::try_from(id).ok())
+            .and_then(|id| outcomes.get_mut(id))
+            .ok_or_else(|| Error::Decode(format!("unexpected response id {:?}", envelope.id)))?;
+        *slot = Some(envelope.into_result());
+    }
+
+    Ok(outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(id, outcome)| {
+            outcome.unwrap_or_else(|| Err(Error::Decode(format!("no response for request {id}"))))
+        })
+        .collect())
+}
+
+/// This function decodes the `result` of a successful JSON-RPC
+/// response, e.g.
+///   `{"answer":[],"state":"a4909ccf-3ef9-45cc-913b-57e58da75788","stderr":"","stdout":""}`
+///
+/// The state of a query made in the initial state of the server, e.g.
+/// `version`, may be `null`, which is decoded as an empty state; it
+/// must not be missing.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `text` is not such an
+/// object.
+pub fn response(text: &str) -> Result<CryptolResult, Error> {
+    serde_json::from_str(text).map_err(|e| Error::Decode(e.to_string()))
+}
+
+/// This function deserializes a value that may be `null`, which is
+/// taken to be the default.
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// This function decodes the `answer` of a successful `call`, which
+/// carries the value along with its type, from its raw JSON text.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `answer` does not have the
+/// shape of a `call` answer.
+pub fn answer(answer: &str) -> Result<Answer, Error> {
+    // Most answers only hold numbers that fit in 64 bits, which a
+    // `Value` keeps exactly.
+    if let Ok(decoded) = serde_json::from_str::<Answer>(answer) {
+        if !rounded(&decoded.value) && !rounded(&decoded.answer_type) {
+            return Ok(decoded);
+        }
+    }
+
+    let mut decoded: Answer = serde_json::from_value(Json::parse(answer)?.to_value())
+        .map_err(|e| Error::Decode(e.to_string()))?;
+    decoded.text = Some(answer.to_string());
+    Ok(decoded)
+}
+
+/// This function returns whether `value` holds a number that `Value`
+/// rounded, i.e. one that does not fit in 64 bits.
+fn rounded(value: &Value) -> bool {
+    match value {
+        Value::Number(n) => n.is_f64(),
+        Value::Array(values) => values.iter().any(rounded),
+        Value::Object(object) => object.values().any(rounded),
+        Value::Null | Value::Bool(_) | Value::String(_) => false,
+    }
+}
+
+/// This function decodes the `error` object of a failed JSON-RPC
+/// response. See [`CryptolError`] for an example.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `error` lacks a `code` or
+/// `message`.
+pub fn server_error(error: &Value) -> Result<CryptolError, Error> {
+    CryptolError::deserialize(error).map_err(|e| Error::Decode(e.to_string()))
+}
+
+/// This function decodes a value in the JSON encoding used by
+/// `cryptol-remote-api`, e.g. the `value` of an [`Answer`].
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `value` is not a valid
+/// encoding of a Cryptol value.
+pub fn value(value: &Value) -> Result<CryptolValue, Error> {
+    decode_value(&Json::from(value))
+}
+
+/// This function decodes a value from JSON whose numbers are kept in
+/// full. See [`value`].
+pub(crate) fn decode_value(value: &Json) -> Result<CryptolValue, Error> {
+    match value {
+        Json::Bool(b) => Ok(CryptolValue::Bit(*b)),
+        Json::Number(n) => Ok(CryptolValue::Integer(integer(n)?)),
+        Json::Object(object) => {
+            let tag = object
+                .get("expression")
+                .and_then(Json::as_str)
+                .ok_or_else(|| Error::Decode(format!("missing expression tag in {value}")))?;
+            let field = |name: &str| {
+                object
+                    .get(name)
+                    .ok_or_else(|| Error::Decode(format!("missing field {name:?} in {tag}")))
+            };
+
+            match tag {
+                "unit" => Ok(CryptolValue::Unit),
+                "bits" => {
+                    let width = field("width")?
+                        .as_u64()
+                        .and_then(|w| usize::try_from(w).ok())
+                        .ok_or_else(|| Error::Decode("invalid bitvector width".to_string()))?;
+                    let data = field("data")?
+                        .as_str()
+                        .ok_or_else(|| Error::Decode("invalid bitvector data".to_string()))?;
+                    match field("encoding")?.as_str() {
+                        Some("hex") => {
+                            Ok(CryptolValue::BitVector(BitVector::from_hex(width, data)?))
+                        }
+                        Some("base64") => Ok(CryptolValue::BitVector(BitVector::from_base64(
+                            width, data,
+                        )?)),
+                        _ => Err(Error::Decode(format!(
+                            "unknown bitvector encoding {}",
+                            field("encoding")?
+                        ))),
+                    }
+                }
+                "sequence" => Ok(CryptolValue::Sequence(elements(field("data")?)?)),
+                "tuple" => Ok(CryptolValue::Tuple(elements(field("data")?)?)),
+                "record" => match field("data")? {
+                    Json::Object(fields) => fields
+                        .iter()
+                        .map(|(name, v)| Ok((name.clone(), decode_value(v)?)))
+                        .collect::<Result<_, Error>>()
+                        .map(CryptolValue::Record),
+                    _ => Err(Error::Decode("record data is not an object".to_string())),
+                },
+                "integer modulo" => {
+                    let integer = |name| match field(name)? {
+                        Json::Number(n) => self::integer(n),
+                        _ => Err(Error::Decode(format!("{name} is not a number"))),
+                    };
+                    Ok(CryptolValue::IntegerModulo {
+                        value: integer("integer")?,
+                        modulus: integer("modulus")?,
+                    })
+                }
+                "variable" => field("identifier")?
+                    .as_str()
+                    .map(|identifier| CryptolValue::Opaque(identifier.to_string()))
+                    .ok_or_else(|| Error::Decode("identifier is not a string".to_string())),
+                _ => Err(Error::Decode(format!("unknown expression tag {tag:?}"))),
+            }
+        }
+        _ => Err(Error::Decode(format!("not a Cryptol value: {value}"))),
+    }
+}
+
+/// This function decodes the answer of `visible names`, a list of the
+/// names in scope with their types and documentation.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `answer` is not a list of
+/// valid name descriptions.
+pub fn visible_names(answer: &Value) -> Result<Vec<Description>, Error> {
+    decode_visible_names(&Json::from(answer))
+}
+
+/// This function decodes the answer of `visible names` from JSON whose
+/// numbers are kept in full. See [`visible_names`].
+pub(crate) fn decode_visible_names(answer: &Json) -> Result<Vec<Description>, Error> {
+    let Json::Array(names) = answer else {
+        return Err(Error::Decode("visible names is not an array".to_string()));
+    };
+
+    names
+        .iter()
+        .map(|name| {
+            let string = |field: &str| {
+                name[field].as_str().map(str::to_string).ok_or_else(|| {
+                    Error::Decode(format!("missing field {field:?} in visible name"))
+                })
+            };
+            let schema = decode_type_schema(&name["type"])?;
+            let property = matches!(&name["pragmas"], Json::Array(pragmas)
+                if pragmas.iter().any(|p| p.as_str() == Some("property")));
+            let kind = if property {
+                DeclarationKind::Property
+            } else if schema.ty.is_function() {
+                DeclarationKind::Function
+            } else {
+                DeclarationKind::Value
+            };
+            Ok(Description {
+                name: string("name")?,
+                module: string("module")?,
+                type_string: string("type string")?,
+                schema,
+                documentation: name["documentation"].as_str().map(str::to_string),
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// This function decodes the answer of `check`, the result of testing
+/// a property.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `answer` is not a valid
+/// check result, e.g. because an argument is not a valid encoding of
+/// a Cryptol value.
+pub fn check_result(answer: &Value) -> Result<CheckResult, Error> {
+    decode_check_result(&Json::from(answer))
+}
+
+/// This function decodes the answer of `check` from JSON whose numbers
+/// are kept in full. See [`check_result`].
+pub(crate) fn decode_check_result(answer: &Json) -> Result<CheckResult, Error> {
+    let tests_run = answer["tests run"]
+        .as_u64()
+        .ok_or_else(|| Error::Decode("missing field \"tests run\" in check result".to_string()))?;
+    let tests_possible = match &answer["tests possible"] {
+        Json::Null => None,
+        Json::Number(n) => Some(integer(n)?),
+        _ => return Err(Error::Decode("tests possible is not a number".to_string())),
+    };
+    let arguments = || match &answer["arguments"] {
+        Json::Array(arguments) => arguments
+            .iter()
+            .map(|argument| decode_value(&argument["expr"]))
+            .collect::<Result<Vec<_>, Error>>(),
+        _ => Err(Error::Decode(
+            "missing arguments in check result".to_string(),
+        )),
+    };
+    let outcome = match answer["result"].as_str() {
+        Some("pass") => CheckOutcome::Passed,
+        Some("fail") => CheckOutcome::Failed {
+            counterexample: arguments()?,
+        },
+        Some("error") => CheckOutcome::Error {
+            message: answer["error message"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            arguments: arguments()?,
+        },
+        _ => {
+            return Err(Error::Decode(format!(
+                "unknown check result {}",
+                answer["result"]
+            )))
+        }
+    };
+    Ok(CheckResult {
+        outcome,
+        tests_run,
+        tests_possible,
+    })
+}
+
+/// This function decodes the answer of `prove or satisfy` to a proof,
+/// e.g. `{"result": "unsatisfiable"}` for a property that holds.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `answer` is not the result
+/// of a proof, e.g. because an argument of the counterexample is not a
+/// valid encoding of a Cryptol value.
+pub fn prove_outcome(answer: &Value) -> Result<ProveOutcome, Error> {
+    decode_prove_outcome(&Json::from(answer))
+}
+
+/// This function decodes the answer of a proof from JSON whose numbers
+/// are kept in full. See [`prove_outcome`].
+pub(crate) fn decode_prove_outcome(answer: &Json) -> Result<ProveOutcome, Error> {
+    let counterexample = || match &answer["counterexample"] {
+        Json::Array(arguments) => arguments
+            .iter()
+            .map(|argument| decode_value(&argument["expr"]))
+            .collect::<Result<Vec<_>, Error>>(),
+        _ => Err(Error::Decode(
+            "missing counterexample in proof result".to_string(),
+        )),
+    };
+    match answer["result"].as_str() {
+        Some("unsatisfiable") => Ok(ProveOutcome::Proved),
+        Some("invalid") => match answer["counterexample type"].as_str() {
+            Some("predicate falsified") => Ok(ProveOutcome::Falsified {
+                counterexample: counterexample()?,
+            }),
+            Some("safety violation") => Ok(ProveOutcome::SafetyViolation {
+                counterexample: counterexample()?,
+            }),
+            _ => Err(Error::Decode(format!(
+                "unknown counterexample type {}",
+                answer["counterexample type"]
+            ))),
+        },
+        _ => Err(Error::Decode(format!(
+            "unknown proof result {}",
+            answer["result"]
+        ))),
+    }
+}
+
+/// This function decodes a type schema in the JSON encoding used by
+/// `cryptol-remote-api`, e.g. the answer of `check type`.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `schema` is not a valid
+/// encoding of a type schema.
+pub fn type_schema(schema: &Value) -> Result<TypeSchema, Error> {
+    decode_type_schema(&Json::from(schema))
+}
+
+/// This function decodes a type schema from JSON whose numbers are
+/// kept in full. See [`type_schema`].
+pub(crate) fn decode_type_schema(schema: &Json) -> Result<TypeSchema, Error> {
+    let forall = match &schema["forall"] {
+        Json::Array(forall) => forall
+            .iter()
+            .map(|parameter| {
+                let name = parameter["name"]
+                    .as_str()
+                    .ok_or_else(|| Error::Decode(format!("invalid type parameter {parameter}")))?;
+                let kind = match &parameter["kind"] {
+                    Json::String(kind) => kind.clone(),
+                    kind => kind.to_string(),
+                };
+                Ok(TypeParameter {
+                    name: name.to_string(),
+                    kind,
+                })
+            })
+            .collect::<Result<_, Error>>()?,
+        Json::Null => Vec::new(),
+        _ => return Err(Error::Decode("forall is not an array".to_string())),
+    };
+    let propositions = match &schema["propositions"] {
+        Json::Array(propositions) => propositions.iter().map(Json::to_value).collect(),
+        Json::Null => Vec::new(),
+        _ => return Err(Error::Decode("propositions is not an array".to_string())),
+    };
+
+    Ok(TypeSchema {
+        forall,
+        propositions,
+        ty: decode_cryptol_type(&schema["type"])?,
+    })
+}
+
+/// This function decodes a type in the JSON encoding used by
+/// `cryptol-remote-api`. Types it does not know are returned as
+/// `CryptolType::Other`.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `ty` is not a JSON object
+/// with a `type` tag, or if a known type is malformed.
+pub fn cryptol_type(ty: &Value) -> Result<CryptolType, Error> {
+    decode_cryptol_type(&Json::from(ty))
+}
+
+/// This function decodes a type from JSON whose numbers are kept in
+/// full. See [`cryptol_type`].
+pub(crate) fn decode_cryptol_type(ty: &Json) -> Result<CryptolType, Error> {
+    let Some(tag) = ty["type"].as_str() else {
+        return Err(Error::Decode(format!("not a Cryptol type: {ty}")));
+    };
+    let field = |name: &str| match ty.get(name) {
+        Some(field) => decode_cryptol_type(field).map(Box::new),
+        None => Err(Error::Decode(format!("missing field {name:?} in {tag}"))),
+    };
+
+    Ok(match tag {
+        "variable" => match ty["name"].as_str() {
+            Some(name) => CryptolType::Variable(name.to_string()),
+            None => return Err(Error::Decode("type variable has no name".to_string())),
+        },
+        "number" => match &ty["value"] {
+            Json::Number(n) => match integer(n) {
+                Ok(n) => CryptolType::Number(n),
+                Err(_) => CryptolType::Other(ty.to_value()),
+            },
+            _ => CryptolType::Other(ty.to_value()),
+        },
+        "inf" => CryptolType::Infinity,
+        "Bit" => CryptolType::Bit,
+        "Integer" => CryptolType::Integer,
+        "Rational" => CryptolType::Rational,
+        "Z" => CryptolType::Z(field("modulus")?),
+        "bitvector" => CryptolType::BitVector(field("width")?),
+        "sequence" => CryptolType::Sequence {
+            length: field("length")?,
+            contents: field("contents")?,
+        },
+        "function" => CryptolType::Function {
+            domain: field("domain")?,
+            range: field("range")?,
+        },
+        "unit" => CryptolType::Unit,
+        "tuple" => match &ty["contents"] {
+            Json::Array(contents) => CryptolType::Tuple(
+                contents
+                    .iter()
+                    .map(decode_cryptol_type)
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(Error::Decode("tuple contents is not an array".to_string())),
+        },
+        "record" => match &ty["fields"] {
+            Json::Object(fields) => CryptolType::Record(
+                fields
+                    .iter()
+                    .map(|(name, field)| Ok((name.clone(), decode_cryptol_type(field)?)))
+                    .collect::<Result<_, Error>>()?,
+            ),
+            _ => return Err(Error::Decode("record fields is not an object".to_string())),
+        },
+        _ => CryptolType::Other(ty.to_value()),
+    })
+}
+
+/// This function decodes the elements of a sequence or tuple.
+fn elements(data: &Json) -> Result<Vec<CryptolValue>, Error> {
+    match data {
+        Json::Array(elements) => elements.iter().map(decode_value).collect(),
+        _ => Err(Error::Decode("data is not an array".to_string())),
+    }
+}
+
+/// This function decodes an integer, which the server sends as a JSON
+/// number of any size, from the text of the number.
+fn integer(n: &str) -> Result<Integer, Error> {
+    n.parse()
+        .map_err(|_| Error::Decode(format!("{n} is not an integer")))
+}
+
+/// How deeply [`Json::parse`] lets values nest, the same limit as
+/// `serde_json`'s, so that hostile input cannot exhaust the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value that keeps the text of its numbers.
+///
+/// The server sends Cryptol integers as JSON numbers of any size. A
+/// `Value` rounds numbers that do not fit in 64 bits, and rejects
+/// those beyond the range of an `f64`, unless `serde_json`'s
+/// `arbitrary_precision` feature is on. That feature cannot be turned
+/// on here, since it would change `Value` for every crate built with
+/// this one, so the decoders read this instead.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    /// A number, as the text it was sent as, e.g. `-12` or `1e3`.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+/// What indexing a `Json` returns for a field it does not have.
+static NULL: Json = Json::Null;
+
+impl Json {
+    /// This function parses JSON text.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` if `text` is not valid
+    /// JSON, or nests more than [`MAX_DEPTH`] levels deep.
+    pub(crate) fn parse(text: &str) -> Result<Json, Error> {
+        let raw: &RawValue =
+            serde_json::from_str(text).map_err(|e| Error::Decode(e.to_string()))?;
+        Json::from_raw(raw, 0)
+    }
+
+    /// This function converts valid JSON text, read one level at a
+    /// time so that numbers are only ever seen as text.
+    fn from_raw(raw: &RawValue, depth: usize) -> Result<Json, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::Decode("JSON is nested too deeply".to_string()));
+        }
+        let text = raw.get().trim();
+        let decode_error = |e: serde_json::Error| Error::Decode(e.to_string());
+        match text.as_bytes().first() {
+            Some(b'{') => serde_json::from_str::<BTreeMap<String, &RawValue>>(text)
+                .map_err(decode_error)?
+                .into_iter()
+                .map(|(name, field)| Ok((name, Json::from_raw(field, depth + 1)?)))
+                .collect::<Result<_, Error>>()
+                .map(Json::Object),
+            Some(b'[') => serde_json::from_str::<Vec<&RawValue>>(text)
+                .map_err(decode_error)?
+                .into_iter()
+                .map(|element| Json::from_raw(element, depth + 1))
+                .collect::<Result<_, Error>>()
+                .map(Json::Array),
+            Some(b'-' | b'0'..=b'9') => Ok(Json::Number(text.to_string())),
+            _ => Ok(match serde_json::from_str(text).map_err(decode_error)? {
+                Value::Bool(b) => Json::Bool(b),
+                Value::String(s) => Json::String(s),
+                _ => Json::Null,
+            }),
+        }
+    }
+
+    /// This function returns the field `name` of an object.
+    pub(crate) fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(object) => object.get(name),
+            _ => None,
+        }
+    }
+
+    /// This function removes and returns the field `name` of an
+    /// object, or `Json::Null` if there is none.
+    pub(crate) fn take(&mut self, name: &str) -> Json {
+        match self {
+            Json::Object(object) => object.remove(name).unwrap_or(Json::Null),
+            _ => Json::Null,
+        }
+    }
+
+    /// This function returns the string this is, if it is one.
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// This function returns the number this is, if it is one that
+    /// fits in a `u64`.
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// This function converts to a `Value`. Numbers that do not fit
+    /// in 64 bits become strings of their text, which the server still
+    /// reads as the same Cryptol literal.
+    pub(crate) fn to_value(&self) -> Value {
+        match self {
+            Json::Null => Value::Null,
+            Json::Bool(b) => Value::Bool(*b),
+            Json::Number(n) => n
+                .parse::<u64>()
+                .map(Value::from)
+                .or_else(|_| n.parse::<i64>().map(Value::from))
+                .unwrap_or_else(|_| Value::String(n.clone())),
+            Json::String(s) => Value::String(s.clone()),
+            Json::Array(values) => Value::Array(values.iter().map(Json::to_value).collect()),
+            Json::Object(object) => Value::Object(
+                object
+                    .iter()
+                    .map(|(name, field)| (name.clone(), field.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<&Value> for Json {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Json::Null,
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Number(n) => Json::Number(n.to_string()),
+            Value::String(s) => Json::String(s.clone()),
+            Value::Array(values) => Json::Array(values.iter().map(Json::from).collect()),
+            Value::Object(object) => Json::Object(
+                object
+                    .iter()
+                    .map(|(name, field)| (name.clone(), Json::from(field)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Index<&str> for Json {
+    type Output = Json;
+
+    /// This function returns the field `name` of an object, or
+    /// `Json::Null` if there is none, like indexing a `Value`.
+    fn index(&self, name: &str) -> &Json {
+        self.get(name).unwrap_or(&NULL)
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Number(n) => f.write_str(n),
+            json => write!(f, "{}", json.to_value()),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! # Progress
+//!
+//! Calls, evaluations and proofs can take a long time. With
+//! [`CryptolClientBuilder::progress`](crate::CryptolClientBuilder::progress),
+//! a callback is invoked at a fixed interval while a request is
+//! pending, e.g. to update a spinner. The callback can cancel the
+//! request by returning [`Control::Interrupt`], which sends an
+//! `interrupt` request to `cryptol-remote-api`; the pending request
+//! then fails with the error the server reports.
+//!
+//! An [`Interrupter`], obtained with
+//! [`CryptolClient::interrupter`](crate::CryptolClient::interrupter),
+//! does the same from anywhere else, e.g. a Ctrl-C handler.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use cryptol_client::progress::{Control, ProgressEvent};
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::builder()
+//!     .progress(Duration::from_secs(1), |event: &ProgressEvent<'_>| {
+//!         eprintln!("{} running for {:?}", event.method, event.elapsed);
+//!         if event.elapsed > Duration::from_secs(60) {
+//!             Control::Interrupt
+//!         } else {
+//!             Control::Continue
+//!         }
+//!     })
+//!     .connect()
+//!     .unwrap();
+//! ```
+
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Map;
+
+use crate::error::Error;
+use crate::transport::Transport;
+
+/// A tick of a pending request, handed to the progress callback.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressEvent<'a> {
+    /// The JSON-RPC method, e.g. `"call"` or `"evaluate expression"`.
+    pub method: &'a str,
+    /// How long the request has been pending.
+    pub elapsed: Duration,
+}
+
+/// What a progress callback asks of the pending request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// Keep waiting for the request.
+    Continue,
+    /// Interrupt the request.
+    Interrupt,
+}
+
+/// A progress callback.
+///
+/// It is called on a separate thread while the request is pending,
+/// once every interval, and should return quickly.
+pub type ProgressCallback = dyn Fn(&ProgressEvent<'_>) -> Control + Send + Sync;
+
+/// A handle that interrupts the requests pending on
+/// `cryptol-remote-api`, e.g. from another thread.
+#[derive(Clone)]
+pub struct Interrupter {
+    transport: Arc<dyn Transport>,
+}
+
+impl fmt::Debug for Interrupter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interrupter")
+            .field("transport", &self.transport)
+            .finish()
+    }
+}
+
+impl Interrupter {
+    /// This function returns a handle that interrupts the requests
+    /// sent through `transport`.
+    pub(crate) fn new(transport: Arc<dyn Transport>) -> Self {
+        Interrupter { transport }
+    }
+
+    /// This function asks `cryptol-remote-api` to interrupt the
+    /// requests it is working on. It does nothing if there are none.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the `interrupt` request fails.
+    pub fn interrupt(&self) -> Result<(), Error> {
+        tracing::info!("interrupting pending requests");
+        self.transport.request("interrupt", &Map::new()).map(drop)
+    }
+}
+
+/// This function runs `request`, a request with the given `method`,
+/// while calling `callback` every `interval` on a separate thread until
+/// it completes. The first time the callback returns
+/// `Control::Interrupt`, the request is interrupted through
+/// `transport`.
+pub(crate) fn watch<T>(
+    transport: &dyn Transport,
+    method: &str,
+    interval: Duration,
+    callback: &ProgressCallback,
+    request: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let (done, wait) = mpsc::channel::<()>();
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut interrupted = false;
+            while let Err(RecvTimeoutError::Timeout) = wait.recv_timeout(interval) {
+                let event = ProgressEvent {
+                    method,
+                    elapsed: start.elapsed(),
+                };
+                if callback(&event) == Control::Interrupt && !interrupted {
+                    interrupted = true;
+                    tracing::info!(method, "interrupting request");
+                    if let Err(e) = transport.request("interrupt", &Map::new()) {
+                        tracing::warn!(error = %e, "interrupt failed");
+                    }
+                }
+            }
+        });
+
+        let result = request();
+        // Dropping the sender wakes the watcher, which then stops.
+        drop(done);
+        result
+    })
+}
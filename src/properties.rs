@@ -0,0 +1,495 @@
+//! # Properties
+//!
+//! A runner for Cryptol properties that collects their results into a
+//! [`Report`], which can be written as `JUnit` XML or JSON so that the
+//! results of a specification show up in CI dashboards like those of
+//! any other test suite, or as Markdown or HTML for a human reader,
+//! e.g. as part of an audit deliverable.
+//!
+//! The properties of a module are proved with
+//! [`CryptolClient::prove`], as `:prove` does in the Cryptol REPL,
+//! using the solver the client was built with; the report names it
+//! for each property. Polymorphic properties cannot be proved and are
+//! skipped. Expressions given to
+//! [`check_properties`](CryptolClient::check_properties) are evaluated
+//! instead.
+//!
+//! ```no_run
+//! use std::fs;
+//!
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! cryptol_client.load_module("MySpec").unwrap();
+//! let report = cryptol_client.check_module_properties("MySpec").unwrap();
+//! fs::write("target/cryptol-properties.xml", report.to_junit_xml()).unwrap();
+//! assert!(report.is_success(), "{} properties failed", report.failed());
+//! ```
+
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use web_time::Instant;
+
+use crate::names::DeclarationKind;
+use crate::prove::ProveOutcome;
+use crate::value::CryptolValue;
+use crate::{CryptolClient, Result};
+
+/// The result of checking a property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// The property holds.
+    Passed,
+    /// The property does not hold.
+    Failed {
+        /// The arguments on which the property is `False`, in order,
+        /// which is empty for a property without parameters.
+        counterexample: Vec<CryptolValue>,
+    },
+    /// The property was not checked, e.g. because it is polymorphic.
+    Skipped {
+        /// Why the property was not checked.
+        reason: String,
+    },
+    /// The property could not be checked, e.g. because it does not
+    /// type check or is not a `Bit`.
+    Error {
+        /// The error that occurred.
+        message: String,
+    },
+}
+
+impl Status {
+    /// This function returns the name of the status, e.g. `"passed"`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Passed => "passed",
+            Status::Failed { .. } => "failed",
+            Status::Skipped { .. } => "skipped",
+            Status::Error { .. } => "error",
+        }
+    }
+
+    /// This function returns a description of the status for reports,
+    /// e.g. why a property was skipped or the counterexample it failed
+    /// on, printed as the Cryptol REPL prints values.
+    fn details(&self) -> String {
+        match self {
+            Status::Passed => String::new(),
+            Status::Failed { counterexample } if counterexample.is_empty() => {
+                "property is False".to_string()
+            }
+            Status::Failed { counterexample } => {
+                let arguments: Vec<String> =
+                    counterexample.iter().map(ToString::to_string).collect();
+                format!("property is False on {}", arguments.join(" "))
+            }
+            Status::Skipped { reason: message } | Status::Error { message } => message.clone(),
+        }
+    }
+}
+
+/// How a property was checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    /// The property is an expression of type `Bit` and was evaluated.
+    Evaluation,
+    /// The property was proved with [`CryptolClient::prove`].
+    Proof {
+        /// The solver that was asked for the proof, e.g. `z3`.
+        solver: String,
+    },
+}
+
+impl Method {
+    /// This function returns the solver of a proof, or `None` if the
+    /// property was evaluated.
+    #[must_use]
+    pub fn solver(&self) -> Option<&str> {
+        match self {
+            Method::Evaluation => None,
+            Method::Proof { solver } => Some(solver),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Evaluation => f.write_str("evaluation"),
+            Method::Proof { solver } => write!(f, "proof with {solver}"),
+        }
+    }
+}
+
+/// A property and the result of checking it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyResult {
+    /// The property, e.g. `sha384_empty` or an expression of type
+    /// `Bit`.
+    pub name: String,
+    /// The result of checking the property.
+    pub status: Status,
+    /// How the property was checked, including the solver of a proof.
+    pub method: Method,
+    /// How long checking the property took.
+    pub duration: Duration,
+}
+
+/// The results of checking a suite of properties, e.g. those of a
+/// module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The name of the suite, e.g. the module of the properties.
+    pub suite: String,
+    /// The results, in the order the properties were checked.
+    pub results: Vec<PropertyResult>,
+}
+
+impl Report {
+    /// This function creates a report without results for the given
+    /// suite.
+    #[must_use]
+    pub fn new(suite: impl Into<String>) -> Self {
+        Report {
+            suite: suite.into(),
+            results: Vec::new(),
+        }
+    }
+
+    /// This function returns the number of properties that hold.
+    #[must_use]
+    pub fn passed(&self) -> usize {
+        self.count(|status| matches!(status, Status::Passed))
+    }
+
+    /// This function returns the number of properties that do not
+    /// hold.
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, Status::Failed { .. }))
+    }
+
+    /// This function returns the number of properties that were not
+    /// checked.
+    #[must_use]
+    pub fn skipped(&self) -> usize {
+        self.count(|status| matches!(status, Status::Skipped { .. }))
+    }
+
+    /// This function returns the number of properties that could not
+    /// be checked.
+    #[must_use]
+    pub fn errors(&self) -> usize {
+        self.count(|status| matches!(status, Status::Error { .. }))
+    }
+
+    /// This function returns whether no property failed or could not
+    /// be checked.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0 && self.errors() == 0
+    }
+
+    /// This function returns the total time spent checking properties.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.results.iter().map(|result| result.duration).sum()
+    }
+
+    fn count(&self, matches: impl Fn(&Status) -> bool) -> usize {
+        self.results
+            .iter()
+            .filter(|result| matches(&result.status))
+            .count()
+    }
+
+    /// This function renders the report as `JUnit` XML, with one
+    /// `testsuite` whose `testcase`s are the properties.
+    #[must_use]
+    pub fn to_junit_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let counts = format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+            self.results.len(),
+            self.failed(),
+            self.errors(),
+            self.skipped(),
+            self.duration().as_secs_f64(),
+        );
+        let suite = escape_xml(&self.suite);
+        // Writing to a `String` cannot fail.
+        let _ = writeln!(xml, "<testsuites {counts}>");
+        let _ = writeln!(xml, "  <testsuite name=\"{suite}\" {counts}>");
+        for result in &self.results {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{suite}\" time=\"{:.3}\"",
+                escape_xml(&result.name),
+                result.duration.as_secs_f64(),
+            );
+            let _ = match &result.status {
+                Status::Passed => writeln!(xml, "/>"),
+                Status::Failed { .. } => writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\" type=\"{}\"/>\n    </testcase>",
+                    escape_xml(&result.status.details()),
+                    escape_xml(&result.method.to_string()),
+                ),
+                Status::Skipped { reason } => writeln!(
+                    xml,
+                    ">\n      <skipped message=\"{}\"/>\n    </testcase>",
+                    escape_xml(reason)
+                ),
+                Status::Error { message } => writeln!(
+                    xml,
+                    ">\n      <error message=\"{}\"/>\n    </testcase>",
+                    escape_xml(message)
+                ),
+            };
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    /// This function renders the report as a Markdown document: a
+    /// summary of the counts followed by a table with the status,
+    /// method, duration and details of each property, including the
+    /// counterexample of a property that failed.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = writeln!(markdown, "# Properties of {}\n", self.suite);
+        let _ = writeln!(markdown, "{}\n", self.summary());
+        markdown.push_str("| Property | Status | Method | Duration | Details |\n");
+        markdown.push_str("| --- | --- | --- | ---: | --- |\n");
+        for result in &self.results {
+            let _ = writeln!(
+                markdown,
+                "| `{}` | {} | {} | {:.3} s | {} |",
+                escape_markdown(&result.name),
+                result.status.name(),
+                escape_markdown(&result.method.to_string()),
+                result.duration.as_secs_f64(),
+                escape_markdown(&result.status.details()),
+            );
+        }
+        markdown
+    }
+
+    /// This function renders the report as a standalone HTML page with
+    /// the same content as [`to_markdown`](Self::to_markdown). Each row
+    /// of the table has the status as its class, for stylesheets to
+    /// color.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let suite = escape_xml(&self.suite);
+        let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n");
+        // Writing to a `String` cannot fail.
+        let _ = writeln!(html, "<meta charset=\"utf-8\">");
+        let _ = writeln!(html, "<title>Properties of {suite}</title>");
+        html.push_str("</head>\n<body>\n");
+        let _ = writeln!(html, "<h1>Properties of {suite}</h1>");
+        let _ = writeln!(html, "<p>{}</p>", escape_xml(&self.summary()));
+        html.push_str("<table>\n");
+        html.push_str(
+            "<tr><th>Property</th><th>Status</th><th>Method</th><th>Duration</th><th>Details</th></tr>\n",
+        );
+        for result in &self.results {
+            let _ = writeln!(
+                html,
+                "<tr class=\"{status}\"><td><code>{}</code></td><td>{status}</td><td>{}</td><td>{:.3} s</td><td>{}</td></tr>",
+                escape_xml(&result.name),
+                escape_xml(&result.method.to_string()),
+                result.duration.as_secs_f64(),
+                escape_xml(&result.status.details()),
+                status = result.status.name(),
+            );
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+
+    /// This function summarizes the counts of the report in a sentence.
+    fn summary(&self) -> String {
+        format!(
+            "{} properties: {} passed, {} failed, {} skipped, {} errors, in {:.3} s.",
+            self.results.len(),
+            self.passed(),
+            self.failed(),
+            self.skipped(),
+            self.errors(),
+            self.duration().as_secs_f64(),
+        )
+    }
+
+    /// This function returns the report as JSON: the suite, the
+    /// number of properties of each status, and the results, with
+    /// their methods, solvers, durations in seconds and the
+    /// counterexamples of failed properties.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        let results: Vec<_> = self
+            .results
+            .iter()
+            .map(|result| {
+                let mut entry = json!({
+                    "name": result.name,
+                    "status": result.status.name(),
+                    "method": result.method.to_string(),
+                    "solver": result.method.solver(),
+                    "duration": result.duration.as_secs_f64(),
+                });
+                match &result.status {
+                    Status::Skipped { reason: message } | Status::Error { message } => {
+                        entry["message"] = json!(message);
+                    }
+                    Status::Failed { counterexample } => {
+                        let arguments: Vec<String> =
+                            counterexample.iter().map(ToString::to_string).collect();
+                        entry["counterexample"] = json!(arguments);
+                    }
+                    Status::Passed => {}
+                }
+                entry
+            })
+            .collect();
+        json!({
+            "suite": self.suite,
+            "tests": self.results.len(),
+            "passed": self.passed(),
+            "failed": self.failed(),
+            "skipped": self.skipped(),
+            "errors": self.errors(),
+            "duration": self.duration().as_secs_f64(),
+            "results": results,
+        })
+    }
+}
+
+/// This function escapes `text` for use in an XML attribute.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// This function escapes `text` for use in a cell of a Markdown table.
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+impl CryptolClient {
+    /// This function checks the given properties, each a name or an
+    /// expression of type `Bit`, by evaluating them, and collects the
+    /// results into a report for `suite`. A property that cannot be
+    /// evaluated is reported as an error rather than ending the run.
+    pub fn check_properties<S: AsRef<str>>(
+        &mut self,
+        suite: &str,
+        properties: impl IntoIterator<Item = S>,
+    ) -> Report {
+        let mut report = Report::new(suite);
+        for property in properties {
+            let property = property.as_ref();
+            let start = Instant::now();
+            let status = match self
+                .evaluate(property)
+                .and_then(|answer| Ok(answer.decode()?))
+            {
+                Ok(CryptolValue::Bit(true)) => Status::Passed,
+                Ok(CryptolValue::Bit(false)) => Status::Failed {
+                    counterexample: Vec::new(),
+                },
+                Ok(value) => Status::Error {
+                    message: format!("property is not a Bit: {value}"),
+                },
+                Err(e) => Status::Error {
+                    message: e.to_string(),
+                },
+            };
+            tracing::info!(property, status = status.name(), "checked property");
+            report.results.push(PropertyResult {
+                name: property.to_string(),
+                status,
+                method: Method::Evaluation,
+                duration: self.elapsed_since(start),
+            });
+        }
+        report
+    }
+
+    /// This function proves the properties declared in `module`, which
+    /// must be loaded, with the client's [solver](Self::solver), and
+    /// collects the results into a report named after it. Polymorphic
+    /// properties are skipped.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the declarations of the module
+    /// cannot be listed.
+    pub fn check_module_properties(&mut self, module: &str) -> Result<Report> {
+        let declarations = self.module_declarations(module)?;
+        let mut report = Report::new(module);
+        for declaration in declarations
+            .iter()
+            .filter(|declaration| declaration.kind == DeclarationKind::Property)
+        {
+            let property = declaration.name.as_str();
+            let start = Instant::now();
+            let status = if declaration.schema.is_monomorphic() {
+                self.prove_property(property)
+            } else {
+                Status::Skipped {
+                    reason: "polymorphic properties cannot be proved".to_string(),
+                }
+            };
+            tracing::info!(property, status = status.name(), "proved property");
+            report.results.push(PropertyResult {
+                name: property.to_string(),
+                status,
+                method: Method::Proof {
+                    solver: self.solver().to_string(),
+                },
+                duration: self.elapsed_since(start),
+            });
+        }
+        Ok(report)
+    }
+
+    /// This function proves `property` with [`prove`](Self::prove). A
+    /// counterexample makes the property fail, and one on which it
+    /// raises an error is reported as an error.
+    fn prove_property(&mut self, property: &str) -> Status {
+        match self.prove(property) {
+            Ok(result) => match result.outcome {
+                ProveOutcome::Proved => Status::Passed,
+                ProveOutcome::Falsified { counterexample } => Status::Failed { counterexample },
+                ProveOutcome::SafetyViolation { counterexample } => {
+                    let arguments: Vec<String> =
+                        counterexample.iter().map(ToString::to_string).collect();
+                    Status::Error {
+                        message: format!("property raises an error on {}", arguments.join(" ")),
+                    }
+                }
+            },
+            Err(e) => Status::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+}
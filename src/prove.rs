@@ -0,0 +1,145 @@
+//! # Proving properties
+//!
+//! `CryptolClient::prove` has `cryptol-remote-api` prove a property
+//! with an SMT solver, as `:prove` does in the Cryptol REPL. Unlike
+//! [testing](crate::check), a proof covers every argument, however many
+//! there are. The [`ProveResult`] names the solver that was used and,
+//! if the property does not hold, the arguments that falsify it.
+//!
+//! The solver is [`DEFAULT_SOLVER`] unless another is given to
+//! [`CryptolClientBuilder::solver`], or as `solver` in a
+//! [configuration file](crate::config).
+//!
+//! ```no_run
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! let result = cryptol_client
+//!     .prove("\\(x : [64]) (y : [64]) -> x ^ y == y ^ x")
+//!     .unwrap();
+//! assert!(result.proved());
+//! println!("proved with {}", result.solver);
+//! ```
+
+use serde_json::json;
+
+use crate::value::CryptolValue;
+use crate::{parse, CryptolClient, CryptolClientBuilder, Result};
+
+/// The solver `prove` uses unless another is configured, as in the
+/// Cryptol REPL.
+pub const DEFAULT_SOLVER: &str = "z3";
+
+/// What proving a property found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProveOutcome {
+    /// The property holds on every argument.
+    Proved,
+    /// The property is `False` on the counterexample, the arguments in
+    /// order.
+    Falsified {
+        /// The arguments on which the property does not hold.
+        counterexample: Vec<CryptolValue>,
+    },
+    /// Evaluating the property on the counterexample raises an error,
+    /// e.g. a division by zero.
+    SafetyViolation {
+        /// The arguments on which the error is raised.
+        counterexample: Vec<CryptolValue>,
+    },
+}
+
+/// The result of [`CryptolClient::prove`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProveResult {
+    /// The solver that was asked for the proof, e.g. `z3`.
+    pub solver: String,
+    /// What the solver found.
+    pub outcome: ProveOutcome,
+}
+
+impl ProveResult {
+    /// This function returns whether the property was proved.
+    #[must_use]
+    pub fn proved(&self) -> bool {
+        self.outcome == ProveOutcome::Proved
+    }
+
+    /// This function returns the arguments on which the property does
+    /// not hold or raises an error, or `None` if it was proved.
+    #[must_use]
+    pub fn counterexample(&self) -> Option<&[CryptolValue]> {
+        match &self.outcome {
+            ProveOutcome::Proved => None,
+            ProveOutcome::Falsified { counterexample }
+            | ProveOutcome::SafetyViolation { counterexample } => Some(counterexample),
+        }
+    }
+}
+
+impl CryptolClientBuilder {
+    /// Sets the solver `prove` uses, one of the provers
+    /// `cryptol-remote-api` knows. The default is [`DEFAULT_SOLVER`].
+    #[must_use]
+    pub fn solver(mut self, solver: &str) -> Self {
+        self.settings.solver = Some(solver.to_string());
+        self
+    }
+}
+
+impl CryptolClient {
+    /// This function proves the given property, an expression of a
+    /// function type returning `Bit`, with the solver the client was
+    /// built with; see [`CryptolClientBuilder::solver`].
+    ///
+    /// # Errors
+    ///
+    /// The function returns the errors of
+    /// [`prove_with`](Self::prove_with).
+    pub fn prove(&mut self, property: &str) -> Result<ProveResult> {
+        let solver = self.solver.clone();
+        self.prove_with(property, &solver)
+    }
+
+    /// This function returns the solver [`prove`](Self::prove) uses.
+    #[must_use]
+    pub fn solver(&self) -> &str {
+        &self.solver
+    }
+
+    /// This function proves the given property with `solver`, one of
+    /// the provers `cryptol-remote-api` knows, e.g. `z3`, `yices`,
+    /// `cvc5` or `w4-z3`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Server` if the property does not
+    /// type check, or if the server does not know the solver or cannot
+    /// run it. It returns `Error::Decode` if the result cannot be
+    /// decoded. A property that does not hold is not an error.
+    #[tracing::instrument(skip(self))]
+    pub fn prove_with(&mut self, property: &str, solver: &str) -> Result<ProveResult> {
+        let mut params = self.session.params();
+        params.insert(
+            "expression".into(),
+            json!(self.substitute_definitions(property)),
+        );
+        params.insert("prover".into(), json!(solver));
+        params.insert("query type".into(), json!("prove"));
+        params.insert("hash consing".into(), json!("true"));
+
+        self.request("prove or satisfy", &params)?;
+
+        let answer = parse::Json::parse(
+            self.answer
+                .as_deref()
+                .map_or("null", serde_json::value::RawValue::get),
+        )?;
+        let outcome = parse::decode_prove_outcome(&answer)?;
+        tracing::debug!(solver, ?outcome, "proved property");
+        Ok(ProveResult {
+            solver: solver.to_string(),
+            outcome,
+        })
+    }
+}
@@ -0,0 +1,207 @@
+//! # Python bindings
+//!
+//! With the `pyo3` feature, this crate builds the Python extension
+//! module `cryptol_client`, a thin wrapper around
+//! [`CryptolClient`](crate::CryptolClient) for driving
+//! `cryptol-remote-api` from Python, e.g. from a notebook.
+//! The module is built with [maturin](https://www.maturin.rs):
+//!
+//! ```text
+//! $ maturin develop --features pyo3,pyo3/extension-module
+//! ```
+//!
+//! Arguments are converted from Python as follows: a `str` is a Cryptol
+//! expression, a `bool` a `Bit`, an `int` a literal whose type Cryptol
+//! infers, and `bytes` a bitvector of 8 bits per byte. Answers are
+//! decoded into `bool`, `int`, `list`, `tuple`, `dict` and `None`;
+//! values the server only refers to by name, like functions, are
+//! returned as that name.
+//!
+//! ```python
+//! import cryptol_client
+//!
+//! client = cryptol_client.connect()  # at $CRYPTOL_SERVER_URL
+//! client.load_module("SuiteB")
+//! digest = client.call("sha384", b"\x00\x01")
+//! print(hex(digest))
+//! assert client.prove("\\(x : [8]) -> reverse (reverse [x]) == [x]") is None
+//! ```
+//!
+//! Failures raise `cryptol_client.CryptolError`, or `TimeoutError` if
+//! the request timed out.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTimeoutError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyInt, PyList, PyString, PyTuple};
+
+use crate::error::Error;
+use crate::value::CryptolValue;
+use crate::{Args, CryptolClientBuilder};
+
+create_exception!(
+    cryptol_client,
+    CryptolError,
+    PyException,
+    "A request to cryptol-remote-api failed."
+);
+
+/// This function converts an error of the client into a Python
+/// exception. It takes the error by value to be used with `map_err`.
+#[allow(clippy::needless_pass_by_value)]
+fn to_py_err(e: Box<dyn std::error::Error>) -> PyErr {
+    match e.downcast_ref::<Error>() {
+        Some(Error::Timeout(_)) => PyTimeoutError::new_err(e.to_string()),
+        _ => CryptolError::new_err(e.to_string()),
+    }
+}
+
+/// This function converts Python call arguments into `Args`.
+fn to_args(arguments: &Bound<'_, PyTuple>) -> PyResult<Args> {
+    let mut args = Args::new();
+    for argument in arguments {
+        // `bool` is a subclass of `int`, so it is checked first.
+        args = if let Ok(bit) = argument.downcast::<PyBool>() {
+            args.bool(bit.is_true())
+        } else if argument.is_instance_of::<PyInt>() {
+            args.expression(&argument.str()?.to_cow()?)
+        } else if let Ok(expression) = argument.downcast::<PyString>() {
+            args.expression(&expression.to_cow()?)
+        } else if let Ok(bytes) = argument.downcast::<PyBytes>() {
+            args.bytes(bytes.as_bytes())
+        } else {
+            return Err(PyTypeError::new_err(format!(
+                "cannot pass a {} to Cryptol",
+                argument.get_type().name()?
+            )));
+        };
+    }
+    Ok(args)
+}
+
+/// This function converts a decoded Cryptol value into Python.
+fn to_python(py: Python<'_>, value: &CryptolValue) -> PyResult<Py<PyAny>> {
+    Ok(match value {
+        CryptolValue::Bit(bit) => PyBool::new(py, *bit).to_owned().into_any().unbind(),
+        CryptolValue::Integer(n) | CryptolValue::IntegerModulo { value: n, .. } => {
+            py.get_type::<PyInt>().call1((n.to_string(),))?.unbind()
+        }
+        CryptolValue::BitVector(bits) => py
+            .get_type::<PyInt>()
+            .call_method1("from_bytes", (PyBytes::new(py, bits.as_bytes()), "big"))?
+            .unbind(),
+        CryptolValue::Sequence(values) => {
+            let values = values
+                .iter()
+                .map(|value| to_python(py, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, values)?.into_any().unbind()
+        }
+        CryptolValue::Tuple(values) => {
+            let values = values
+                .iter()
+                .map(|value| to_python(py, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyTuple::new(py, values)?.into_any().unbind()
+        }
+        CryptolValue::Record(fields) => {
+            let dict = PyDict::new(py);
+            for (name, value) in fields {
+                dict.set_item(name, to_python(py, value)?)?;
+            }
+            dict.into_any().unbind()
+        }
+        CryptolValue::Unit => py.None(),
+        CryptolValue::Opaque(name) => PyString::new(py, name).into_any().unbind(),
+    })
+}
+
+/// A connection to `cryptol-remote-api`, as seen from Python.
+#[pyclass(name = "CryptolClient", module = "cryptol_client")]
+pub struct PyCryptolClient {
+    client: crate::CryptolClient,
+}
+
+#[pymethods]
+impl PyCryptolClient {
+    /// Loads a Cryptol module.
+    fn load_module(&mut self, module: &str) -> PyResult<()> {
+        self.client.load_module(module).map_err(to_py_err)
+    }
+
+    /// Calls a Cryptol function and returns its decoded result.
+    #[pyo3(signature = (function, *arguments))]
+    fn call(
+        &mut self,
+        py: Python<'_>,
+        function: &str,
+        arguments: &Bound<'_, PyTuple>,
+    ) -> PyResult<Py<PyAny>> {
+        let answer = self
+            .client
+            .call(function, to_args(arguments)?)
+            .map_err(to_py_err)?;
+        to_python(py, &answer.decode().map_err(|e| to_py_err(e.into()))?)
+    }
+
+    /// Evaluates a Cryptol expression and returns its decoded value.
+    fn evaluate(&mut self, py: Python<'_>, expression: &str) -> PyResult<Py<PyAny>> {
+        let answer = self.client.evaluate(expression).map_err(to_py_err)?;
+        to_python(py, &answer.decode().map_err(|e| to_py_err(e.into()))?)
+    }
+
+    /// Proves a property with a solver, the client's unless another
+    /// one is given. Returns `None` if the property holds, and otherwise the
+    /// list of arguments on which it does not.
+    #[pyo3(signature = (property, solver = None))]
+    fn prove(
+        &mut self,
+        py: Python<'_>,
+        property: &str,
+        solver: Option<&str>,
+    ) -> PyResult<Py<PyAny>> {
+        let result = match solver {
+            Some(solver) => self.client.prove_with(property, solver),
+            None => self.client.prove(property),
+        }
+        .map_err(to_py_err)?;
+        match result.counterexample() {
+            None => Ok(py.None()),
+            Some(arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| to_python(py, argument))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(PyList::new(py, arguments)?.into_any().unbind())
+            }
+        }
+    }
+}
+
+/// Connects to `cryptol-remote-api` at `url`, or at
+/// `CRYPTOL_SERVER_URL` if no URL is given.
+#[pyfunction]
+#[pyo3(signature = (url = None))]
+fn connect(url: Option<&str>) -> PyResult<PyCryptolClient> {
+    let mut builder = CryptolClientBuilder::new();
+    if let Some(url) = url {
+        builder = builder.url(url);
+    }
+    let client = builder.connect().map_err(to_py_err)?;
+    Ok(PyCryptolClient { client })
+}
+
+/// This function initializes the `cryptol_client` Python module. It
+/// is public so that programs embedding Python can register the module
+/// themselves.
+///
+/// # Errors
+///
+/// The function returns an error if the module cannot be populated.
+#[pymodule]
+pub fn cryptol_client(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(connect, m)?)?;
+    m.add_class::<PyCryptolClient>()?;
+    m.add("CryptolError", m.py().get_type::<CryptolError>())?;
+    Ok(())
+}
@@ -0,0 +1,514 @@
+//! # Serde encoding
+//!
+//! [`to_cryptol_value`] converts any Rust value that implements
+//! `serde::Serialize` into a Cryptol value, so that plain Rust data
+//! structures can be passed to calls with [`Args::serialize`] without
+//! implementing [`ToCryptolValue`] by hand. Values are encoded as
+//! follows:
+//!
+//! - `bool` becomes a `Bit`;
+//! - fixed-width integers become bitvectors of the same width, two's
+//!   complement for signed types, as with `ToCryptolValue`; `usize`
+//!   and `isize` serialize as 64-bit integers and so become `[64]`;
+//! - strings, characters and byte strings become sequences of bytes;
+//! - sequences, e.g. `Vec`s and slices, become sequences, and tuples
+//!   and tuple structs become tuples; serde serializes arrays as
+//!   tuples, so a `[u8; 16]` becomes a tuple of 16 bytes, and should be
+//!   passed as a `Vec` or slice to become a `[16][8]`;
+//! - structs and maps with string keys become records;
+//! - `()`, unit structs and `None` become `()`, `Some(x)` and newtype
+//!   structs become the encoding of `x`.
+//!
+//! Floating-point numbers and enum variants other than unit ones have
+//! no Cryptol counterpart and fail to encode. A unit variant becomes
+//! the string of its name.
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! use cryptol_client::ser::to_cryptol_value;
+//!
+//! #[derive(Serialize)]
+//! struct Point {
+//!     x: u8,
+//!     y: u8,
+//! }
+//!
+//! let value = to_cryptol_value(&vec![Point { x: 1, y: 2 }]).unwrap();
+//! assert_eq!(value.to_expression(), "[{x = 0x01, y = 0x02}]");
+//! ```
+//!
+//! [`Args::serialize`]: crate::Args::serialize
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::convert::ToCryptolValue;
+use crate::error::Error;
+use crate::value::CryptolValue;
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Decode(msg.to_string())
+    }
+}
+
+/// This function encodes `value` as a Cryptol value. See the [module
+/// documentation](crate::ser).
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `value` contains a floating
+/// point number, an enum variant with data, or a map key that is not a
+/// string, or if its `Serialize` implementation fails.
+pub fn to_cryptol_value<T: Serialize + ?Sized>(value: &T) -> Result<CryptolValue, Error> {
+    value.serialize(Serializer)
+}
+
+/// A `serde::Serializer` whose output is a [`CryptolValue`]. See the
+/// [module documentation](crate::ser).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Serializer;
+
+/// This function reports that values of `kind` cannot be encoded.
+fn unsupported(kind: &str) -> Error {
+    Error::Decode(format!("{kind} cannot be encoded as a Cryptol value"))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = CryptolValue;
+    type Error = Error;
+    type SerializeSeq = Sequence;
+    type SerializeTuple = Sequence;
+    type SerializeTupleStruct = Sequence;
+    type SerializeTupleVariant = Impossible<CryptolValue, Error>;
+    type SerializeMap = Record;
+    type SerializeStruct = Record;
+    type SerializeStructVariant = Impossible<CryptolValue, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<CryptolValue, Error> {
+        Err(unsupported("a floating-point number"))
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<CryptolValue, Error> {
+        Err(unsupported("a floating-point number"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<CryptolValue, Error> {
+        Ok(v.encode_utf8(&mut [0; 4]).to_cryptol_value())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<CryptolValue, Error> {
+        Ok(v.to_cryptol_value())
+    }
+
+    fn serialize_none(self) -> Result<CryptolValue, Error> {
+        Ok(CryptolValue::Unit)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<CryptolValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<CryptolValue, Error> {
+        Ok(CryptolValue::Unit)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<CryptolValue, Error> {
+        Ok(CryptolValue::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<CryptolValue, Error> {
+        Ok(variant.to_cryptol_value())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<CryptolValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: &T,
+    ) -> Result<CryptolValue, Error> {
+        Err(unsupported(&format!("the enum variant {name}::{variant}")))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Sequence, Error> {
+        Ok(Sequence::new(len, CryptolValue::Sequence))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Sequence, Error> {
+        Ok(Sequence::new(Some(len), CryptolValue::Tuple))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Sequence, Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported(&format!("the enum variant {name}::{variant}")))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Record, Error> {
+        Ok(Record::default())
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Record, Error> {
+        Ok(Record::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported(&format!("the enum variant {name}::{variant}")))
+    }
+}
+
+/// The elements of a sequence or tuple being encoded.
+#[derive(Debug)]
+pub struct Sequence {
+    elements: Vec<CryptolValue>,
+    wrap: fn(Vec<CryptolValue>) -> CryptolValue,
+}
+
+impl Sequence {
+    fn new(len: Option<usize>, wrap: fn(Vec<CryptolValue>) -> CryptolValue) -> Self {
+        Sequence {
+            elements: Vec::with_capacity(len.unwrap_or_default()),
+            wrap,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.elements.push(to_cryptol_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> CryptolValue {
+        (self.wrap)(self.elements)
+    }
+}
+
+impl ser::SerializeSeq for Sequence {
+    type Ok = CryptolValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<CryptolValue, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for Sequence {
+    type Ok = CryptolValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<CryptolValue, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for Sequence {
+    type Ok = CryptolValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<CryptolValue, Error> {
+        Ok(self.finish())
+    }
+}
+
+/// The fields of a record being encoded.
+#[derive(Debug, Default)]
+pub struct Record {
+    fields: BTreeMap<String, CryptolValue>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for Record {
+    type Ok = CryptolValue;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Decode("a map value was given without a key".to_string()))?;
+        self.fields.insert(key, to_cryptol_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<CryptolValue, Error> {
+        Ok(CryptolValue::Record(self.fields))
+    }
+}
+
+impl ser::SerializeStruct for Record {
+    type Ok = CryptolValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.fields
+            .insert(key.to_string(), to_cryptol_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<CryptolValue, Error> {
+        Ok(CryptolValue::Record(self.fields))
+    }
+}
+
+/// A serializer for the keys of a map, which must be strings to name
+/// the fields of a record.
+struct KeySerializer;
+
+/// This function reports a map key that cannot name a record field.
+fn not_a_field_name() -> Error {
+    Error::Decode("the keys of a map must be strings to encode it as a record".to_string())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+        Err(not_a_field_name())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(not_a_field_name())
+    }
+}
@@ -0,0 +1,397 @@
+//! # Server management
+//!
+//! A `ServerManager` runs `cryptol-remote-api` as a child process, so
+//! that tools and tests do not need a server started by hand. The
+//! server listens on a local port and is stopped when the manager is
+//! dropped.
+//!
+//! The `CRYPTOLPATH` of the server, its working directory and extra
+//! command line flags are set through [`ServerManagerBuilder`], so
+//! that specifications in the project are visible without changing
+//! the environment of the whole system.
+//!
+//! What the server writes to standard output and standard error is
+//! captured line by line and logged as `tracing` events with target
+//! `cryptol_client::server`: output at level `INFO` and errors at
+//! `WARN`. The most recent lines are also kept, for
+//! [`ServerManager::logs`], and quoted when the server exits while
+//! starting.
+//!
+//! ```no_run
+//! use cryptol_client::server::ServerManager;
+//!
+//! let server = ServerManager::builder()
+//!     .cryptol_path(["specs", "vendor/cryptol-specs"])
+//!     .arg("--max-occupancy")
+//!     .arg("1000")
+//!     .spawn()
+//!     .unwrap();
+//! let mut cryptol_client = server.client_builder().connect().unwrap();
+//! cryptol_client.load_module("MySpec").unwrap();
+//! ```
+
+use std::collections::VecDeque;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::{CryptolClientBuilder, Result};
+
+/// How often a starting server is checked for whether it listens yet.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a server that exited while starting is given to finish
+/// writing its output.
+const OUTPUT_GRACE: Duration = Duration::from_millis(500);
+
+/// How many of the last lines of output of a server that exited while
+/// starting are quoted in the error.
+const QUOTED_LINES: usize = 5;
+
+/// Builder for a `ServerManager`.
+#[derive(Debug, Clone)]
+pub struct ServerManagerBuilder {
+    program: PathBuf,
+    port: Option<u16>,
+    cryptol_path: Vec<PathBuf>,
+    current_dir: Option<PathBuf>,
+    envs: Vec<(OsString, OsString)>,
+    args: Vec<OsString>,
+    startup_timeout: Duration,
+    log_capacity: usize,
+}
+
+impl Default for ServerManagerBuilder {
+    fn default() -> Self {
+        ServerManagerBuilder {
+            program: PathBuf::from("cryptol-remote-api"),
+            port: None,
+            cryptol_path: Vec::new(),
+            current_dir: None,
+            envs: Vec::new(),
+            args: Vec::new(),
+            startup_timeout: Duration::from_secs(30),
+            log_capacity: 1000,
+        }
+    }
+}
+
+impl ServerManagerBuilder {
+    /// Creates a builder with default settings.
+    #[must_use]
+    pub fn new() -> Self {
+        ServerManagerBuilder::default()
+    }
+
+    /// Sets the server executable. By default it is
+    /// `cryptol-remote-api`, looked up in the `PATH`.
+    #[must_use]
+    pub fn program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Sets the local port the server listens on. By default a free
+    /// port is chosen.
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sets the directories the server searches for modules, passed
+    /// to it as `CRYPTOLPATH`. Relative directories are relative to
+    /// the working directory of the server. By default the
+    /// `CRYPTOLPATH` of this process, if any, is inherited.
+    #[must_use]
+    pub fn cryptol_path<P: Into<PathBuf>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.cryptol_path = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the working directory of the server. By default it is the
+    /// working directory of this process.
+    #[must_use]
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets an environment variable of the server, in addition to
+    /// those inherited from this process.
+    #[must_use]
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.envs
+            .push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    /// Adds a command line flag, e.g. `--max-occupancy`, passed after
+    /// the flags that make the server listen on its port.
+    #[must_use]
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Adds several command line flags. See [`arg`](Self::arg).
+    #[must_use]
+    pub fn args<A: AsRef<OsStr>>(mut self, args: impl IntoIterator<Item = A>) -> Self {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets how long `spawn` waits for the server to listen on its
+    /// port. The default is 30 seconds.
+    #[must_use]
+    pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Sets how many of the most recent lines of output of the server
+    /// are kept for [`ServerManager::logs`]. The default is 1000. All
+    /// lines are logged regardless.
+    #[must_use]
+    pub fn log_capacity(mut self, lines: usize) -> Self {
+        self.log_capacity = lines;
+        self
+    }
+
+    /// This function starts the server and waits until it listens on
+    /// its port.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the server cannot be started,
+    /// e.g. because the executable does not exist or the `CRYPTOLPATH`
+    /// cannot be represented, `Error::Transport` if it exits while
+    /// starting, quoting the last lines it wrote, and `Error::Timeout`
+    /// if it does not listen within the startup timeout.
+    #[tracing::instrument(skip(self), fields(program = %self.program.display()))]
+    pub fn spawn(self) -> Result<ServerManager> {
+        let port = match self.port {
+            Some(port) => port,
+            None => TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+                .local_addr()?
+                .port(),
+        };
+
+        let mut command = Command::new(&self.program);
+        command
+            .arg("http")
+            .arg("--host")
+            .arg(Ipv4Addr::LOCALHOST.to_string())
+            .arg("--port")
+            .arg(port.to_string())
+            .arg("/")
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if !self.cryptol_path.is_empty() {
+            command.env("CRYPTOLPATH", env::join_paths(&self.cryptol_path)?);
+        }
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = command.spawn()?;
+        let pid = child.id();
+        tracing::info!(pid, port, "started cryptol-remote-api");
+        let logs = Arc::new(Logs {
+            lines: Mutex::new(VecDeque::new()),
+            capacity: self.log_capacity,
+        });
+        let readers = [
+            child
+                .stdout
+                .take()
+                .map(|out| forward(out, Stream::Stdout, pid, logs.clone())),
+            child
+                .stderr
+                .take()
+                .map(|err| forward(err, Stream::Stderr, pid, logs.clone())),
+        ];
+        let mut server = ServerManager {
+            child,
+            url: format!("http://{}:{port}/", Ipv4Addr::LOCALHOST),
+            logs,
+        };
+
+        let start = Instant::now();
+        while TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_err() {
+            if let Some(status) = server.child.try_wait()? {
+                let grace = Instant::now();
+                while readers.iter().flatten().any(|r| !r.is_finished())
+                    && grace.elapsed() < OUTPUT_GRACE
+                {
+                    thread::sleep(POLL_INTERVAL);
+                }
+                let mut message = format!("cryptol-remote-api exited while starting: {status}");
+                let logs = server.logs();
+                for line in &logs[logs.len().saturating_sub(QUOTED_LINES)..] {
+                    message.push_str("\n    ");
+                    message.push_str(line.text());
+                }
+                return Err(Error::Transport(message.into()).into());
+            }
+            if start.elapsed() >= self.startup_timeout {
+                return Err(Error::Timeout(None).into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        Ok(server)
+    }
+}
+
+/// A `cryptol-remote-api` process started by this crate. Dropping it
+/// stops the process.
+pub struct ServerManager {
+    child: Child,
+    url: String,
+    logs: Arc<Logs>,
+}
+
+impl fmt::Debug for ServerManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerManager")
+            .field("pid", &self.child.id())
+            .field("url", &self.url)
+            .field("logs", &self.logs)
+            .finish()
+    }
+}
+
+impl ServerManager {
+    /// This function returns a builder for starting a server.
+    #[must_use]
+    pub fn builder() -> ServerManagerBuilder {
+        ServerManagerBuilder::new()
+    }
+
+    /// This function returns the URL the server listens on.
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// This function returns the most recent lines the server wrote
+    /// to standard output and standard error, oldest first. See
+    /// [`ServerManagerBuilder::log_capacity`].
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the log was poisoned by a panic in
+    /// another thread.
+    #[must_use]
+    pub fn logs(&self) -> Vec<LogLine> {
+        self.logs.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// This function returns a builder for clients of the server.
+    #[must_use]
+    pub fn client_builder(&self) -> CryptolClientBuilder {
+        CryptolClientBuilder::new().url(self.url.as_str())
+    }
+}
+
+impl Drop for ServerManager {
+    fn drop(&mut self) {
+        // The process may have exited on its own already.
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        tracing::info!(pid = self.child.id(), "stopped cryptol-remote-api");
+    }
+}
+
+/// The stream of a server a line of output was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+/// A line written by a server started by a `ServerManager`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    stream: Stream,
+    text: String,
+}
+
+impl LogLine {
+    /// This function returns the stream the line was written to.
+    #[must_use]
+    pub fn stream(&self) -> Stream {
+        self.stream
+    }
+
+    /// This function returns the line, without its line break.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// The most recent lines written by a server.
+#[derive(Debug)]
+struct Logs {
+    lines: Mutex<VecDeque<LogLine>>,
+    capacity: usize,
+}
+
+/// This function starts a thread that logs each line read from
+/// `output`, a stream of the server with id `pid`, and keeps it in
+/// `logs`. The thread ends when the server closes the stream.
+fn forward(
+    output: impl Read + Send + 'static,
+    stream: Stream,
+    pid: u32,
+    logs: Arc<Logs>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut output = BufReader::new(output);
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            match output.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&buffer);
+            let text = text.trim_end_matches(['\n', '\r']);
+            match stream {
+                Stream::Stdout => {
+                    tracing::info!(target: "cryptol_client::server", pid, "{text}");
+                }
+                Stream::Stderr => {
+                    tracing::warn!(target: "cryptol_client::server", pid, "{text}");
+                }
+            }
+            if logs.capacity == 0 {
+                continue;
+            }
+            let mut lines = logs.lines.lock().unwrap();
+            if lines.len() == logs.capacity {
+                lines.pop_front();
+            }
+            lines.push_back(LogLine {
+                stream,
+                text: text.to_string(),
+            });
+        }
+    })
+}
@@ -0,0 +1,345 @@
+//! # Remote sessions
+//!
+//! `cryptol-remote-api` is built on Galois' argo framework, as is
+//! `saw-remote-api`. Both speak JSON-RPC and thread an opaque state
+//! token through it: every request names the state it runs in, and
+//! every response names the state it produced, together with the
+//! answer and the output of the server.
+//!
+//! A `RemoteSession` implements this part of the protocol without
+//! knowing anything about Cryptol: it sends requests through a
+//! [`Transport`], keeps the state token, and decodes responses and
+//! server errors. [`CryptolClient`] is built on it, and a client for
+//! another argo server can be too.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use cryptol_client::session::RemoteSession;
+//! use cryptol_client::transport::HttpTransport;
+//!
+//! let transport = HttpTransport::new("http://localhost:49352/").unwrap();
+//! let mut session = RemoteSession::new(Arc::new(transport));
+//!
+//! let mut params = session.params();
+//! params.insert("module name".into(), "SuiteB".into());
+//! session.request("load module", &params).unwrap();
+//! println!("now in state {}", session.state().unwrap());
+//! ```
+//!
+//! ## Programming against the operations of a client
+//!
+//! The [`CryptolSession`] trait captures what an application does with
+//! a client: loading modules, calling, evaluating and typing
+//! expressions in them, and testing and proving properties. Code written against
+//! the trait runs with a
+//! [`CryptolClient`], a [`functional::Session`], or, in unit tests, a
+//! client on a [`MockTransport`](crate::transport::MockTransport)
+//! that needs no server.
+
+use std::sync::Arc;
+
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
+
+use crate::check::{CheckResult, TestCount};
+use crate::error::Error;
+use crate::functional;
+use crate::prove::ProveResult;
+use crate::transport::Transport;
+use crate::types::TypeSchema;
+use crate::value::CryptolValue;
+use crate::{parse, Answer, Args, CryptolClient, CryptolResult, Result as ClientResult};
+
+/// A JSON-RPC session with an argo server, e.g. `cryptol-remote-api`
+/// or `saw-remote-api`.
+///
+/// Cloning a session forks it: both clones start in the same state and
+/// share the transport, but advance independently.
+#[derive(Debug, Clone)]
+pub struct RemoteSession {
+    transport: Arc<dyn Transport>,
+    state: Option<String>,
+}
+
+impl RemoteSession {
+    /// This function starts a session that sends its requests through
+    /// `transport`. The session has no state yet, so its first request
+    /// runs in the initial state of the server.
+    #[must_use]
+    pub fn new(transport: Arc<dyn Transport>) -> Self {
+        RemoteSession {
+            transport,
+            state: None,
+        }
+    }
+
+    /// This function returns the transport of the session.
+    #[must_use]
+    pub fn transport(&self) -> &Arc<dyn Transport> {
+        &self.transport
+    }
+
+    /// This function returns the state token the next request runs in,
+    /// or `None` if no request has completed yet.
+    #[must_use]
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    /// This function moves the session to `state`, e.g. to return to a
+    /// state an earlier request produced.
+    pub fn set_state(&mut self, state: Option<String>) {
+        self.state = state;
+    }
+
+    /// This function returns request parameters that carry the state
+    /// of the session, for the caller to add the parameters of the
+    /// method to.
+    #[must_use]
+    pub fn params(&self) -> Map<String, Value> {
+        let mut params = Map::new();
+        params.insert("state".into(), json!(self.state));
+        params
+    }
+
+    /// This function sends a request with the given `method` and
+    /// `params` and decodes its response, without changing the state
+    /// of the session.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Server` if the server answers with
+    /// an error, `Error::Decode` if the response cannot be decoded, and
+    /// the error of the transport if the request fails.
+    pub fn send(&self, method: &str, params: &Map<String, Value>) -> Result<CryptolResult, Error> {
+        self.exchange(|transport| transport.request(method, params))
+            .1
+    }
+
+    /// This function makes a request with `request`, e.g. one that also
+    /// reports its progress, and decodes its response. It returns the
+    /// size of the response body, if one was received, along with the
+    /// response.
+    pub(crate) fn exchange(
+        &self,
+        request: impl FnOnce(&dyn Transport) -> Result<Box<RawValue>, Error>,
+    ) -> (Option<usize>, Result<CryptolResult, Error>) {
+        match request(self.transport.as_ref()) {
+            Ok(raw) => (Some(raw.get().len()), parse::response(raw.get())),
+            Err(e) => (None, Err(e)),
+        }
+    }
+
+    /// This function sends `requests` as one batch and decodes their
+    /// responses, without changing the state of the session. It
+    /// returns the total size of the response bodies along with the
+    /// responses, in the order of `requests`.
+    pub(crate) fn exchange_batch(
+        &self,
+        requests: &[(&str, Map<String, Value>)],
+    ) -> Result<(usize, Vec<Result<CryptolResult, Error>>), Error> {
+        let responses = self.transport.batch(requests)?;
+        let size = responses.iter().flatten().map(|raw| raw.get().len()).sum();
+        let responses = responses
+            .into_iter()
+            .map(|raw| parse::response(raw?.get()))
+            .collect();
+        Ok((size, responses))
+    }
+
+    /// This function moves the session to the state `response` names.
+    pub(crate) fn advance(&mut self, response: &CryptolResult) {
+        self.state = Some(response.state.clone());
+    }
+
+    /// This function sends a request like [`send`](Self::send) and then
+    /// moves the session to the state the response names.
+    ///
+    /// # Errors
+    ///
+    /// The function returns the errors of [`send`](Self::send). The
+    /// state of the session is unchanged if the request fails.
+    pub fn request(
+        &mut self,
+        method: &str,
+        params: &Map<String, Value>,
+    ) -> Result<CryptolResult, Error> {
+        let response = self.send(method, params)?;
+        self.advance(&response);
+        Ok(response)
+    }
+}
+
+/// The operations of a Cryptol client, for code that should not depend
+/// on how they are carried out. See the [module
+/// documentation](crate::session).
+///
+/// The trait is object safe, so a `&mut dyn CryptolSession` can be
+/// passed around as well.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use cryptol_client::session::CryptolSession;
+/// use cryptol_client::transport::MockTransport;
+/// use cryptol_client::{Args, CryptolClient};
+/// use serde_json::json;
+///
+/// /// The width of the digest of `message`, in bits.
+/// fn digest_width(session: &mut dyn CryptolSession, message: &str) -> Option<usize> {
+///     session.load_module("SuiteB").ok()?;
+///     session.call("sha384", Args::new().expression(message)).ok()?.bit_width()
+/// }
+///
+/// let transport = MockTransport::new()
+///     .respond("load module", json!([]))
+///     .respond(
+///         "call",
+///         json!({"type": {"forall": [], "propositions": [], "type": {"type": "bitvector", "width": {"type": "number", "value": 384}}}, "type string": "[384]", "value": {"expression": "bits", "encoding": "hex", "width": 384, "data": "00"}}),
+///     );
+/// let mut cryptol_client = CryptolClient::builder()
+///     .transport(Arc::new(transport))
+///     .connect()
+///     .unwrap();
+/// assert_eq!(digest_width(&mut cryptol_client, "0x0001"), Some(384));
+/// ```
+pub trait CryptolSession {
+    /// This function loads `module`. See
+    /// [`CryptolClient::load_module`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the module cannot be loaded.
+    fn load_module(&mut self, module: &str) -> ClientResult<()>;
+
+    /// This function returns the modules loaded, in the order they were
+    /// loaded. See [`CryptolClient::loaded_modules`].
+    fn loaded_modules(&self) -> &[String];
+
+    /// This function calls `function` with `arguments`. See
+    /// [`CryptolClient::call`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the call fails.
+    fn call(&mut self, function: &str, arguments: Args) -> ClientResult<Answer>;
+
+    /// This function evaluates `expression`. See
+    /// [`CryptolClient::evaluate`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the evaluation fails.
+    fn evaluate(&mut self, expression: &str) -> ClientResult<Answer>;
+
+    /// This function returns the type of `expression`. See
+    /// [`CryptolClient::type_of`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the expression does not type
+    /// check.
+    fn type_of(&mut self, expression: &str) -> ClientResult<TypeSchema>;
+
+    /// This function defines `name` as the value of `expression` and
+    /// returns the value. See [`CryptolClient::define`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the expression cannot be
+    /// evaluated.
+    fn define(&mut self, name: &str, expression: &str) -> ClientResult<CryptolValue>;
+
+    /// This function tests `property` on as many arguments as `tests`
+    /// asks for. See [`CryptolClient::check`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the property cannot be
+    /// tested. A property that does not hold is not an error.
+    fn check(&mut self, property: &str, tests: TestCount) -> ClientResult<CheckResult>;
+
+    /// This function proves `property` with the configured solver. See
+    /// [`CryptolClient::prove`].
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the property cannot be
+    /// proved or refuted. A property that does not hold is not an
+    /// error.
+    fn prove(&mut self, property: &str) -> ClientResult<ProveResult>;
+}
+
+impl CryptolSession for CryptolClient {
+    fn load_module(&mut self, module: &str) -> ClientResult<()> {
+        CryptolClient::load_module(self, module)
+    }
+
+    fn loaded_modules(&self) -> &[String] {
+        CryptolClient::loaded_modules(self)
+    }
+
+    fn call(&mut self, function: &str, arguments: Args) -> ClientResult<Answer> {
+        CryptolClient::call(self, function, arguments)
+    }
+
+    fn evaluate(&mut self, expression: &str) -> ClientResult<Answer> {
+        CryptolClient::evaluate(self, expression)
+    }
+
+    fn type_of(&mut self, expression: &str) -> ClientResult<TypeSchema> {
+        CryptolClient::type_of(self, expression)
+    }
+
+    fn define(&mut self, name: &str, expression: &str) -> ClientResult<CryptolValue> {
+        CryptolClient::define(self, name, expression)
+    }
+
+    fn check(&mut self, property: &str, tests: TestCount) -> ClientResult<CheckResult> {
+        CryptolClient::check(self, property, tests)
+    }
+
+    fn prove(&mut self, property: &str) -> ClientResult<ProveResult> {
+        CryptolClient::prove(self, property)
+    }
+}
+
+/// A persistent session carries out the commands of the trait by
+/// moving to the session they return.
+impl CryptolSession for functional::Session {
+    fn load_module(&mut self, module: &str) -> ClientResult<()> {
+        *self = functional::Session::load_module(self, module)?;
+        Ok(())
+    }
+
+    fn loaded_modules(&self) -> &[String] {
+        functional::Session::loaded_modules(self)
+    }
+
+    fn call(&mut self, function: &str, arguments: Args) -> ClientResult<Answer> {
+        functional::Session::call(self, function, arguments)
+    }
+
+    fn evaluate(&mut self, expression: &str) -> ClientResult<Answer> {
+        functional::Session::evaluate(self, expression)
+    }
+
+    fn type_of(&mut self, expression: &str) -> ClientResult<TypeSchema> {
+        functional::Session::type_of(self, expression)
+    }
+
+    fn define(&mut self, name: &str, expression: &str) -> ClientResult<CryptolValue> {
+        let (session, value) = functional::Session::define(self, name, expression)?;
+        *self = session;
+        Ok(value)
+    }
+
+    fn check(&mut self, property: &str, tests: TestCount) -> ClientResult<CheckResult> {
+        functional::Session::check(self, property, tests)
+    }
+
+    fn prove(&mut self, property: &str) -> ClientResult<ProveResult> {
+        functional::Session::prove(self, property)
+    }
+}
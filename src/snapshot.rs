@@ -0,0 +1,110 @@
+//! # Snapshots
+//!
+//! A `Snapshot` records the state of a `CryptolClient` so that it can
+//! be returned to later with `CryptolClient::restore`, e.g. to run
+//! several experiments from the same loaded module.
+//!
+//! The state itself lives on the server, and `cryptol-remote-api` may
+//! forget it: when started with `--max-occupancy`, it keeps only that
+//! many states and drops the least recently used ones. `restore`
+//! therefore checks that the state of the snapshot is still known to
+//! the server. If it is not, a warning is logged and the state is
+//! rebuilt by loading the modules of the snapshot again, in the order
+//! they were loaded.
+
+use std::mem;
+
+use serde_json::json;
+
+use crate::error::Error;
+use crate::value::CryptolValue;
+use crate::{CryptolClient, Result};
+
+/// The state of a `CryptolClient` at some point, created by
+/// [`CryptolClient::snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    state: Option<String>,
+    loaded: Option<(String, String)>,
+    modules: Vec<String>,
+    definitions: Vec<(String, CryptolValue)>,
+    pending_modules: Vec<String>,
+}
+
+impl Snapshot {
+    /// This function returns the token of the state the snapshot was
+    /// taken in, or `None` if the client had not made a request yet.
+    #[must_use]
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    /// This function returns the modules loaded when the snapshot was
+    /// taken. See [`CryptolClient::loaded_modules`].
+    #[must_use]
+    pub fn loaded_modules(&self) -> &[String] {
+        &self.modules
+    }
+
+    /// This function returns the definitions made when the snapshot
+    /// was taken. See [`CryptolClient::definitions`].
+    #[must_use]
+    pub fn definitions(&self) -> &[(String, CryptolValue)] {
+        &self.definitions
+    }
+}
+
+impl CryptolClient {
+    /// This function records the current state of the client, along
+    /// with its loaded modules and definitions.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.session.state().map(str::to_string),
+            loaded: self.loaded.clone(),
+            modules: self.modules.clone(),
+            definitions: self.definitions.clone(),
+            pending_modules: self.pending_modules.clone(),
+        }
+    }
+
+    /// This function returns the client to the state recorded in
+    /// `snapshot`. If the server no longer knows that state, it is
+    /// rebuilt by loading the modules of the snapshot again, which
+    /// picks up any changes to their source since.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a POST request to
+    /// `cryptol-remote-api` fails, or if the state has expired and a
+    /// module of the snapshot can no longer be loaded.
+    #[tracing::instrument(skip_all, fields(state = snapshot.state()))]
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        self.session.set_state(snapshot.state.clone());
+        self.loaded.clone_from(&snapshot.loaded);
+        self.modules.clone_from(&snapshot.modules);
+        self.definitions.clone_from(&snapshot.definitions);
+        self.pending_modules.clone_from(&snapshot.pending_modules);
+        if self.session.state().is_none() {
+            return Ok(());
+        }
+
+        // A query from a state the server has dropped fails.
+        let mut params = self.session.params();
+        params.insert("expression".into(), json!("True"));
+        let Err(e) = self.request("check type", &params) else {
+            return Ok(());
+        };
+        if !matches!(e.downcast_ref::<Error>(), Some(Error::Server(_))) {
+            return Err(e);
+        }
+
+        tracing::warn!(error = %e, "state has expired, loading its modules again");
+        self.session.set_state(None);
+        self.loaded = None;
+        for module in mem::take(&mut self.modules) {
+            self.load_module(&module)?;
+        }
+        Ok(())
+    }
+}
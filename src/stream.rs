@@ -0,0 +1,228 @@
+//! # Streaming
+//!
+//! Very large results, e.g. megabytes of keystream, are expensive to
+//! receive in one piece: the server encodes them as a single JSON
+//! string that has to be buffered in full before it can be decoded.
+//! `CryptolClient::stream_bytes` instead evaluates the result in
+//! windows of bytes, using `take` and `drop`, and writes each window to
+//! a sink as it arrives. `CryptolClient::evaluate_to_file` streams a
+//! result to a file, e.g. to produce test vectors for other tools.
+//! `CryptolClient::evaluate_windowed` retrieves a long sequence of any
+//! type in windows of elements and stitches them together, so that no
+//! single response is too large.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::value::{BitVector, CryptolValue};
+use crate::{CryptolClient, Result};
+
+/// The number of bytes `evaluate_to_file` requests at a time.
+const FILE_CHUNK_BYTES: usize = 1 << 20;
+
+impl CryptolClient {
+    /// This function evaluates the given Cryptol expression, which
+    /// must have type `[8 * n]` or `[n][8]` for some finite `n`, and
+    /// writes its bytes to `writer`, most significant byte first. At
+    /// most `chunk_bytes` bytes are requested from the server at a
+    /// time. The number of bytes written is returned.
+    ///
+    /// The expression is evaluated once per window, so it should be
+    /// cheap to re-evaluate, e.g. the name of a top-level definition.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let mut file = std::fs::File::create("keystream.bin").unwrap();
+    /// cryptol_client
+    ///     .stream_bytes("take`{2^^20} keystream", 1 << 16, &mut file)
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the expression does not have
+    /// one of the types above, if `chunk_bytes` is zero, if a request
+    /// to `cryptol-remote-api` fails, or if writing fails.
+    #[tracing::instrument(skip(self, writer))]
+    pub fn stream_bytes<W: Write>(
+        &mut self,
+        expression: &str,
+        chunk_bytes: usize,
+        mut writer: W,
+    ) -> Result<u64> {
+        if chunk_bytes == 0 {
+            return Err(Error::Decode("chunk size must be positive".to_string()).into());
+        }
+
+        let schema = self.type_schema(expression)?;
+        let (bytes, len) = byte_sequence(expression, &schema["type"])?;
+
+        let mut written = 0u64;
+        let mut offset = 0;
+        while offset < len {
+            let window = chunk_bytes.min(len - offset);
+            let answer = self.evaluate(&format!(
+                "join (take`{{{window}}} (drop`{{{offset}}} {bytes}))"
+            ))?;
+            match answer.decode()? {
+                CryptolValue::BitVector(bits) if bits.width() == 8 * window => {
+                    writer.write_all(bits.as_bytes())?;
+                }
+                _ => {
+                    return Err(Error::Decode(format!("unexpected window at byte {offset}")).into())
+                }
+            }
+            written += window as u64;
+            offset += window;
+        }
+        writer.flush()?;
+
+        Ok(written)
+    }
+
+    /// This function evaluates the given Cryptol expression, which
+    /// must have type `[8 * n]` or `[n][8]` for some finite `n`, and
+    /// writes its bytes to the file at `path`, most significant byte
+    /// first, replacing the file if it exists. See
+    /// [`stream_bytes`](Self::stream_bytes). The number of bytes
+    /// written is returned.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// cryptol_client.load_module("SuiteB").unwrap();
+    /// cryptol_client
+    ///     .evaluate_to_file("sha384 (join \"abc\")", "sha384-abc.bin")
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the expression does not have
+    /// one of the types above, if a request to `cryptol-remote-api`
+    /// fails, or if the file cannot be written. A file that was
+    /// created is removed again if writing it fails.
+    #[tracing::instrument(skip(self, path), fields(path = %path.as_ref().display()))]
+    pub fn evaluate_to_file(&mut self, expression: &str, path: impl AsRef<Path>) -> Result<u64> {
+        let path = path.as_ref();
+        let file = BufWriter::new(File::create(path)?);
+        let result = self.stream_bytes(expression, FILE_CHUNK_BYTES, file);
+        if result.is_err() {
+            // A partial file must not be mistaken for a result.
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+
+    /// This function evaluates the given Cryptol expression like
+    /// [`evaluate`](Self::evaluate) and decodes its value, but requests
+    /// a finite sequence longer than `window` elements in windows of at
+    /// most `window` elements, using `take` and `drop`, so that each
+    /// response stays bounded. A bitvector whose width is a whole number
+    /// of bytes is retrieved with [`stream_bytes`](Self::stream_bytes)
+    /// in windows of about `window` bits. Other values are evaluated in
+    /// one piece.
+    ///
+    /// The expression is evaluated once per window, so it should be
+    /// cheap to re-evaluate, e.g. the name of a top-level definition.
+    ///
+    /// ```no_run
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let mut cryptol_client = CryptolClient::connect().unwrap();
+    /// let vectors = cryptol_client
+    ///     .evaluate_windowed("[ (i, i * i) | i <- [0 .. 99999] ] : [_]([32], [32])", 4096)
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `window` is zero, if a request
+    /// to `cryptol-remote-api` fails, or `Error::Decode` if a window
+    /// does not hold the expected elements.
+    #[tracing::instrument(skip(self))]
+    pub fn evaluate_windowed(&mut self, expression: &str, window: usize) -> Result<CryptolValue> {
+        if window == 0 {
+            return Err(Error::Decode("window size must be positive".to_string()).into());
+        }
+
+        let schema = self.type_schema(expression)?;
+        let ty = &schema["type"];
+        let length = |ty: &Value| match (ty["type"].as_str(), ty["value"].as_u64()) {
+            (Some("number"), Some(n)) => usize::try_from(n).ok(),
+            _ => None,
+        };
+        match ty["type"].as_str() {
+            Some("bitvector") if length(&ty["width"]).is_some_and(|w| w % 8 == 0 && w > window) => {
+                let mut bytes = Vec::new();
+                self.stream_bytes(expression, (window / 8).max(1), &mut bytes)?;
+                Ok(CryptolValue::BitVector(BitVector::from_be_bytes(bytes)))
+            }
+            Some("sequence") if length(&ty["length"]).is_some_and(|len| len > window) => {
+                let len = length(&ty["length"]).unwrap_or_default();
+                let mut elements = Vec::with_capacity(len);
+                while elements.len() < len {
+                    let offset = elements.len();
+                    let size = window.min(len - offset);
+                    let answer = self.evaluate(&format!(
+                        "take`{{{size}}} (drop`{{{offset}}} ({expression}))"
+                    ))?;
+                    match answer.decode()? {
+                        CryptolValue::Sequence(chunk) if chunk.len() == size => {
+                            elements.extend(chunk);
+                        }
+                        _ => {
+                            return Err(Error::Decode(format!(
+                                "unexpected window at element {offset}"
+                            ))
+                            .into())
+                        }
+                    }
+                }
+                Ok(CryptolValue::Sequence(elements))
+            }
+            _ => Ok(self.evaluate(expression)?.decode()?),
+        }
+    }
+}
+
+/// This function returns a Cryptol expression of type `[len][8]` for
+/// the bytes of `expression`, which has the given type, along with
+/// `len`.
+fn byte_sequence(expression: &str, ty: &Value) -> std::result::Result<(String, usize), Error> {
+    let number = |ty: &Value| match (ty["type"].as_str(), ty["value"].as_u64()) {
+        (Some("number"), Some(n)) => usize::try_from(n).ok(),
+        _ => None,
+    };
+
+    match ty["type"].as_str() {
+        Some("bitvector") => match number(&ty["width"]) {
+            Some(width) if width % 8 == 0 => Ok((
+                format!("(split`{{each=8}} ({expression}) : [{}][8])", width / 8),
+                width / 8,
+            )),
+            _ => Err(Error::Decode(format!(
+                "{expression} is not a whole number of bytes"
+            ))),
+        },
+        Some("sequence")
+            if ty["contents"]["type"] == "bitvector"
+                && number(&ty["contents"]["width"]) == Some(8) =>
+        {
+            match number(&ty["length"]) {
+                Some(len) => Ok((format!("({expression})"), len)),
+                None => Err(Error::Decode(format!("{expression} is not finite"))),
+            }
+        }
+        _ => Err(Error::Decode(format!(
+            "{expression} does not have type [8 * n] or [n][8]"
+        ))),
+    }
+}
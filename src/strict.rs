@@ -0,0 +1,131 @@
+//! # Strict decoding
+//!
+//! Every answer of `cryptol-remote-api` carries the type of its value
+//! next to the value itself. A client built with
+//! `CryptolClientBuilder::strict_decoding` checks each decoded value
+//! against that type, so that a decoding bug or a change in the
+//! server's encodings fails loudly at the answer instead of producing a
+//! value of the wrong shape.
+
+use crate::error::Error;
+use crate::types::CryptolType;
+use crate::value::{CryptolValue, Integer};
+
+impl CryptolValue {
+    /// This function checks that the value has the type `ty`: that
+    /// bitvectors have its widths, sequences and tuples its lengths,
+    /// records its fields and `Z n` values its modulus. Parts of the
+    /// type that are not known, such as type variables and type-level
+    /// arithmetic, match any value, as do values the server could not
+    /// encode.
+    ///
+    /// ```
+    /// use cryptol_client::types::CryptolType;
+    /// use cryptol_client::value::{BitVector, CryptolValue};
+    ///
+    /// let byte = CryptolValue::BitVector(BitVector::from_be_bytes(vec![0x2a]));
+    /// let ty = CryptolType::BitVector(Box::new(CryptolType::Number(8.into())));
+    /// assert!(byte.check_type(&ty).is_ok());
+    /// assert!(byte.check_type(&CryptolType::Bit).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` describing the first part
+    /// of the value that does not match the type.
+    pub fn check_type(&self, ty: &CryptolType) -> Result<(), Error> {
+        check(self, ty, "value")
+    }
+}
+
+/// This function checks `value` against `ty`, where `path` names the
+/// part of the checked value `value` is, e.g. `value.1[3]`.
+fn check(value: &CryptolValue, ty: &CryptolType, path: &str) -> Result<(), Error> {
+    let mismatch = |expected: &str| {
+        Error::Decode(format!(
+            "{path} is {}, which does not match its type {ty}: {expected}",
+            describe(value)
+        ))
+    };
+    let count = |expected: &CryptolType, actual: usize, what: &str| match expected {
+        CryptolType::Number(n)
+            if u64::try_from(actual).ok().map(Integer::from).as_ref() != Some(n) =>
+        {
+            Err(mismatch(&format!("expected {what} {n}")))
+        }
+        _ => Ok(()),
+    };
+
+    match (value, ty) {
+        (CryptolValue::Opaque(_), _)
+        | (_, CryptolType::Variable(_) | CryptolType::Other(_))
+        | (CryptolValue::Bit(_), CryptolType::Bit)
+        | (CryptolValue::Integer(_), CryptolType::Integer)
+        | (CryptolValue::Unit, CryptolType::Unit) => Ok(()),
+        (CryptolValue::Unit, CryptolType::Tuple(types)) if types.is_empty() => Ok(()),
+        (CryptolValue::Tuple(values), CryptolType::Unit) if values.is_empty() => Ok(()),
+        (CryptolValue::IntegerModulo { value: n, modulus }, CryptolType::Z(expected)) => {
+            if let CryptolType::Number(expected) = &**expected {
+                if expected != modulus {
+                    return Err(mismatch(&format!("expected modulus {expected}")));
+                }
+            }
+            if n.is_negative() || n >= modulus {
+                return Err(mismatch("the value is out of range"));
+            }
+            Ok(())
+        }
+        (CryptolValue::BitVector(bits), CryptolType::BitVector(width)) => {
+            count(width, bits.width(), "width")
+        }
+        (CryptolValue::Sequence(values), CryptolType::Sequence { length, contents }) => {
+            count(length, values.len(), "length")?;
+            values
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, value)| check(value, contents, &format!("{path}[{i}]")))
+        }
+        (CryptolValue::Tuple(values), CryptolType::Tuple(types)) => {
+            if values.len() != types.len() {
+                return Err(mismatch(&format!("expected {} elements", types.len())));
+            }
+            values
+                .iter()
+                .zip(types)
+                .enumerate()
+                .try_for_each(|(i, (value, ty))| check(value, ty, &format!("{path}.{i}")))
+        }
+        (CryptolValue::Record(fields), CryptolType::Record(types)) => {
+            if !fields.keys().eq(types.keys()) {
+                let names: Vec<&str> = types.keys().map(String::as_str).collect();
+                return Err(mismatch(&format!(
+                    "expected the fields {{{}}}",
+                    names.join(", ")
+                )));
+            }
+            fields
+                .iter()
+                .zip(types.values())
+                .try_for_each(|((name, value), ty)| check(value, ty, &format!("{path}.{name}")))
+        }
+        _ => Err(mismatch("the kinds differ")),
+    }
+}
+
+/// This function describes the shape of `value` for an error message.
+fn describe(value: &CryptolValue) -> String {
+    match value {
+        CryptolValue::Bit(_) => "a bit".to_string(),
+        CryptolValue::Integer(_) => "an integer".to_string(),
+        CryptolValue::IntegerModulo { modulus, .. } => format!("an integer modulo {modulus}"),
+        CryptolValue::BitVector(bits) => format!("a bitvector of width {}", bits.width()),
+        CryptolValue::Sequence(values) => format!("a sequence of length {}", values.len()),
+        CryptolValue::Tuple(values) => format!("a tuple of {} elements", values.len()),
+        CryptolValue::Record(fields) => {
+            let names: Vec<&str> = fields.keys().map(String::as_str).collect();
+            format!("a record with the fields {{{}}}", names.join(", "))
+        }
+        CryptolValue::Unit => "()".to_string(),
+        CryptolValue::Opaque(name) => format!("the value {name}"),
+    }
+}
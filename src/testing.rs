@@ -0,0 +1,103 @@
+//! # Testing against a server
+//!
+//! Integration tests of code that uses the client need a running
+//! `cryptol-remote-api`. A [`TestClient`] connects to one the way
+//! [`CryptolClient::connect`] would, lets the test skip itself when
+//! there is none, and clears the state the test ended in on the
+//! server when it goes out of scope. With the `macros` feature, the
+//! `#[cryptol_test]` attribute writes this for a test.
+//!
+//! ```no_run
+//! use cryptol_client::testing::TestClient;
+//! use cryptol_client::CryptolClient;
+//!
+//! #[test]
+//! fn sha384_of_one() {
+//!     let builder = CryptolClient::builder().initial_module("SuiteB");
+//!     let Some(mut cryptol_client) = TestClient::connect(builder) else {
+//!         return;
+//!     };
+//!     let answer = cryptol_client.call("sha384", &["0x0001"]).unwrap();
+//!     assert_eq!(answer.bit_width(), Some(384));
+//! }
+//! ```
+
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+
+use serde_json::json;
+
+use crate::error::Error;
+use crate::{CryptolClient, CryptolClientBuilder};
+
+/// A client for a test, created with [`TestClient::connect`]. It
+/// dereferences to the [`CryptolClient`].
+#[derive(Debug)]
+pub struct TestClient {
+    client: CryptolClient,
+}
+
+impl TestClient {
+    /// This function connects a client with `builder`, or returns
+    /// `None`, after saying why on standard error, if no server is
+    /// configured or none accepts the connection. The message is
+    /// written directly to standard error, so that it is shown even
+    /// though the test harness captures the output of passing tests.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the client cannot connect for another
+    /// reason, e.g. because an initial module cannot be loaded.
+    #[must_use]
+    pub fn connect(builder: CryptolClientBuilder) -> Option<TestClient> {
+        let skip = |reason: &str, e: &dyn std::error::Error| {
+            let thread = std::thread::current();
+            let test = thread.name().unwrap_or("test");
+            // A failure to say why a test is skipped is not worth
+            // failing the test over.
+            let _ = writeln!(io::stderr(), "skipping {test}: {reason}: {e}");
+            None
+        };
+        let mut client = match builder.connect_lazy() {
+            Ok(client) => client,
+            Err(e) => return skip("no cryptol-remote-api is configured", e.as_ref()),
+        };
+        match client.load_pending_modules() {
+            Ok(()) => Some(TestClient { client }),
+            Err(e) if matches!(e.downcast_ref::<Error>(), Some(Error::Transport(_))) => {
+                skip("no cryptol-remote-api is reachable", e.as_ref())
+            }
+            Err(e) => panic!("cannot connect to cryptol-remote-api: {e}"),
+        }
+    }
+}
+
+impl Deref for TestClient {
+    type Target = CryptolClient;
+
+    fn deref(&self) -> &CryptolClient {
+        &self.client
+    }
+}
+
+impl DerefMut for TestClient {
+    fn deref_mut(&mut self) -> &mut CryptolClient {
+        &mut self.client
+    }
+}
+
+impl Drop for TestClient {
+    /// This function asks the server to forget the state the test
+    /// ended in. Failures are logged and otherwise ignored.
+    fn drop(&mut self) {
+        let Some(state) = self.client.session.state().map(str::to_string) else {
+            return;
+        };
+        let mut params = self.client.session.params();
+        params.insert("state to clear".into(), json!(state));
+        match self.client.request("clear state", &params) {
+            Ok(_) => tracing::debug!(state, "cleared the state of the test"),
+            Err(e) => tracing::debug!(state, error = %e, "cannot clear the state of the test"),
+        }
+    }
+}
@@ -0,0 +1,591 @@
+//! # Test vectors
+//!
+//! A test vector is a function of a specification, the inputs it was
+//! applied to, and the output it gave. [`CryptolClient::generate_test_vectors`]
+//! runs a set of [`TestCase`]s through `cryptol-remote-api`, and
+//! [`write()`] stores the vectors in a JSON or CSV file, to be kept
+//! under version control. [`verify`] later checks a Rust
+//! implementation against the file, without a server, and
+//! [`CryptolClient::verify_test_vectors`] checks the specification
+//! itself, e.g. after it was changed.
+//!
+//! ```no_run
+//! use cryptol_client::convert::{FromCryptolValue, ToCryptolValue};
+//! use cryptol_client::testvec::{self, TestCase};
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! cryptol_client.load_module("SuiteB").unwrap();
+//! let cases = (0..16u16).map(|n| TestCase::new("sha384", [n.to_cryptol_value()]));
+//! let vectors = cryptol_client.generate_test_vectors(cases).unwrap();
+//! testvec::write("tests/vectors/sha384.csv", &vectors).unwrap();
+//!
+//! // Later, in a test of the Rust implementation:
+//! let vectors = testvec::read("tests/vectors/sha384.csv").unwrap();
+//! let mismatches = testvec::verify(&vectors, |_function, inputs| {
+//!     let n = u16::from_cryptol_value(inputs[0].clone())?;
+//!     Ok(my_sha384(&n.to_be_bytes()).to_cryptol_value())
+//! });
+//! assert!(mismatches.is_empty(), "{mismatches:?}");
+//! # fn my_sha384(_: &[u8]) -> [u8; 48] { [0; 48] }
+//! ```
+//!
+//! Values are written as Cryptol expressions, e.g. `0x0001` or
+//! `[0x01, 0x02]`, so files are readable and diff well. A JSON file
+//! holds an array of objects with the fields `function`, `inputs` and
+//! `output`. The rows of a CSV file are the function, the inputs and
+//! the output, after a header row, with the inputs of functions of
+//! fewer parameters padded with empty cells.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::value::{BitVector, CryptolValue, Integer};
+use crate::{golden, Args, CryptolClient, Result};
+
+/// A function and the inputs to apply it to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    /// The function, e.g. `sha384`, in the loaded module.
+    pub function: String,
+    /// The inputs, in order.
+    pub inputs: Vec<CryptolValue>,
+}
+
+impl TestCase {
+    /// This function creates a test case applying `function` to
+    /// `inputs`.
+    pub fn new(
+        function: impl Into<String>,
+        inputs: impl IntoIterator<Item = CryptolValue>,
+    ) -> Self {
+        TestCase {
+            function: function.into(),
+            inputs: inputs.into_iter().collect(),
+        }
+    }
+}
+
+/// A test case and the output of the specification on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    /// The function applied.
+    pub function: String,
+    /// The inputs it was applied to.
+    pub inputs: Vec<CryptolValue>,
+    /// The output it gave.
+    pub output: CryptolValue,
+}
+
+/// A test vector whose output was not reproduced by [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The test vector, with the expected output.
+    pub vector: TestVector,
+    /// The output given instead, or why none was.
+    pub actual: std::result::Result<CryptolValue, String>,
+}
+
+/// The format of a test-vector file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A JSON array of objects.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+impl Format {
+    /// This function returns the format of the file at `path`, from
+    /// its extension, `.json` or `.csv`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Decode` for any other extension.
+    pub fn from_path(path: &Path) -> std::result::Result<Self, Error> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(Format::Json),
+            Some("csv") => Ok(Format::Csv),
+            _ => Err(Error::Decode(format!(
+                "{} is not a .json or .csv file",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// A test vector as written in a JSON file.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    function: String,
+    inputs: Vec<String>,
+    output: String,
+}
+
+/// This function renders `vectors` as a JSON test-vector file.
+#[must_use]
+pub fn to_json(vectors: &[TestVector]) -> String {
+    let records: Vec<Record> = vectors
+        .iter()
+        .map(|vector| Record {
+            function: vector.function.clone(),
+            inputs: vector
+                .inputs
+                .iter()
+                .map(CryptolValue::to_expression)
+                .collect(),
+            output: vector.output.to_expression(),
+        })
+        .collect();
+    // Strings always serialize.
+    golden::to_canonical_json(&records).unwrap_or_default()
+}
+
+/// This function reads the test vectors of a JSON test-vector file.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if `json` is not a test-vector
+/// file or a value is not written as `to_json` writes it.
+pub fn from_json(json: &str) -> std::result::Result<Vec<TestVector>, Error> {
+    let records: Vec<Record> =
+        serde_json::from_str(json).map_err(|e| Error::Decode(e.to_string()))?;
+    records
+        .into_iter()
+        .map(|record| {
+            Ok(TestVector {
+                inputs: record
+                    .inputs
+                    .iter()
+                    .map(|input| parse_value(input))
+                    .collect::<std::result::Result<_, Error>>()?,
+                output: parse_value(&record.output)?,
+                function: record.function,
+            })
+        })
+        .collect()
+}
+
+/// This function renders `vectors` as a CSV test-vector file.
+#[must_use]
+pub fn to_csv(vectors: &[TestVector]) -> String {
+    let arity = vectors
+        .iter()
+        .map(|vector| vector.inputs.len())
+        .max()
+        .unwrap_or(0);
+    let mut csv = String::from("function");
+    for i in 1..=arity {
+        // Writing to a `String` cannot fail.
+        let _ = write!(csv, ",input {i}");
+    }
+    csv.push_str(",output\n");
+    for vector in vectors {
+        csv.push_str(&csv_cell(&vector.function));
+        for i in 0..arity {
+            csv.push(',');
+            if let Some(input) = vector.inputs.get(i) {
+                csv.push_str(&csv_cell(&input.to_expression()));
+            }
+        }
+        csv.push(',');
+        csv.push_str(&csv_cell(&vector.output.to_expression()));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// This function reads the test vectors of a CSV test-vector file.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if a row does not have as many
+/// cells as the header, or a value is not written as `to_csv` writes
+/// it.
+pub fn from_csv(csv: &str) -> std::result::Result<Vec<TestVector>, Error> {
+    let mut rows = csv.lines().filter(|line| !line.is_empty());
+    let columns = match rows.next() {
+        Some(header) => csv_cells(header)?.len(),
+        None => return Ok(Vec::new()),
+    };
+    rows.enumerate()
+        .map(|(i, row)| {
+            let cells = csv_cells(row)?;
+            if cells.len() != columns || columns < 2 {
+                return Err(Error::Decode(format!(
+                    "row {} has {} cells rather than {columns}",
+                    i + 2,
+                    cells.len()
+                )));
+            }
+            Ok(TestVector {
+                function: cells[0].clone(),
+                inputs: cells[1..columns - 1]
+                    .iter()
+                    .filter(|cell| !cell.is_empty())
+                    .map(|cell| parse_value(cell))
+                    .collect::<std::result::Result<_, Error>>()?,
+                output: parse_value(&cells[columns - 1])?,
+            })
+        })
+        .collect()
+}
+
+/// This function writes `vectors` to the file at `path`, in the format
+/// of its extension, `.json` or `.csv`.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if the extension is neither,
+/// or an I/O error if the file cannot be written.
+pub fn write(path: impl AsRef<Path>, vectors: &[TestVector]) -> Result<()> {
+    let path = path.as_ref();
+    let contents = match Format::from_path(path)? {
+        Format::Json => to_json(vectors),
+        Format::Csv => to_csv(vectors),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// This function reads the test vectors of the file at `path`, in the
+/// format of its extension, `.json` or `.csv`.
+///
+/// # Errors
+///
+/// The function returns `Error::Decode` if the extension is neither or
+/// the file is not a test-vector file, or an I/O error if it cannot be
+/// read.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<TestVector>> {
+    let path = path.as_ref();
+    let format = Format::from_path(path)?;
+    let contents = fs::read_to_string(path)?;
+    Ok(match format {
+        Format::Json => from_json(&contents)?,
+        Format::Csv => from_csv(&contents)?,
+    })
+}
+
+/// This function applies `implementation` to the function and inputs
+/// of each test vector and returns those whose output it does not
+/// reproduce, in order.
+pub fn verify<F>(vectors: &[TestVector], mut implementation: F) -> Vec<Mismatch>
+where
+    F: FnMut(&str, &[CryptolValue]) -> std::result::Result<CryptolValue, Error>,
+{
+    mismatches(vectors, |function, inputs| {
+        implementation(function, inputs).map_err(|e| e.to_string())
+    })
+}
+
+/// This function returns the test vectors whose output `run` does not
+/// reproduce.
+fn mismatches<F>(vectors: &[TestVector], mut run: F) -> Vec<Mismatch>
+where
+    F: FnMut(&str, &[CryptolValue]) -> std::result::Result<CryptolValue, String>,
+{
+    vectors
+        .iter()
+        .filter_map(|vector| {
+            let actual = run(&vector.function, &vector.inputs);
+            (actual.as_ref() != Ok(&vector.output)).then(|| Mismatch {
+                vector: vector.clone(),
+                actual,
+            })
+        })
+        .collect()
+}
+
+impl CryptolClient {
+    /// This function calls the function of each test case on its
+    /// inputs, in the loaded module, and returns the test vectors, in
+    /// order. See [`testvec`](crate::testvec).
+    ///
+    /// # Errors
+    ///
+    /// The function returns the error of the first call that fails, or
+    /// `Error::Decode` if an output cannot be decoded.
+    pub fn generate_test_vectors(
+        &mut self,
+        cases: impl IntoIterator<Item = TestCase>,
+    ) -> Result<Vec<TestVector>> {
+        cases
+            .into_iter()
+            .map(|case| {
+                let output = self.call_values(&case.function, &case.inputs)?;
+                Ok(TestVector {
+                    function: case.function,
+                    inputs: case.inputs,
+                    output,
+                })
+            })
+            .collect()
+    }
+
+    /// This function calls the function of each test vector on its
+    /// inputs, in the loaded module, and returns those whose output
+    /// the specification no longer gives, in order. A call that fails
+    /// is a mismatch.
+    pub fn verify_test_vectors(&mut self, vectors: &[TestVector]) -> Vec<Mismatch> {
+        mismatches(vectors, |function, inputs| {
+            self.call_values(function, inputs)
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// This function calls `function` on `inputs` and decodes the
+    /// output.
+    fn call_values(&mut self, function: &str, inputs: &[CryptolValue]) -> Result<CryptolValue> {
+        let arguments = inputs.iter().fold(Args::new(), Args::cryptol_value);
+        Ok(self.call(function, &arguments)?.decode()?)
+    }
+}
+
+/// This function returns `cell` as a CSV cell, quoted if it holds a
+/// comma or a quote.
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// This function splits a CSV row into its cells.
+fn csv_cells(row: &str) -> std::result::Result<Vec<String>, Error> {
+    let mut cells = Vec::new();
+    let mut chars = row.chars().peekable();
+    loop {
+        let mut cell = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        cell.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => cell.push(c),
+                    None => return Err(Error::Decode(format!("unterminated quote in {row:?}"))),
+                }
+            }
+        }
+        while let Some(c) = chars.next_if(|&c| c != ',') {
+            cell.push(c);
+        }
+        cells.push(cell);
+        if chars.next().is_none() {
+            return Ok(cells);
+        }
+    }
+}
+
+/// This function reads a value written by
+/// [`CryptolValue::to_expression`].
+fn parse_value(source: &str) -> std::result::Result<CryptolValue, Error> {
+    let mut parser = Parser {
+        source,
+        position: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < source.len() {
+        return Err(parser.error());
+    }
+    Ok(value)
+}
+
+/// A reader of the concrete syntax of values.
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.position..]
+    }
+
+    fn error(&self) -> Error {
+        Error::Decode(format!(
+            "invalid value {:?} at column {}",
+            self.source,
+            self.position + 1
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.position = self.source.len() - self.rest().trim_start().len();
+    }
+
+    /// This function skips `token`, if the rest of the source starts
+    /// with it after whitespace.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.position += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> std::result::Result<(), Error> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    /// This function reads a word: an identifier or a number.
+    fn word(&mut self) -> &str {
+        self.skip_whitespace();
+        let start = self.position;
+        let length = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\'' || c == ':'))
+            .unwrap_or(self.rest().len());
+        // A `::` belongs to a qualified name, a single `:` to a type
+        // annotation.
+        let word = &self.source[start..start + length];
+        let length = word
+            .char_indices()
+            .find(|&(i, c)| c == ':' && !word[i..].starts_with("::") && !word[..i].ends_with(':'))
+            .map_or(length, |(i, _)| i);
+        self.position += length;
+        &self.source[start..start + length]
+    }
+
+    fn integer(&mut self) -> std::result::Result<Integer, Error> {
+        let negative = self.eat("-");
+        let digits = self.word();
+        let n: Integer = digits.parse().map_err(|_| self.error())?;
+        Ok(if negative {
+            Integer::from_sign_magnitude(true, n.magnitude())
+        } else {
+            n
+        })
+    }
+
+    /// This function reads the elements of a list up to `close`.
+    fn elements(&mut self, close: &str) -> std::result::Result<Vec<CryptolValue>, Error> {
+        let mut elements = Vec::new();
+        if self.eat(close) {
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.value()?);
+            if self.eat(close) {
+                return Ok(elements);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn value(&mut self) -> std::result::Result<CryptolValue, Error> {
+        if self.eat("[") {
+            return Ok(CryptolValue::Sequence(self.elements("]")?));
+        }
+        if self.eat("{") {
+            let mut fields = BTreeMap::new();
+            if self.eat("}") {
+                return Ok(CryptolValue::Record(fields));
+            }
+            loop {
+                let name = self.word().to_string();
+                self.expect("=")?;
+                fields.insert(name, self.value()?);
+                if self.eat("}") {
+                    return Ok(CryptolValue::Record(fields));
+                }
+                self.expect(",")?;
+            }
+        }
+        if self.eat("(") {
+            if self.eat(")") {
+                return Ok(CryptolValue::Unit);
+            }
+            // A negative integer is parenthesized, with or without its
+            // type.
+            let negative = self.rest().starts_with('-');
+            let first = if negative {
+                CryptolValue::Integer(self.integer()?)
+            } else {
+                self.value()?
+            };
+            if self.eat(":") {
+                let value = match (first, self.eat("Z")) {
+                    (CryptolValue::Integer(n), false) if self.eat("Integer") => {
+                        CryptolValue::Integer(n)
+                    }
+                    (CryptolValue::Integer(value), true) => CryptolValue::IntegerModulo {
+                        value,
+                        modulus: self.integer()?,
+                    },
+                    (CryptolValue::Integer(n), false) if n.magnitude().is_empty() => {
+                        self.expect("[")?;
+                        self.expect("0")?;
+                        self.expect("]")?;
+                        CryptolValue::BitVector(BitVector::from_be_bytes(Vec::new()))
+                    }
+                    _ => return Err(self.error()),
+                };
+                self.expect(")")?;
+                return Ok(value);
+            }
+            if negative {
+                self.expect(")")?;
+                return Ok(first);
+            }
+            let mut elements = vec![first];
+            if !self.eat(")") {
+                self.expect(",")?;
+                elements.extend(self.elements(")")?);
+            }
+            return Ok(CryptolValue::Tuple(elements));
+        }
+
+        let word = self.word().to_string();
+        if let Some(digits) = word.strip_prefix("0x") {
+            return Ok(CryptolValue::BitVector(BitVector::from_hex(
+                4 * digits.len(),
+                digits,
+            )?));
+        }
+        if let Some(digits) = word.strip_prefix("0b") {
+            let width = digits.len();
+            let mut bytes = vec![0u8; width.div_ceil(8)];
+            for (i, digit) in digits.chars().enumerate() {
+                let bit = width - 1 - i;
+                match digit {
+                    '0' => {}
+                    '1' => {
+                        let byte = bytes.len() - 1 - bit / 8;
+                        bytes[byte] |= 1 << (bit % 8);
+                    }
+                    _ => return Err(self.error()),
+                }
+            }
+            return Ok(CryptolValue::BitVector(BitVector::new(width, bytes)?));
+        }
+        match word.as_str() {
+            "True" => Ok(CryptolValue::Bit(true)),
+            "False" => Ok(CryptolValue::Bit(false)),
+            "" => Err(self.error()),
+            _ if word.starts_with(|c: char| c.is_ascii_digit()) => word
+                .parse()
+                .map(CryptolValue::Integer)
+                .map_err(|_| self.error()),
+            _ => Ok(CryptolValue::Opaque(word)),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! # Trace output
+//!
+//! Cryptol's `trace` and `traceVal` print a message and a value while
+//! an expression is evaluated, e.g. `traceVal "x" x` prints `x 0x01`.
+//! `cryptol-remote-api` returns what was printed in the `stdout` of
+//! the response. Each line of it becomes a `TraceEvent`, which is
+//! attached to the [`Answer`](crate::Answer) and also logged as a
+//! `tracing` event with target `cryptol_client::trace`.
+
+use serde::{Deserialize, Serialize};
+
+/// A line printed by Cryptol's `trace` or `traceVal` during a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEvent {
+    message: String,
+}
+
+impl TraceEvent {
+    /// This function creates an event for a line of output.
+    pub fn new(message: impl Into<String>) -> Self {
+        TraceEvent {
+            message: message.into(),
+        }
+    }
+
+    /// This function returns the printed line, i.e. the message
+    /// passed to `trace` followed by a space and the traced value.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// This function splits the `stdout` of a response into trace events,
+/// one per non-empty line.
+#[must_use]
+pub fn events(stdout: &str) -> Vec<TraceEvent> {
+    stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(TraceEvent::new)
+        .collect()
+}
+
+/// This function logs trace events as `tracing` events.
+pub(crate) fn emit(events: &[TraceEvent]) {
+    for event in events {
+        tracing::info!(target: "cryptol_client::trace", "{}", event.message);
+    }
+}
@@ -0,0 +1,164 @@
+//! # Transcripts
+//!
+//! A client built with [`CryptolClientBuilder::record_transcript`]
+//! keeps a record of every request it makes, in order: the method, its
+//! parameters, the state it was made from and the state it produced,
+//! or why it failed. [`CryptolClient::transcript`] returns the record,
+//! e.g. to log an auditable account of exactly what was asked of the
+//! server.
+//!
+//! Parameters can hold secrets, such as key material passed to a
+//! specification. A redactor set with
+//! [`CryptolClientBuilder::redact_transcript`] rewrites the parameters
+//! of each request before they are recorded.
+//!
+//! ```no_run
+//! use cryptol_client::CryptolClient;
+//! use serde_json::json;
+//!
+//! let mut cryptol_client = CryptolClient::builder()
+//!     .record_transcript(true)
+//!     .redact_transcript(|method, params| {
+//!         if method == "call" {
+//!             params.insert("arguments".into(), json!("<redacted>"));
+//!         }
+//!     })
+//!     .connect()
+//!     .unwrap();
+//! cryptol_client.load_module("SuiteB").unwrap();
+//! cryptol_client.call("sha384", &["0x0001"]).unwrap();
+//! for entry in cryptol_client.transcript() {
+//!     println!("{}", serde_json::to_string(entry).unwrap());
+//! }
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{CryptolClient, CryptolClientBuilder};
+
+/// A function rewriting the parameters of a request for `method`
+/// before they are recorded in a transcript.
+pub type Redactor = dyn Fn(&str, &mut Map<String, Value>) + Send + Sync;
+
+/// A request recorded in a transcript.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptEntry {
+    /// The method of the request, e.g. `load module`.
+    pub method: String,
+    /// The parameters of the request, after redaction, without the
+    /// state token.
+    pub params: Map<String, Value>,
+    /// The state the request was made from, or `None` for the initial
+    /// state of the server.
+    pub state: Option<String>,
+    /// The state the request left the client in, or `None` if it
+    /// failed. Queries, e.g. `call` or `version`, leave it in the state
+    /// they were made from, which is `None` for the initial state.
+    pub new_state: Option<String>,
+    /// Why the request failed, or `None` if it succeeded.
+    pub error: Option<String>,
+}
+
+/// The transcript of a client and how its entries are redacted.
+#[derive(Clone, Default)]
+pub(crate) struct Transcript {
+    pub(crate) entries: Vec<TranscriptEntry>,
+    pub(crate) redactor: Option<Arc<Redactor>>,
+}
+
+impl fmt::Debug for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transcript")
+            .field("entries", &self.entries.len())
+            .field("redactor", &self.redactor.is_some())
+            .finish()
+    }
+}
+
+impl CryptolClientBuilder {
+    /// Sets whether the client records a transcript of its requests,
+    /// including those answered from a cache. This is off by default.
+    /// See [`transcript`](crate::transcript).
+    #[must_use]
+    pub fn record_transcript(mut self, record_transcript: bool) -> Self {
+        self.transcript = match (record_transcript, self.transcript.take()) {
+            (true, transcript) => Some(transcript.unwrap_or_default()),
+            (false, _) => None,
+        };
+        self
+    }
+
+    /// Sets a function that rewrites the parameters of each request
+    /// before they are recorded in the transcript, e.g. to remove the
+    /// arguments of calls. It is given the method of the request. This
+    /// turns on [`record_transcript`](Self::record_transcript).
+    #[must_use]
+    pub fn redact_transcript(
+        mut self,
+        redactor: impl Fn(&str, &mut Map<String, Value>) + Send + Sync + 'static,
+    ) -> Self {
+        let mut transcript = self.transcript.take().unwrap_or_default();
+        transcript.redactor = Some(Arc::new(redactor));
+        self.transcript = Some(transcript);
+        self
+    }
+}
+
+impl CryptolClient {
+    /// This function returns the requests made by the client so far,
+    /// in order, or nothing if it does not record a transcript. A fork
+    /// of the client starts with the transcript of the client it was
+    /// forked from.
+    #[must_use]
+    pub fn transcript(&self) -> &[TranscriptEntry] {
+        self.transcript
+            .as_ref()
+            .map_or(&[], |transcript| &transcript.entries)
+    }
+
+    /// This function returns the requests recorded so far and empties
+    /// the transcript, e.g. to log it in batches.
+    pub fn take_transcript(&mut self) -> Vec<TranscriptEntry> {
+        self.transcript
+            .as_mut()
+            .map(|transcript| std::mem::take(&mut transcript.entries))
+            .unwrap_or_default()
+    }
+
+    /// This function records a request for `method` with `params` in
+    /// the transcript, if there is one, with the state it left the
+    /// client in or the error it failed with.
+    pub(crate) fn record_request(
+        &mut self,
+        method: &str,
+        params: &Map<String, Value>,
+        outcome: Result<Option<&str>, &dyn std::error::Error>,
+    ) {
+        let Some(transcript) = &mut self.transcript else {
+            return;
+        };
+        let mut params = params.clone();
+        let state = match params.remove("state") {
+            Some(Value::String(state)) => Some(state),
+            _ => None,
+        };
+        if let Some(redact) = &transcript.redactor {
+            redact(method, &mut params);
+        }
+        let (new_state, error) = match outcome {
+            Ok(new_state) => (new_state.map(str::to_string), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        transcript.entries.push(TranscriptEntry {
+            method: method.to_string(),
+            params,
+            state,
+            new_state,
+            error,
+        });
+    }
+}
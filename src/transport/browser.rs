@@ -0,0 +1,86 @@
+//! The transport for `wasm32` targets running in a browser.
+
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use web_sys::XmlHttpRequest;
+
+use crate::error::Error;
+use crate::parse;
+use crate::transport::{self, Transport};
+
+/// A transport that POSTs JSON-RPC requests to `cryptol-remote-api`
+/// from a browser.
+///
+/// `CryptolClient` is a blocking API, so requests are issued as
+/// synchronous `XMLHttpRequest`s. Browsers only allow these without
+/// a deprecation warning inside a Web Worker, which is where the
+/// client should run. The server must allow cross-origin requests
+/// from the page, e.g. via a reverse proxy.
+#[derive(Debug, Clone)]
+pub struct BrowserTransport {
+    url: String,
+}
+
+impl BrowserTransport {
+    /// This function creates a transport for the server at `url`.
+    /// No request is made until the transport is used.
+    #[must_use]
+    pub fn new(url: &str) -> Self {
+        BrowserTransport {
+            url: url.to_string(),
+        }
+    }
+}
+
+/// This function converts a JavaScript exception into an error.
+#[allow(clippy::needless_pass_by_value)] // called through `map_err`
+fn js_error(e: wasm_bindgen::JsValue) -> Error {
+    Error::Transport(format!("{e:?}").into())
+}
+
+impl BrowserTransport {
+    /// This function POSTs `body` and waits for the response text.
+    fn post(&self, body: &Value) -> Result<String, Error> {
+        let xhr = XmlHttpRequest::new().map_err(js_error)?;
+        xhr.open_with_async("POST", &self.url, false)
+            .map_err(js_error)?;
+        xhr.set_request_header("Content-Type", "application/json")
+            .map_err(js_error)?;
+        xhr.send_with_opt_str(Some(&body.to_string()))
+            .map_err(js_error)?;
+
+        let status = xhr.status().map_err(js_error)?;
+        if !(200..300).contains(&status) {
+            return Err(Error::Transport(
+                format!("request failed with HTTP status {status}").into(),
+            ));
+        }
+
+        Ok(xhr.response_text().map_err(js_error)?.unwrap_or_default())
+    }
+}
+
+impl Transport for BrowserTransport {
+    fn request(&self, method: &str, params: &Map<String, Value>) -> Result<Box<RawValue>, Error> {
+        let text = self.post(&transport::envelope(0, method, params))?;
+        parse::rpc_response(&text)
+    }
+
+    /// This function submits `requests` as a single JSON-RPC batch.
+    fn batch(
+        &self,
+        requests: &[(&str, Map<String, Value>)],
+    ) -> Result<Vec<Result<Box<RawValue>, Error>>, Error> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let body = requests
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| transport::envelope(id, method, params))
+            .collect();
+        let text = self.post(&Value::Array(body))?;
+        parse::rpc_batch_response(&text, requests.len())
+    }
+}
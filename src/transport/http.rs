@@ -0,0 +1,571 @@
+//! The HTTP transport for native targets, built on `hyper`.
+
+use std::fs;
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use hyper::body::HttpBody;
+use hyper::client::HttpConnector;
+use hyper::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Client, Request, Response, StatusCode, Uri};
+use hyper_rustls::{ConfigBuilderExt, HttpsConnector, HttpsConnectorBuilder};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use tokio::runtime::Runtime;
+
+use crate::error::{Error, PartialOutput};
+use crate::parse;
+use crate::transport::{self, Transport};
+use crate::value::decode_base64;
+
+/// A transport that POSTs JSON-RPC requests to `cryptol-remote-api`
+/// over HTTP or HTTPS.
+///
+/// Responses are requested compressed with gzip or deflate, which
+/// pays off for the large hex-encoded bitvectors the server tends to
+/// return. Connections are kept alive and pooled between requests, so
+/// a long run of small calls pays for connection setup only once.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: Client<HttpsConnector<HttpConnector>>,
+    uri: Uri,
+    runtime: Arc<Runtime>,
+    timeout: Duration,
+    accept_compressed: bool,
+    compress_requests: bool,
+    max_response_size: Option<usize>,
+    interrupt_on_timeout: bool,
+}
+
+/// How long to wait for the response to a request that timed out once
+/// it has been interrupted.
+const INTERRUPT_GRACE: Duration = Duration::from_secs(1);
+
+/// The status, `Content-Encoding` and body of a response.
+type Exchange = (StatusCode, Option<HeaderValue>, Vec<u8>);
+
+/// Builder for an `HttpTransport`.
+///
+/// ```no_run
+/// use cryptol_client::transport::HttpTransport;
+///
+/// let transport = HttpTransport::builder()
+///     .compress_requests(true)
+///     .max_idle_connections(4)
+///     .build("http://0.0.0.0:49352");
+/// ```
+#[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)] // each is an independent setting
+pub struct HttpTransportBuilder {
+    timeout: Duration,
+    accept_compressed: bool,
+    compress_requests: bool,
+    idle_timeout: Option<Duration>,
+    max_idle_connections: usize,
+    tcp_keep_alive: Option<Duration>,
+    http2_only: bool,
+    max_response_size: Option<usize>,
+    interrupt_on_timeout: bool,
+    ca_bundle: Option<PathBuf>,
+    client_cert: Option<PathBuf>,
+}
+
+impl Default for HttpTransportBuilder {
+    fn default() -> Self {
+        HttpTransportBuilder {
+            timeout: Duration::from_secs(60 * 60),
+            accept_compressed: true,
+            compress_requests: false,
+            idle_timeout: Some(Duration::from_secs(90)),
+            max_idle_connections: usize::MAX,
+            tcp_keep_alive: None,
+            http2_only: false,
+            max_response_size: None,
+            interrupt_on_timeout: false,
+            ca_bundle: None,
+            client_cert: None,
+        }
+    }
+}
+
+impl HttpTransportBuilder {
+    /// Sets how long to wait for the response to a request. The
+    /// default is one hour.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets whether to ask the server for gzip or deflate compressed
+    /// responses. Compressed responses are decompressed transparently.
+    /// This is on by default.
+    #[must_use]
+    pub fn accept_compressed(mut self, accept_compressed: bool) -> Self {
+        self.accept_compressed = accept_compressed;
+        self
+    }
+
+    /// Sets whether to gzip request bodies. This is off by default,
+    /// since the server must be set up to accept them.
+    #[must_use]
+    pub fn compress_requests(mut self, compress_requests: bool) -> Self {
+        self.compress_requests = compress_requests;
+        self
+    }
+
+    /// Sets how long an unused connection is kept open for reuse, or
+    /// `None` to keep it open indefinitely. The default is 90 seconds.
+    #[must_use]
+    pub fn idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Sets the maximum number of unused connections kept open for
+    /// reuse. Zero disables connection reuse. By default there is no
+    /// limit.
+    #[must_use]
+    pub fn max_idle_connections(mut self, max_idle_connections: usize) -> Self {
+        self.max_idle_connections = max_idle_connections;
+        self
+    }
+
+    /// Sets the interval of TCP keep-alive probes, or `None` to not
+    /// send them. This is off by default.
+    #[must_use]
+    pub fn tcp_keep_alive(mut self, tcp_keep_alive: Option<Duration>) -> Self {
+        self.tcp_keep_alive = tcp_keep_alive;
+        self
+    }
+
+    /// Sets whether to speak only HTTP/2, which multiplexes requests
+    /// over a single connection. Plain `http` URLs then use HTTP/2
+    /// with prior knowledge, so the server must support it. For
+    /// `https` URLs, HTTP/2 is negotiated regardless of this setting.
+    /// This is off by default.
+    #[must_use]
+    pub fn http2_only(mut self, http2_only: bool) -> Self {
+        self.http2_only = http2_only;
+        self
+    }
+
+    /// Sets the maximum size of a response body, in bytes, or `None`
+    /// for no limit, which is the default. The limit applies to the
+    /// body after decompression. A larger response fails with
+    /// `Error::ResponseTooLarge` as soon as the limit is exceeded,
+    /// without reading the rest of it.
+    #[must_use]
+    pub fn max_response_size(mut self, max_response_size: Option<usize>) -> Self {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    /// Sets whether to interrupt a request that times out. The server
+    /// then answers the request with what it had written by then,
+    /// e.g. the output of Cryptol's `trace`, which is kept in the
+    /// `Error::Timeout`, and stops working on it. Since `interrupt`
+    /// stops every request the server is working on, this should only
+    /// be turned on for a server that is not shared. This is off by
+    /// default.
+    #[must_use]
+    pub fn interrupt_on_timeout(mut self, interrupt_on_timeout: bool) -> Self {
+        self.interrupt_on_timeout = interrupt_on_timeout;
+        self
+    }
+
+    /// Sets the PEM file of the certificate authorities to trust for
+    /// `https` URLs, e.g. a private authority that signed the server's
+    /// certificate. They replace the Mozilla roots trusted by default.
+    #[must_use]
+    pub fn ca_bundle(mut self, ca_bundle: impl Into<PathBuf>) -> Self {
+        self.ca_bundle = Some(ca_bundle.into());
+        self
+    }
+
+    /// Sets the PEM file holding the certificate chain and private key
+    /// the client presents to an `https` server that asks for one. By
+    /// default the client presents none.
+    #[must_use]
+    pub fn client_cert(mut self, client_cert: impl Into<PathBuf>) -> Self {
+        self.client_cert = Some(client_cert.into());
+        self
+    }
+
+    /// This function returns the TLS settings for `https` URLs.
+    fn tls_config(&self) -> Result<ClientConfig, Error> {
+        let builder = ClientConfig::builder().with_safe_defaults();
+        let builder = match &self.ca_bundle {
+            None => builder.with_webpki_roots(),
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                for (label, der) in read_pem(path)? {
+                    if label == "CERTIFICATE" {
+                        roots
+                            .add(&Certificate(der))
+                            .map_err(|e| tls_error(path, e))?;
+                    }
+                }
+                if roots.is_empty() {
+                    return Err(tls_error(path, "no certificate found"));
+                }
+                builder.with_root_certificates(roots)
+            }
+        };
+
+        let Some(path) = &self.client_cert else {
+            return Ok(builder.with_no_client_auth());
+        };
+        let mut chain = Vec::new();
+        let mut key = None;
+        for (label, der) in read_pem(path)? {
+            if label == "CERTIFICATE" {
+                chain.push(Certificate(der));
+            } else if label.ends_with("PRIVATE KEY") {
+                key.get_or_insert(PrivateKey(der));
+            }
+        }
+        if chain.is_empty() {
+            return Err(tls_error(path, "no certificate found"));
+        }
+        let key = key.ok_or_else(|| tls_error(path, "no private key found"))?;
+        builder
+            .with_client_auth_cert(chain, key)
+            .map_err(|e| tls_error(path, e))
+    }
+
+    /// This function creates a transport for the server at `url`,
+    /// e.g. `http://0.0.0.0:49352`. No request is made until the
+    /// transport is used; see [`Transport::warm_up`].
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Transport` if `url` is not a valid
+    /// `http` or `https` URL, or if the CA bundle or client
+    /// certificate cannot be read or holds no usable certificate.
+    pub fn build(self, url: &str) -> Result<HttpTransport, Error> {
+        let uri: Uri = url.parse().map_err(|e| Error::Transport(Box::new(e)))?;
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            return Err(Error::Transport(
+                format!("{url} is not an http or https URL").into(),
+            ));
+        }
+
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_nodelay(true);
+        http.set_keepalive(self.tcp_keep_alive);
+        let connector = HttpsConnectorBuilder::new()
+            .with_tls_config(self.tls_config()?)
+            .https_or_http()
+            .enable_http1()
+            .enable_http2()
+            .wrap_connector(http);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Transport(e.into()))?;
+
+        let client = Client::builder()
+            .pool_idle_timeout(self.idle_timeout)
+            .pool_max_idle_per_host(self.max_idle_connections)
+            .http2_only(self.http2_only)
+            .build(connector);
+
+        Ok(HttpTransport {
+            client,
+            uri,
+            runtime: Arc::new(runtime),
+            timeout: self.timeout,
+            accept_compressed: self.accept_compressed,
+            compress_requests: self.compress_requests,
+            max_response_size: self.max_response_size,
+            interrupt_on_timeout: self.interrupt_on_timeout,
+        })
+    }
+}
+
+/// This function reads the PEM file at `path`, returning the label and
+/// the decoded contents of each block, e.g. `CERTIFICATE` or
+/// `PRIVATE KEY`.
+fn read_pem(path: &Path) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let pem = fs::read_to_string(path).map_err(|e| tls_error(path, e))?;
+    let mut blocks = Vec::new();
+    let mut lines = pem.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some(label) = line
+            .strip_prefix("-----BEGIN ")
+            .and_then(|rest| rest.strip_suffix("-----"))
+        else {
+            continue;
+        };
+        let end = format!("-----END {label}-----");
+        let mut base64 = String::new();
+        loop {
+            match lines.next() {
+                Some(line) if line == end => break,
+                Some(line) => base64.push_str(line),
+                None => return Err(tls_error(path, format!("missing {end}"))),
+            }
+        }
+        let der = decode_base64(&base64).map_err(|e| tls_error(path, e))?;
+        blocks.push((label.to_string(), der));
+    }
+    Ok(blocks)
+}
+
+/// This function returns the error for a TLS file that cannot be used.
+fn tls_error(path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::Transport(format!("{}: {e}", path.display()).into())
+}
+
+impl HttpTransport {
+    /// This function creates a transport for the server at `url` with
+    /// default settings. See [`HttpTransportBuilder::build`].
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Transport` if `url` is not a valid
+    /// `http` or `https` URL.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        Self::builder().build(url)
+    }
+
+    /// This function returns a builder for configuring a transport.
+    #[must_use]
+    pub fn builder() -> HttpTransportBuilder {
+        HttpTransportBuilder::default()
+    }
+
+    /// This function POSTs `body` and waits for the response text.
+    fn post(&self, body: &Value) -> Result<String, Error> {
+        let exchange = self.exchange(self.build_request(body)?);
+        let (status, encoding, bytes) = self.runtime.block_on(async {
+            tokio::pin!(exchange);
+            match tokio::time::timeout(self.timeout, &mut exchange).await {
+                Ok(response) => response,
+                Err(_) if self.interrupt_on_timeout => {
+                    Err(Error::Timeout(self.interrupt(exchange).await))
+                }
+                Err(_) => Err(Error::Timeout(None)),
+            }
+        })?;
+
+        let text = decompress(encoding.as_ref(), &bytes, self.max_response_size);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { bytes });
+        let text = text?;
+        if !status.is_success() {
+            // The server may still explain the failure with a JSON-RPC
+            // error.
+            return Err(match parse::rpc_response(&text) {
+                Err(e @ Error::Server(_)) => e,
+                _ => Error::Transport(format!("server responded with HTTP status {status}").into()),
+            });
+        }
+        Ok(text)
+    }
+
+    /// This function creates the POST request of `body`.
+    fn build_request(&self, body: &Value) -> Result<Request<Body>, Error> {
+        let mut body = serde_json::to_vec(body).map_err(|e| Error::Transport(e.into()))?;
+
+        let mut request = Request::post(&self.uri)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if self.accept_compressed {
+            request = request.header(ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate"));
+        }
+        if self.compress_requests {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder
+                .write_all(&body)
+                .map_err(|e| Error::Transport(e.into()))?;
+            let compressed = encoder.finish().map_err(|e| Error::Transport(e.into()))?;
+            #[cfg(feature = "zeroize")]
+            zeroize::Zeroize::zeroize(&mut body);
+            body = compressed;
+            request = request.header(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+        // The body is wiped once `hyper` is done sending it.
+        #[cfg(feature = "zeroize")]
+        let body = bytes::Bytes::from_owner(zeroize::Zeroizing::new(body));
+        request
+            .body(Body::from(body))
+            .map_err(|e| Error::Transport(e.into()))
+    }
+
+    /// This function sends `request` and reads the status, the
+    /// `Content-Encoding` and the body of its response.
+    async fn exchange(&self, request: Request<Body>) -> Result<Exchange, Error> {
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| Error::Transport(e.into()))?;
+        let status = response.status();
+        let encoding = response.headers().get(CONTENT_ENCODING).cloned();
+        let bytes = self.read_body(response).await?;
+        Ok((status, encoding, bytes))
+    }
+
+    /// This function interrupts the server after the request whose
+    /// response is `exchange` timed out, and returns what the server
+    /// had written while handling it, if it answers in time.
+    async fn interrupt(
+        &self,
+        exchange: impl Future<Output = Result<Exchange, Error>>,
+    ) -> Option<PartialOutput> {
+        tracing::info!("interrupting request that timed out");
+        let interrupt = self
+            .build_request(&transport::envelope(0, "interrupt", &Map::new()))
+            .ok()?;
+        match tokio::time::timeout(INTERRUPT_GRACE, self.exchange(interrupt)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::warn!(error = %e, "interrupt failed"),
+            Err(_) => tracing::warn!("interrupt timed out"),
+        }
+
+        let (_, encoding, bytes) = tokio::time::timeout(INTERRUPT_GRACE, exchange)
+            .await
+            .ok()?
+            .ok()?;
+        let text = decompress(encoding.as_ref(), &bytes, self.max_response_size);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { bytes });
+        let text = text.ok()?;
+        let response = serde_json::from_str(&text);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { text });
+        let response: Value = response.ok()?;
+        let output = PartialOutput::from_response(&response);
+        #[cfg(feature = "zeroize")]
+        crate::wipe::json(&mut { response });
+        output
+    }
+
+    /// This function reads the body of `response`, failing as soon as
+    /// it exceeds the maximum response size.
+    async fn read_body(&self, response: Response<Body>) -> Result<Vec<u8>, Error> {
+        let too_large = |size: usize| {
+            self.max_response_size
+                .filter(|limit| size > *limit)
+                .map(|limit| Error::ResponseTooLarge { limit })
+        };
+        let length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+        if let Some(e) = length.and_then(too_large) {
+            return Err(e);
+        }
+
+        let mut body = response.into_body();
+        let mut bytes = Vec::with_capacity(length.unwrap_or_default().min(1 << 20));
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|e| Error::Transport(e.into()))?;
+            if let Some(e) = too_large(bytes.len() + chunk.len()) {
+                return Err(e);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+}
+
+/// This function decodes a response body according to its
+/// `Content-Encoding`, failing if it decompresses to more than `limit`
+/// bytes.
+fn decompress(
+    encoding: Option<&HeaderValue>,
+    bytes: &[u8],
+    limit: Option<usize>,
+) -> Result<String, Error> {
+    // Reading one byte past the limit tells whether it is exceeded.
+    let cap = limit.map_or(u64::MAX, |limit| limit as u64 + 1);
+    let mut text = String::new();
+    let result = match encoding.map(HeaderValue::to_str) {
+        None | Some(Ok("identity")) => {
+            return String::from_utf8(bytes.to_vec()).map_err(|e| Error::Decode(e.to_string()))
+        }
+        Some(Ok("gzip")) => GzDecoder::new(bytes).take(cap).read_to_string(&mut text),
+        Some(Ok("deflate")) => ZlibDecoder::new(bytes).take(cap).read_to_string(&mut text),
+        Some(encoding) => {
+            return Err(Error::Decode(format!(
+                "unsupported content encoding {encoding:?}"
+            )))
+        }
+    };
+    if let Some(limit) = limit.filter(|limit| text.len() > *limit) {
+        return Err(Error::ResponseTooLarge { limit });
+    }
+    result.map_err(|e| Error::Decode(format!("could not decompress response: {e}")))?;
+    Ok(text)
+}
+
+impl Transport for HttpTransport {
+    fn request(&self, method: &str, params: &Map<String, Value>) -> Result<Box<RawValue>, Error> {
+        let envelope = transport::envelope(0, method, params);
+        let text = self.post(&envelope);
+        #[cfg(feature = "zeroize")]
+        crate::wipe::json(&mut { envelope });
+        let text = text?;
+        let result = parse::rpc_response(&text);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { text });
+        result
+    }
+
+    /// This function opens a connection by sending a `GET` request.
+    /// The response is discarded, whatever its status.
+    fn warm_up(&self) -> Result<(), Error> {
+        let request = Request::get(&self.uri)
+            .body(Body::empty())
+            .map_err(|e| Error::Transport(e.into()))?;
+        let exchange = async {
+            let response = self
+                .client
+                .request(request)
+                .await
+                .map_err(|e| Error::Transport(e.into()))?;
+            hyper::body::to_bytes(response.into_body())
+                .await
+                .map_err(|e| Error::Transport(e.into()))?;
+            Ok(())
+        };
+        self.runtime
+            .block_on(async { tokio::time::timeout(self.timeout, exchange).await })
+            .map_err(|_| Error::Timeout(None))?
+    }
+
+    /// This function submits `requests` as a single JSON-RPC batch.
+    fn batch(
+        &self,
+        requests: &[(&str, Map<String, Value>)],
+    ) -> Result<Vec<Result<Box<RawValue>, Error>>, Error> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let body = requests
+            .iter()
+            .enumerate()
+            .map(|(id, (method, params))| transport::envelope(id, method, params))
+            .collect();
+        let body = Value::Array(body);
+        let text = self.post(&body);
+        #[cfg(feature = "zeroize")]
+        crate::wipe::json(&mut { body });
+        let text = text?;
+        let results = parse::rpc_batch_response(&text, requests.len());
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { text });
+        results
+    }
+}
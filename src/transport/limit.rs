@@ -0,0 +1,298 @@
+//! A transport that limits the load a client puts on the server and
+//! schedules requests by priority.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::transport::Transport;
+
+/// A transport that passes requests on to another transport, but holds
+/// them back so that at most a given number are in flight at a time
+/// and they start at no more than a given rate. Requests over the limit
+/// wait their turn rather than fail, so a client that outpaces the
+/// server is slowed down to its pace.
+///
+/// The limits are set with a [`RateLimitedTransportBuilder`] and apply
+/// to all clients sharing the transport, including the forks of a
+/// client, and to its clones and its views of other
+/// [priorities](Self::with_priority).
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use cryptol_client::transport::{HttpTransport, RateLimitedTransport};
+/// use cryptol_client::CryptolClient;
+///
+/// let http = HttpTransport::new("http://localhost:49352").unwrap();
+/// let transport = RateLimitedTransport::builder()
+///     .max_in_flight(4)
+///     .requests_per_second(50.0)
+///     .build(Arc::new(http));
+/// let cryptol_client = CryptolClient::builder()
+///     .transport(Arc::new(transport))
+///     .connect();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimitedTransport {
+    inner: Arc<dyn Transport>,
+    limiter: Arc<Limiter>,
+    priority: Priority,
+}
+
+/// The priority of the requests sent through a
+/// [`RateLimitedTransport`]. While an interactive request waits for a
+/// request slot or for its start time, no batch request starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Requests a user is waiting on, e.g. evaluations in a REPL. This
+    /// is the default.
+    #[default]
+    Interactive,
+    /// Background requests, e.g. long-running proof or test jobs.
+    Batch,
+}
+
+/// A builder for the limits of a [`RateLimitedTransport`]. Both limits
+/// are off by default.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitedTransportBuilder {
+    max_in_flight: Option<usize>,
+    interval: Duration,
+}
+
+impl RateLimitedTransportBuilder {
+    /// Sets the maximum number of requests in flight at a time. A
+    /// batch counts as one request. Zero means no limit, which is the
+    /// default.
+    #[must_use]
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = (max_in_flight > 0).then_some(max_in_flight);
+        self
+    }
+
+    /// Sets the maximum number of requests started per second, each
+    /// request of a batch counting separately. Requests are spaced
+    /// evenly rather than let through in bursts, so a batch of `n`
+    /// requests waits as long as `n` single requests would, letting
+    /// interactive requests go first meanwhile. A rate that is not
+    /// positive means no limit, which is the default.
+    #[must_use]
+    pub fn requests_per_second(mut self, requests_per_second: f64) -> Self {
+        self.interval = if requests_per_second > 0.0 {
+            Duration::try_from_secs_f64(requests_per_second.recip()).unwrap_or(Duration::MAX)
+        } else {
+            Duration::ZERO
+        };
+        self
+    }
+
+    /// This function creates a transport that passes requests on to
+    /// `inner` within the limits. Requests through it are interactive.
+    #[must_use]
+    pub fn build(self, inner: Arc<dyn Transport>) -> RateLimitedTransport {
+        RateLimitedTransport {
+            inner,
+            limiter: Arc::new(Limiter {
+                max_in_flight: self.max_in_flight.unwrap_or(usize::MAX),
+                interval: self.interval,
+                queue: Mutex::new(Queue::default()),
+                changed: Condvar::new(),
+            }),
+            priority: Priority::Interactive,
+        }
+    }
+}
+
+/// The limits and bookkeeping shared by the clones and views of a
+/// `RateLimitedTransport`.
+#[derive(Debug)]
+struct Limiter {
+    max_in_flight: usize,
+    interval: Duration,
+    queue: Mutex<Queue>,
+    /// Notified when a slot is freed or a request starts.
+    changed: Condvar,
+}
+
+/// The requests in flight and those waiting to start.
+#[derive(Debug, Default)]
+struct Queue {
+    in_flight: usize,
+    waiting_interactive: usize,
+    /// The time before which no further request may start.
+    next_start: Option<Instant>,
+}
+
+impl RateLimitedTransport {
+    /// This function wraps `inner` without limits. See
+    /// [`RateLimitedTransportBuilder::build`].
+    #[must_use]
+    pub fn new(inner: Arc<dyn Transport>) -> Self {
+        Self::builder().build(inner)
+    }
+
+    /// This function returns a builder for setting the limits of a
+    /// transport.
+    #[must_use]
+    pub fn builder() -> RateLimitedTransportBuilder {
+        RateLimitedTransportBuilder::default()
+    }
+
+    /// This function returns a view of the transport that shares its
+    /// limits, but sends requests with the given priority. Give the
+    /// view to the clients running background jobs, and the
+    /// transport itself to interactive ones:
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// use cryptol_client::transport::{HttpTransport, Priority, RateLimitedTransport};
+    /// use cryptol_client::CryptolClient;
+    ///
+    /// let http = HttpTransport::new("http://localhost:49352").unwrap();
+    /// let interactive = RateLimitedTransport::builder()
+    ///     .max_in_flight(4)
+    ///     .build(Arc::new(http));
+    /// let batch = interactive.with_priority(Priority::Batch);
+    /// let prover = CryptolClient::builder().transport(Arc::new(batch)).connect();
+    /// let repl = CryptolClient::builder().transport(Arc::new(interactive)).connect();
+    /// ```
+    #[must_use]
+    pub fn with_priority(&self, priority: Priority) -> Self {
+        RateLimitedTransport {
+            priority,
+            ..self.clone()
+        }
+    }
+
+    /// This function returns the priority of requests sent through the
+    /// transport.
+    #[must_use]
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// This function returns the transport the requests are passed on
+    /// to.
+    #[must_use]
+    pub fn inner(&self) -> &Arc<dyn Transport> {
+        &self.inner
+    }
+
+    /// This function returns the number of requests currently in
+    /// flight, through this transport and its views.
+    ///
+    /// # Panics
+    ///
+    /// The function panics if the transport was poisoned by a panic in
+    /// another thread.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.limiter.queue.lock().unwrap().in_flight
+    }
+
+    /// This function waits until `requests` requests may start, and
+    /// returns a guard that holds their slot until it is dropped.
+    ///
+    /// A request waits for its start time before it takes a slot, so a
+    /// request held back by the rate does not keep another from
+    /// running. A batch of requests starts once the time for all of
+    /// them has passed, and claims nothing while it waits.
+    fn admit(&self, requests: usize) -> Slot<'_> {
+        let limiter = &*self.limiter;
+        let interactive = self.priority == Priority::Interactive;
+        let mut queue = limiter.queue.lock().unwrap();
+        if interactive {
+            queue.waiting_interactive += 1;
+        }
+        // A batch is spaced as if its requests had come one at a time,
+        // the first when it arrived.
+        let arrival = Instant::now();
+        let mut logged = false;
+        loop {
+            let now = Instant::now();
+            let first = queue.next_start.map_or(arrival, |next| next.max(arrival));
+            let start = later(first, limiter.interval, requests.saturating_sub(1));
+            let yields = !interactive && queue.waiting_interactive > 0;
+            let full = queue.in_flight >= limiter.max_in_flight;
+            if yields || full {
+                if !logged {
+                    tracing::debug!(
+                        in_flight = queue.in_flight,
+                        priority = ?self.priority,
+                        "waiting for a request slot"
+                    );
+                    logged = true;
+                }
+                queue = limiter.changed.wait(queue).unwrap();
+            } else if start > now {
+                queue = limiter.changed.wait_timeout(queue, start - now).unwrap().0;
+            } else {
+                break;
+            }
+        }
+        if interactive {
+            queue.waiting_interactive -= 1;
+        }
+        queue.in_flight += 1;
+        if !limiter.interval.is_zero() {
+            queue.next_start = Some(later(Instant::now(), limiter.interval, 1));
+        }
+        drop(queue);
+        // Batch requests may have been waiting for this one to start.
+        limiter.changed.notify_all();
+        Slot(limiter)
+    }
+}
+
+/// This function returns `count` intervals after `instant`. A time too
+/// far off to represent, at a rate close to zero, is moved to about the
+/// furthest one that is.
+fn later(instant: Instant, interval: Duration, count: usize) -> Instant {
+    let count = u32::try_from(count).unwrap_or(u32::MAX);
+    let mut spacing = interval.checked_mul(count).unwrap_or(Duration::MAX);
+    loop {
+        if let Some(later) = instant.checked_add(spacing) {
+            return later;
+        }
+        spacing /= 2;
+    }
+}
+
+/// A request slot of a `RateLimitedTransport`, freed when dropped.
+struct Slot<'a>(&'a Limiter);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.0.queue.lock().unwrap().in_flight -= 1;
+        // Waiters of either priority may be next.
+        self.0.changed.notify_all();
+    }
+}
+
+impl Transport for RateLimitedTransport {
+    fn request(&self, method: &str, params: &Map<String, Value>) -> Result<Box<RawValue>, Error> {
+        let _slot = self.admit(1);
+        self.inner.request(method, params)
+    }
+
+    fn batch(
+        &self,
+        requests: &[(&str, Map<String, Value>)],
+    ) -> Result<Vec<Result<Box<RawValue>, Error>>, Error> {
+        let _slot = self.admit(requests.len());
+        self.inner.batch(requests)
+    }
+
+    fn warm_up(&self) -> Result<(), Error> {
+        self.inner.warm_up()
+    }
+
+    fn fixed_latency(&self) -> Option<Duration> {
+        self.inner.fixed_latency()
+    }
+}
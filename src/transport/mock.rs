@@ -0,0 +1,265 @@
+//! A transport that answers requests itself, for tests that should not
+//! depend on a running server, and can inject faults into them, for
+//! tests of how an application recovers from a misbehaving one.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
+
+use crate::error::{CryptolError, Error};
+use crate::transport::Transport;
+
+/// The JSON-RPC error code for a method the server does not provide.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The JSON-RPC error code for invalid parameters, e.g. the token of a
+/// state the server does not know.
+const INVALID_PARAMS: i64 = -32602;
+
+type Handler = Box<dyn Fn(&Map<String, Value>) -> Result<Value, Error> + Send + Sync>;
+
+type Schedule = Box<dyn Fn(u64, &str) -> Option<Fault> + Send + Sync>;
+
+/// A failure a [`MockTransport`] injects into a request, in place of
+/// or on top of its response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Answer the request after the given delay, as a loaded server
+    /// would. The delay is real, but the round-trip time reported for
+    /// the request is still the fixed latency of the transport. On
+    /// `wasm32`, where a thread cannot sleep, the request is answered
+    /// at once.
+    Latency(Duration),
+    /// Fail the request with `Error::Transport`, as if the connection
+    /// dropped before the response arrived.
+    Disconnect,
+    /// Answer the request with a body that is not a valid response, so
+    /// that decoding it fails with `Error::Decode`.
+    Malformed,
+    /// Fail the request with the `Error::Server` of a server that no
+    /// longer knows the state the request was made from, e.g. because
+    /// it restarted or evicted the state.
+    StaleState,
+}
+
+/// A transport that answers requests from canned responses instead of
+/// sending them to `cryptol-remote-api`.
+///
+/// Everything a `MockTransport` reports is deterministic, so that
+/// fixtures recorded through it, and snapshot tests of what it
+/// answers, are byte-for-byte the same across runs and machines:
+///
+/// - the state token of the `n`th response is
+///   `00000000-0000-0000-0000-` followed by `n` as 12 digits, shaped
+///   like the UUIDs the server hands out, counting from 1 in the order
+///   the requests arrive;
+/// - the round-trip time of every request is the same fixed
+///   [`latency`](Self::latency), zero by default, in place of the
+///   time measured by the client;
+/// - faults are injected into requests by their number, or by a
+///   schedule computed from it, never at random.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use cryptol_client::transport::MockTransport;
+/// use cryptol_client::CryptolClient;
+/// use serde_json::json;
+///
+/// let transport = MockTransport::new()
+///     .respond("load module", json!([]))
+///     .respond(
+///         "call",
+///         json!({"type": {"type": "bitvector", "width": 8}, "type string": "[8]", "value": {"expression": "bits", "encoding": "hex", "width": 8, "data": "2a"}}),
+///     );
+/// let mut cryptol_client = CryptolClient::builder()
+///     .transport(Arc::new(transport))
+///     .connect()
+///     .unwrap();
+/// cryptol_client.load_module("Cryptol").unwrap();
+/// let answer = cryptol_client.call("id", &["42 : [8]"]).unwrap();
+/// assert_eq!(answer.elapsed().as_secs(), 0);
+/// ```
+pub struct MockTransport {
+    handlers: HashMap<String, Handler>,
+    latency: Duration,
+    requests: AtomicU64,
+    faults: HashMap<u64, Fault>,
+    schedule: Option<Schedule>,
+}
+
+impl MockTransport {
+    /// This function creates a transport with no responses. Requests
+    /// for methods without a response fail with the JSON-RPC error
+    /// "Method not found".
+    #[must_use]
+    pub fn new() -> Self {
+        MockTransport {
+            handlers: HashMap::new(),
+            latency: Duration::ZERO,
+            requests: AtomicU64::new(0),
+            faults: HashMap::new(),
+            schedule: None,
+        }
+    }
+
+    /// Sets the `answer` of every request for `method`, replacing any
+    /// earlier response for it.
+    #[must_use]
+    pub fn respond(self, method: &str, answer: Value) -> Self {
+        self.respond_with(method, move |_| Ok(answer.clone()))
+    }
+
+    /// Sets a function that computes the `answer` of each request for
+    /// `method` from its parameters, or the error to fail it with,
+    /// replacing any earlier response for it.
+    #[must_use]
+    pub fn respond_with(
+        mut self,
+        method: &str,
+        handler: impl Fn(&Map<String, Value>) -> Result<Value, Error> + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.insert(method.to_string(), Box::new(handler));
+        self
+    }
+
+    /// Sets the round-trip time reported for every request. The
+    /// default is zero.
+    #[must_use]
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the fault to inject into the `n`th request, counting from 1
+    /// in the order the requests arrive, as state tokens do. Requests
+    /// that fail count too, so the request made to recover from a
+    /// fault is the next one.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// use cryptol_client::error::Error;
+    /// use cryptol_client::transport::{Fault, MockTransport};
+    /// use cryptol_client::CryptolClient;
+    /// use serde_json::json;
+    ///
+    /// let transport = MockTransport::new()
+    ///     .respond("load module", json!([]))
+    ///     .fault(3, Fault::Disconnect);
+    /// let mut cryptol_client = CryptolClient::builder()
+    ///     .transport(Arc::new(transport))
+    ///     .connect()
+    ///     .unwrap();
+    /// // Requests 1 and 2 asked for the version and loaded `Cryptol`.
+    /// let e = cryptol_client.load_module("SuiteB").unwrap_err();
+    /// assert!(matches!(e.downcast_ref::<Error>(), Some(Error::Transport(_))));
+    /// // Trying again succeeds.
+    /// cryptol_client.load_module("SuiteB").unwrap();
+    /// ```
+    #[must_use]
+    pub fn fault(mut self, n: u64, fault: Fault) -> Self {
+        self.faults.insert(n, fault);
+        self
+    }
+
+    /// Sets a function that chooses the fault, if any, to inject into
+    /// each request from its number and method, e.g. to disconnect
+    /// every third request. Faults set with [`fault`](Self::fault)
+    /// take precedence.
+    #[must_use]
+    pub fn faults(
+        mut self,
+        schedule: impl Fn(u64, &str) -> Option<Fault> + Send + Sync + 'static,
+    ) -> Self {
+        self.schedule = Some(Box::new(schedule));
+        self
+    }
+
+    /// This function returns the number of requests answered so far,
+    /// which is also the number in the state token of the last one.
+    #[must_use]
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// This function returns the state token of the `n`th response.
+    #[must_use]
+    pub fn state_token(n: u64) -> String {
+        format!("00000000-0000-0000-0000-{n:012}")
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        MockTransport::new()
+    }
+}
+
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut methods: Vec<&str> = self.handlers.keys().map(String::as_str).collect();
+        methods.sort_unstable();
+        f.debug_struct("MockTransport")
+            .field("methods", &methods)
+            .field("latency", &self.latency)
+            .field("requests", &self.requests())
+            .field("faults", &self.faults)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transport for MockTransport {
+    fn request(&self, method: &str, params: &Map<String, Value>) -> Result<Box<RawValue>, Error> {
+        let n = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        let fault = self.faults.get(&n).copied().or_else(|| {
+            self.schedule
+                .as_ref()
+                .and_then(|schedule| schedule(n, method))
+        });
+        if let Some(fault) = fault {
+            tracing::debug!(n, method, ?fault, "injecting fault");
+        }
+        match fault {
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(Fault::Latency(delay)) => std::thread::sleep(delay),
+            Some(Fault::Disconnect) => {
+                return Err(Error::Transport("connection closed by the mock".into()));
+            }
+            Some(Fault::Malformed) => {
+                return RawValue::from_string("{\"stdout\": 0}".to_string())
+                    .map_err(|e| Error::Decode(e.to_string()));
+            }
+            Some(Fault::StaleState) => {
+                let state = params.get("state").and_then(Value::as_str).unwrap_or("");
+                return Err(CryptolError::new(
+                    INVALID_PARAMS,
+                    &format!("Unknown state ID {state}"),
+                )
+                .into());
+            }
+            _ => {}
+        }
+        let answer = match self.handlers.get(method) {
+            Some(handler) => handler(params)?,
+            None => {
+                return Err(CryptolError::new(METHOD_NOT_FOUND, "Method not found").into());
+            }
+        };
+        let response = json!({
+            "answer": answer,
+            "state": MockTransport::state_token(n),
+            "stderr": "",
+            "stdout": "",
+        });
+        serde_json::value::to_raw_value(&response).map_err(|e| Error::Decode(e.to_string()))
+    }
+
+    fn fixed_latency(&self) -> Option<Duration> {
+        Some(self.latency)
+    }
+}
@@ -0,0 +1,204 @@
+//! # Transports
+//!
+//! A `Transport` carries JSON-RPC requests to `cryptol-remote-api` and
+//! brings back their results. `CryptolClient` threads the state token
+//! through requests on top of whichever transport it was built with.
+//!
+//! On native targets the default transport is [`HttpTransport`]. When
+//! compiling for `wasm32`, it is [`BrowserTransport`], which issues
+//! requests through the browser. On native targets, any transport can
+//! be wrapped in a [`RateLimitedTransport`] to spare a shared server.
+//! Tests can do without a server through a [`MockTransport`].
+//!
+//! ## Connection strings
+//!
+//! A URL given to [`CryptolClientBuilder::url`](crate::CryptolClientBuilder::url)
+//! may also be a connection string that selects the transport, so that
+//! tools can take the location of the server as a single setting:
+//!
+//! - `cryptol+http://host:port/path`, or `cryptol+https://…`, is the
+//!   same as the plain URL, reached through the default transport;
+//! - `cryptol+socket://host:port` is a server started with
+//!   `cryptol-remote-api socket`, reached through a [`SocketTransport`];
+//! - `cryptol+unix:///path/to/socket` is a server listening on a Unix
+//!   domain socket, also reached through a `SocketTransport`.
+//!
+//! Other URLs are passed to the default transport as they are. The
+//! settings of the HTTP transport only apply to HTTP servers.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
+
+use crate::error::Error;
+
+#[cfg(target_arch = "wasm32")]
+mod browser;
+#[cfg(not(target_arch = "wasm32"))]
+mod http;
+#[cfg(not(target_arch = "wasm32"))]
+mod limit;
+mod mock;
+#[cfg(not(target_arch = "wasm32"))]
+mod socket;
+
+#[cfg(target_arch = "wasm32")]
+pub use browser::BrowserTransport;
+#[cfg(not(target_arch = "wasm32"))]
+pub use http::{HttpTransport, HttpTransportBuilder};
+#[cfg(not(target_arch = "wasm32"))]
+pub use limit::{Priority, RateLimitedTransport, RateLimitedTransportBuilder};
+pub use mock::{Fault, MockTransport};
+#[cfg(not(target_arch = "wasm32"))]
+pub use socket::SocketTransport;
+
+/// A way of sending JSON-RPC requests to `cryptol-remote-api`.
+///
+/// Implementations block until the response to a request has been
+/// received.
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// This function sends the JSON-RPC request `method` with the
+    /// given `params` and returns the `result` member of the
+    /// response.
+    ///
+    /// # Errors
+    ///
+    /// Implementations return `Error::Server` when the server answers
+    /// with a JSON-RPC error, `Error::Timeout` when no answer arrives
+    /// in time, and `Error::Transport` for any other failure.
+    fn request(&self, method: &str, params: &Map<String, Value>) -> Result<Box<RawValue>, Error>;
+
+    /// This function sends several independent JSON-RPC requests and
+    /// returns the outcome of each, in the order of `requests`.
+    ///
+    /// The default implementation sends the requests one at a time.
+    /// Transports that can submit them as a single JSON-RPC batch
+    /// should override it.
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error for the whole batch only if no
+    /// outcomes could be obtained; failures of individual requests are
+    /// reported in the corresponding entry, as for
+    /// [`request`](Transport::request).
+    #[allow(clippy::type_complexity)]
+    fn batch(
+        &self,
+        requests: &[(&str, Map<String, Value>)],
+    ) -> Result<Vec<Result<Box<RawValue>, Error>>, Error> {
+        Ok(requests
+            .iter()
+            .map(|(method, params)| self.request(method, params))
+            .collect())
+    }
+
+    /// This function establishes a connection to the server ahead of
+    /// the first request, so that its cost is not attributed to that
+    /// request. The default implementation does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Implementations return an error if the server cannot be
+    /// reached.
+    fn warm_up(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// This function returns the round-trip time to report for every
+    /// request in place of the measured one, or `None` to report the
+    /// measured time. Transports that replay canned responses fix it so
+    /// that timings recorded through them are reproducible. The
+    /// default implementation returns `None`.
+    fn fixed_latency(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// This function builds the JSON-RPC request object for `method`.
+pub(crate) fn envelope(id: usize, method: &str, params: &Map<String, Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params,
+    })
+}
+
+/// The server a connection string points to. See the
+/// [module documentation](self#connection-strings).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// An HTTP or HTTPS URL.
+    Http(String),
+    /// The host and port of a server speaking netstrings over TCP.
+    Socket(String),
+    /// The path of a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    /// This function parses a connection string or URL.
+    ///
+    /// # Errors
+    ///
+    /// The function returns `Error::Transport` if the string has the
+    /// `cryptol+` prefix, but not one of the schemes above, or if a
+    /// socket address lacks a host or port, or a Unix socket a path.
+    pub fn parse(connection: &str) -> Result<Endpoint, Error> {
+        let invalid = |reason: &str| {
+            Error::Transport(format!("invalid connection string {connection:?}: {reason}").into())
+        };
+        let Some(rest) = connection.strip_prefix("cryptol+") else {
+            return Ok(Endpoint::Http(connection.to_string()));
+        };
+        let (scheme, location) = rest
+            .split_once("://")
+            .ok_or_else(|| invalid("expected \"://\""))?;
+        match scheme {
+            "http" | "https" => Ok(Endpoint::Http(rest.to_string())),
+            "socket" => {
+                let address = location.trim_end_matches('/');
+                match address.rsplit_once(':') {
+                    Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                        Ok(Endpoint::Socket(address.to_string()))
+                    }
+                    _ => Err(invalid("expected host:port")),
+                }
+            }
+            "unix" if !location.is_empty() => Ok(Endpoint::Unix(PathBuf::from(location))),
+            "unix" => Err(invalid("expected a path")),
+            _ => Err(invalid("the scheme must be http, https, socket or unix")),
+        }
+    }
+}
+
+/// This function builds the transport for a connection string, the
+/// default transport of the current target for a URL.
+///
+/// # Errors
+///
+/// The function returns `Error::Transport` if `url` is not usable, or
+/// it selects a transport not available on the current target.
+pub(crate) fn default_transport(url: &str) -> Result<Box<dyn Transport>, Error> {
+    #[cfg(any(target_arch = "wasm32", not(unix)))]
+    let unsupported = |kind: &str| {
+        Error::Transport(format!("{kind} transports are not supported on this target").into())
+    };
+    match Endpoint::parse(url)? {
+        #[cfg(not(target_arch = "wasm32"))]
+        Endpoint::Http(url) => Ok(Box::new(HttpTransport::new(&url)?)),
+        #[cfg(target_arch = "wasm32")]
+        Endpoint::Http(url) => Ok(Box::new(BrowserTransport::new(&url))),
+        #[cfg(not(target_arch = "wasm32"))]
+        Endpoint::Socket(address) => Ok(Box::new(SocketTransport::tcp(address))),
+        #[cfg(target_arch = "wasm32")]
+        Endpoint::Socket(_) => Err(unsupported("socket")),
+        #[cfg(unix)]
+        Endpoint::Unix(path) => Ok(Box::new(SocketTransport::unix(path))),
+        #[cfg(not(unix))]
+        Endpoint::Unix(_) => Err(unsupported("Unix socket")),
+    }
+}
@@ -0,0 +1,233 @@
+//! The socket transport for native targets, speaking the netstring
+//! framing of `cryptol-remote-api socket`.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::parse;
+use crate::transport::{self, Transport};
+
+/// A transport that sends JSON-RPC requests to `cryptol-remote-api`
+/// over a TCP or Unix domain socket, as served by
+/// `cryptol-remote-api socket`. Each message is framed as a netstring,
+/// i.e. its length in decimal, a colon, the message and a comma.
+///
+/// The connection is opened with the first request, and opened again
+/// after a request fails on it. Requests through the same transport are
+/// sent one at a time.
+///
+/// ```no_run
+/// use cryptol_client::transport::SocketTransport;
+///
+/// let transport = SocketTransport::tcp("localhost:49352");
+/// ```
+#[derive(Debug)]
+pub struct SocketTransport {
+    address: Address,
+    timeout: Duration,
+    connection: Mutex<Option<BufReader<Connection>>>,
+    next_id: AtomicUsize,
+}
+
+/// Where a `SocketTransport` connects to.
+#[derive(Debug, Clone)]
+enum Address {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// An open socket.
+#[derive(Debug)]
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+impl SocketTransport {
+    /// This function creates a transport for the server listening on
+    /// the TCP `address`, e.g. `localhost:49352`, without connecting
+    /// to it.
+    #[must_use]
+    pub fn tcp(address: impl Into<String>) -> Self {
+        SocketTransport::new(Address::Tcp(address.into()))
+    }
+
+    /// This function creates a transport for the server listening on
+    /// the Unix domain socket at `path`, without connecting to it.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn unix(path: impl Into<PathBuf>) -> Self {
+        SocketTransport::new(Address::Unix(path.into()))
+    }
+
+    fn new(address: Address) -> Self {
+        SocketTransport {
+            address,
+            timeout: Duration::from_secs(60 * 60),
+            connection: Mutex::new(None),
+            next_id: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets how long to wait for the response to a request. The
+    /// default is one hour.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// This function opens a connection to the server.
+    fn connect(&self) -> io::Result<Connection> {
+        let connection = match &self.address {
+            Address::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                Connection::Tcp(stream)
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                Connection::Unix(stream)
+            }
+        };
+        tracing::debug!(address = ?self.address, "connected to cryptol-remote-api");
+        Ok(connection)
+    }
+
+    /// This function sends `message` with the given `id` and returns
+    /// the text of the response with the same id, connecting first if
+    /// need be. The connection is dropped if the exchange fails.
+    fn exchange(&self, id: usize, message: &Value) -> Result<String, Error> {
+        let body = serde_json::to_vec(message).map_err(|e| Error::Transport(e.into()))?;
+        let mut connection = self.connection.lock().unwrap();
+        let result = (|| -> io::Result<_> {
+            let mut stream = match connection.take() {
+                Some(stream) => stream,
+                None => BufReader::new(self.connect()?),
+            };
+            write_netstring(stream.get_mut(), &body)?;
+            loop {
+                let text = read_netstring(&mut stream)?;
+                let response_id = serde_json::from_str::<Value>(&text)
+                    .ok()
+                    .and_then(|response| response.get("id").and_then(Value::as_u64));
+                // An error about a request that could not be read has
+                // no id.
+                if response_id.is_none() || response_id == u64::try_from(id).ok() {
+                    return Ok((text, stream));
+                }
+                tracing::debug!(id = response_id, "skipping response to another request");
+            }
+        })();
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { body });
+        match result {
+            Ok((text, stream)) => {
+                *connection = Some(stream);
+                Ok(text)
+            }
+            Err(e) => Err(match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Error::Timeout(None),
+                _ => Error::Transport(e.into()),
+            }),
+        }
+    }
+}
+
+/// This function writes `message` as a netstring.
+fn write_netstring(stream: &mut impl Write, message: &[u8]) -> io::Result<()> {
+    write!(stream, "{}:", message.len())?;
+    stream.write_all(message)?;
+    stream.write_all(b",")?;
+    stream.flush()
+}
+
+/// This function reads a netstring, which must hold UTF-8 text.
+fn read_netstring(stream: &mut impl BufRead) -> io::Result<String> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut length = Vec::new();
+    stream.read_until(b':', &mut length)?;
+    if length.pop() != Some(b':') {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let length: usize = std::str::from_utf8(&length)
+        .ok()
+        .and_then(|length| length.trim().parse().ok())
+        .ok_or_else(|| invalid("malformed netstring length"))?;
+    let mut message = vec![0; length + 1];
+    stream.read_exact(&mut message)?;
+    if message.pop() != Some(b',') {
+        return Err(invalid("netstring is not terminated by a comma"));
+    }
+    String::from_utf8(message).map_err(|_| invalid("response is not UTF-8"))
+}
+
+impl Transport for SocketTransport {
+    fn request(&self, method: &str, params: &Map<String, Value>) -> Result<Box<RawValue>, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let envelope = transport::envelope(id, method, params);
+        let text = self.exchange(id, &envelope);
+        #[cfg(feature = "zeroize")]
+        crate::wipe::json(&mut { envelope });
+        let text = text?;
+        let result = parse::rpc_response(&text);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut { text });
+        result
+    }
+
+    /// This function opens the connection ahead of the first request.
+    fn warm_up(&self) -> Result<(), Error> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(BufReader::new(
+                self.connect().map_err(|e| Error::Transport(e.into()))?,
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! # Cryptol types
+//!
+//! `cryptol-remote-api` encodes types as JSON, for example
+//! `{"type":"bitvector","width":{"type":"number","value":16}}` for
+//! `[16]`. This module provides Rust types for those encodings. They
+//! are produced by [`parse::type_schema`]
+//! and returned by [`CryptolClient::type_of`](crate::CryptolClient::type_of).
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::parse;
+use crate::value::Integer;
+
+/// A type parameter of a polymorphic type, e.g. `n` in
+/// `{n} (fin n) => [n] -> [n]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeParameter {
+    /// The name of the parameter.
+    pub name: String,
+    /// The kind of the parameter, e.g. `#` for sizes and `*` for
+    /// types.
+    pub kind: String,
+}
+
+/// The type of a Cryptol expression, with the type parameters it is
+/// polymorphic in and the constraints on them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeSchema {
+    /// The type parameters, in order.
+    pub forall: Vec<TypeParameter>,
+    /// The constraints on the type parameters, e.g. `fin n`, as
+    /// encoded by `cryptol-remote-api`.
+    pub propositions: Vec<Value>,
+    /// The type itself.
+    pub ty: CryptolType,
+}
+
+impl TypeSchema {
+    /// This function returns whether the type has no type parameters.
+    #[must_use]
+    pub fn is_monomorphic(&self) -> bool {
+        self.forall.is_empty()
+    }
+}
+
+/// A Cryptol type.
+///
+/// A type is displayed in Cryptol syntax, e.g. `[n][8] -> [384]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CryptolType {
+    /// A type variable, e.g. `n`.
+    Variable(String),
+    /// A size, e.g. `16` in `[16]`, or a modulus, e.g. `2^^255 - 19`
+    /// in `Z (2^^255 - 19)`.
+    Number(Integer),
+    /// The infinite size `inf`.
+    Infinity,
+    /// `Bit`.
+    Bit,
+    /// `Integer`.
+    Integer,
+    /// `Rational`.
+    Rational,
+    /// `Z n`, with the modulus `n`.
+    Z(Box<CryptolType>),
+    /// `[n]`, a sequence of bits of width `n`.
+    BitVector(Box<CryptolType>),
+    /// `[n]a` where `a` is not `Bit`.
+    Sequence {
+        /// The length `n`.
+        length: Box<CryptolType>,
+        /// The element type `a`.
+        contents: Box<CryptolType>,
+    },
+    /// `a -> b`.
+    Function {
+        /// The parameter type `a`.
+        domain: Box<CryptolType>,
+        /// The result type `b`.
+        range: Box<CryptolType>,
+    },
+    /// `()`.
+    Unit,
+    /// A tuple type.
+    Tuple(Vec<CryptolType>),
+    /// A record type, keyed by field name.
+    Record(BTreeMap<String, CryptolType>),
+    /// Any other type, e.g. type-level arithmetic like `n + 1` or
+    /// `Float 8 24`, as encoded by `cryptol-remote-api`.
+    Other(Value),
+}
+
+impl CryptolType {
+    /// This function returns whether the type is `[n]` for some `n`.
+    #[must_use]
+    pub fn is_bitvector(&self) -> bool {
+        matches!(self, CryptolType::BitVector(_))
+    }
+
+    /// This function returns whether the type is a function type.
+    #[must_use]
+    pub fn is_function(&self) -> bool {
+        matches!(self, CryptolType::Function { .. })
+    }
+
+    /// This function returns the width of a bitvector type of known
+    /// width.
+    #[must_use]
+    pub fn bit_width(&self) -> Option<u64> {
+        match self {
+            CryptolType::BitVector(width) => width.number(),
+            _ => None,
+        }
+    }
+
+    /// This function returns the number of elements of a sequence
+    /// type of known length, counting the bits of a bitvector.
+    #[must_use]
+    pub fn sequence_length(&self) -> Option<u64> {
+        match self {
+            CryptolType::BitVector(length) | CryptolType::Sequence { length, .. } => {
+                length.number()
+            }
+            _ => None,
+        }
+    }
+
+    /// This function returns the fields of a record type.
+    #[must_use]
+    pub fn record_fields(&self) -> Option<&BTreeMap<String, CryptolType>> {
+        match self {
+            CryptolType::Record(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// This function returns the parameter types of a function type,
+    /// in order, and its final result type. A type that is not a
+    /// function has no parameters.
+    #[must_use]
+    pub fn parameters(&self) -> (Vec<&CryptolType>, &CryptolType) {
+        let mut parameters = Vec::new();
+        let mut ty = self;
+        while let CryptolType::Function { domain, range } = ty {
+            parameters.push(&**domain);
+            ty = range;
+        }
+        (parameters, ty)
+    }
+
+    /// This function returns the value of a size that fits in a
+    /// `u64`.
+    fn number(&self) -> Option<u64> {
+        match self {
+            CryptolType::Number(n) => u64::try_from(n).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CryptolType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptolType::Variable(name) => f.write_str(name),
+            CryptolType::Number(n) => write!(f, "{n}"),
+            CryptolType::Infinity => f.write_str("inf"),
+            CryptolType::Bit => f.write_str("Bit"),
+            CryptolType::Integer => f.write_str("Integer"),
+            CryptolType::Rational => f.write_str("Rational"),
+            CryptolType::Z(modulus) => {
+                f.write_str("Z ")?;
+                write_atom(modulus, f)
+            }
+            CryptolType::BitVector(width) => write!(f, "[{width}]"),
+            CryptolType::Sequence { length, contents } => {
+                write!(f, "[{length}]")?;
+                write_atom(contents, f)
+            }
+            CryptolType::Function { domain, range } => {
+                if domain.is_function() {
+                    write!(f, "({domain}) -> {range}")
+                } else {
+                    write!(f, "{domain} -> {range}")
+                }
+            }
+            CryptolType::Unit => f.write_str("()"),
+            CryptolType::Tuple(components) => {
+                f.write_str("(")?;
+                for (i, component) in components.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{component}")?;
+                }
+                f.write_str(")")
+            }
+            CryptolType::Record(fields) => {
+                f.write_str("{")?;
+                for (i, (name, field)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{name} : {field}")?;
+                }
+                f.write_str("}")
+            }
+            CryptolType::Other(ty) => write_other(ty, f),
+        }
+    }
+}
+
+/// This function writes `ty`, parenthesized unless it is a single
+/// token or already bracketed, as the argument of a type constructor.
+fn write_atom(ty: &CryptolType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match ty {
+        CryptolType::Z(_) | CryptolType::Function { .. } => {
+            write!(f, "({ty})")
+        }
+        CryptolType::Other(other) if other["arguments"].is_array() => write!(f, "({ty})"),
+        _ => write!(f, "{ty}"),
+    }
+}
+
+/// This function writes a type the client does not model, e.g.
+/// `{"type": "+", "arguments": [..]}` for `n + 1`, in Cryptol syntax
+/// when it is an application of a named type or operator, and as JSON
+/// otherwise.
+fn write_other(ty: &Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let (Some(name), Some(arguments)) = (ty["type"].as_str(), ty["arguments"].as_array()) else {
+        return write!(f, "{ty}");
+    };
+    let Ok(arguments) = arguments
+        .iter()
+        .map(parse::cryptol_type)
+        .collect::<Result<Vec<_>, _>>()
+    else {
+        return write!(f, "{ty}");
+    };
+
+    match arguments.as_slice() {
+        [left, right] if !name.starts_with(|c: char| c.is_alphanumeric()) => {
+            write_atom(left, f)?;
+            write!(f, " {name} ")?;
+            write_atom(right, f)
+        }
+        _ => {
+            f.write_str(name)?;
+            for argument in &arguments {
+                f.write_str(" ")?;
+                write_atom(argument, f)?;
+            }
+            Ok(())
+        }
+    }
+}
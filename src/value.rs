@@ -0,0 +1,864 @@
+//! # Cryptol values
+//!
+//! `cryptol-remote-api` encodes Cryptol values as JSON, for example
+//! `{"expression":"bits","encoding":"hex","width":16,"data":"0001"}`
+//! for the 16-bit value `0x0001`. This module provides Rust types for
+//! those values. They are produced from answers by
+//! [`parse::value`](crate::parse::value).
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::ops::{Bound, RangeBounds};
+
+use serde::ser::SerializeStruct;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Error;
+
+/// A fixed-width bitvector, i.e. a value of Cryptol type `[n]` for
+/// some finite `n`.
+///
+/// The bits are stored most significant byte first. When `width` is
+/// not a multiple of 8, the unused high bits of the first byte are
+/// zero.
+///
+/// A bitvector is displayed in hexadecimal with all of its digits, as
+/// the Cryptol REPL does, e.g. `0x0001`. Its `Debug` form is its
+/// concrete syntax, see [`CryptolValue::to_expression`].
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BitVector {
+    width: usize,
+    bytes: Vec<u8>,
+}
+
+impl BitVector {
+    /// This function creates a bitvector of the given `width` from
+    /// big-endian `bytes`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `bytes` does not contain
+    /// exactly `width` bits, i.e. if its length is not
+    /// `width.div_ceil(8)` or if any bit above `width` is set.
+    pub fn new(width: usize, bytes: Vec<u8>) -> Result<Self, Error> {
+        if bytes.len() != width.div_ceil(8) {
+            return Err(Error::Decode(format!(
+                "a bitvector of width {width} needs {} bytes, got {}",
+                width.div_ceil(8),
+                bytes.len()
+            )));
+        }
+
+        let excess = bytes.len() * 8 - width;
+        if excess > 0 && bytes[0] >> (8 - excess) != 0 {
+            return Err(Error::Decode(format!(
+                "value does not fit in a bitvector of width {width}"
+            )));
+        }
+
+        Ok(BitVector { width, bytes })
+    }
+
+    /// This function returns the number of bits in the bitvector.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// This function returns the bits of the bitvector as big-endian
+    /// bytes.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// This function overwrites the bytes of the bitvector with zeros
+    /// and leaves it empty.
+    #[cfg(feature = "zeroize")]
+    pub(crate) fn zeroize_bytes(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.bytes);
+        self.width = 0;
+    }
+
+    /// This function creates a bitvector from big-endian `bytes`,
+    /// using all of their bits.
+    #[must_use]
+    pub fn from_be_bytes(bytes: Vec<u8>) -> Self {
+        BitVector {
+            width: bytes.len() * 8,
+            bytes,
+        }
+    }
+
+    /// This function returns bit `i` of the bitvector, counting from
+    /// the most significant bit as Cryptol's `@` does, or `None` if `i`
+    /// is not less than the width.
+    #[must_use]
+    pub fn bit(&self, i: usize) -> Option<bool> {
+        (i < self.width).then(|| self.bit_from_lsb(self.width - 1 - i))
+    }
+
+    /// This function returns the bits in `range` as a bitvector,
+    /// counting from the most significant bit, or `None` if `range`
+    /// extends past the width. For example, `slice(..8)` is the most
+    /// significant byte, like Cryptol's ``take`{8}``.
+    #[must_use]
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Option<BitVector> {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.checked_add(1)?,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.checked_add(1)?,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.width,
+        };
+        if start > end || end > self.width {
+            return None;
+        }
+
+        let width = end - start;
+        let mut bytes = vec![0u8; width.div_ceil(8)];
+        let offset = bytes.len() * 8 - width;
+        for i in (start..end).filter(|&i| self.bit_from_lsb(self.width - 1 - i)) {
+            let j = offset + i - start;
+            bytes[j / 8] |= 0x80 >> (j % 8);
+        }
+        Some(BitVector { width, bytes })
+    }
+
+    /// This function returns the hex digits of the bitvector, one for
+    /// every 4 bits of width or part thereof, without a `0x` prefix.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        let mut hex = String::with_capacity(self.width.div_ceil(4));
+        // Writing to a `String` cannot fail.
+        let _ = write_hex(self, &mut hex);
+        hex
+    }
+
+    /// This function returns the bits of the bitvector as big-endian
+    /// bytes.
+    #[must_use]
+    pub fn to_bytes_be(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// This function returns the bits of the bitvector as
+    /// little-endian bytes.
+    #[must_use]
+    pub fn to_bytes_le(&self) -> Vec<u8> {
+        self.bytes.iter().rev().copied().collect()
+    }
+
+    /// This function returns a formatter that shows the big-endian
+    /// bytes of the bitvector the way `hexdump -C` does: an offset,
+    /// sixteen bytes in hex and the same bytes as ASCII per line.
+    ///
+    /// ```
+    /// use cryptol_client::value::BitVector;
+    ///
+    /// let bits = BitVector::from_be_bytes(b"Cryptol".to_vec());
+    /// assert_eq!(
+    ///     bits.hexdump().to_string(),
+    ///     "00000000  43 72 79 70 74 6f 6c                              |Cryptol|"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn hexdump(&self) -> Hexdump<'_> {
+        Hexdump { bits: self }
+    }
+
+    /// This function returns whether bit `i` is set, counting from the
+    /// least significant bit.
+    fn bit_from_lsb(&self, i: usize) -> bool {
+        self.bytes[self.bytes.len() - 1 - i / 8] >> (i % 8) & 1 == 1
+    }
+
+    /// This function decodes a bitvector from the hex digits used by
+    /// the `"hex"` encoding of `cryptol-remote-api`. The digits may
+    /// omit leading zeros.
+    pub(crate) fn from_hex(width: usize, digits: &str) -> Result<Self, Error> {
+        if digits.len() > width.div_ceil(4).max(1) {
+            return Err(Error::Decode(format!(
+                "too many hex digits for a bitvector of width {width}"
+            )));
+        }
+
+        let mut bytes = vec![0u8; width.div_ceil(8)];
+        for (i, c) in digits.bytes().rev().enumerate() {
+            let nibble = match c {
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'f' => c - b'a' + 10,
+                b'A'..=b'F' => c - b'A' + 10,
+                _ => return Err(Error::Decode(format!("invalid hex digit {:?}", c as char))),
+            };
+            if nibble == 0 {
+                continue;
+            }
+            let Some(byte) = bytes.len().checked_sub(i / 2 + 1) else {
+                return Err(Error::Decode(format!(
+                    "value does not fit in a bitvector of width {width}"
+                )));
+            };
+            bytes[byte] |= nibble << (4 * (i % 2));
+        }
+
+        BitVector::new(width, bytes)
+    }
+
+    /// This function decodes a bitvector from the `"base64"` encoding
+    /// of `cryptol-remote-api`, which holds the big-endian bytes of the
+    /// value.
+    pub(crate) fn from_base64(width: usize, data: &str) -> Result<Self, Error> {
+        BitVector::from_unpadded_be_bytes(width, &decode_base64(data)?)
+    }
+
+    /// This function creates a bitvector of the given `width` from
+    /// big-endian bytes that may have more or fewer leading zero
+    /// bytes than the width needs.
+    pub(crate) fn from_unpadded_be_bytes(width: usize, bytes: &[u8]) -> Result<Self, Error> {
+        // Strip or add leading zero bytes to match the width.
+        let len = width.div_ceil(8);
+        let leading = bytes.iter().take_while(|&&b| b == 0).count();
+        let significant = &bytes[leading..];
+        if significant.len() > len {
+            return Err(Error::Decode(format!(
+                "value does not fit in a bitvector of width {width}"
+            )));
+        }
+        let mut padded = vec![0u8; len - significant.len()];
+        padded.extend_from_slice(significant);
+
+        BitVector::new(width, padded)
+    }
+}
+
+/// With the `num-bigint` feature, bitvectors convert to and from
+/// unsigned big integers, e.g. for the moduli and points of RSA and
+/// elliptic curve specifications. See [`bignum`](crate::bignum) for
+/// other backends.
+#[cfg(feature = "num-bigint")]
+impl BitVector {
+    /// This function returns the value of the bitvector as an
+    /// unsigned integer.
+    #[must_use]
+    pub fn to_biguint(&self) -> num_bigint::BigUint {
+        self.to_big()
+    }
+
+    /// This function creates a bitvector of the given `width` holding
+    /// `n`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `n` does not fit in `width`
+    /// bits.
+    pub fn from_biguint(width: usize, n: &num_bigint::BigUint) -> Result<Self, Error> {
+        BitVector::from_big(width, n)
+    }
+}
+
+impl fmt::Display for BitVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.width == 0 {
+            return f.write_str("0x0");
+        }
+        f.write_str("0x")?;
+        write_hex(self, f)
+    }
+}
+
+impl fmt::Debug for BitVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_bits_expression(self, f)
+    }
+}
+
+/// A bitvector is serialized as its width and its bytes, most
+/// significant first, e.g. `{"width": 12, "bytes": [15, 255]}` in JSON.
+/// The bytes are serialized as a byte string, so that compact formats
+/// such as CBOR and bincode store them without per-byte overhead.
+impl Serialize for BitVector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bits = serializer.serialize_struct("BitVector", 2)?;
+        bits.serialize_field("width", &self.width)?;
+        bits.serialize_field("bytes", &ByteStr(&self.bytes))?;
+        bits.end()
+    }
+}
+
+/// Deserializing a bitvector checks its bytes like [`BitVector::new`].
+impl<'de> Deserialize<'de> for BitVector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "BitVector")]
+        struct Fields {
+            width: usize,
+            bytes: ByteBuf,
+        }
+
+        let Fields { width, bytes } = Fields::deserialize(deserializer)?;
+        BitVector::new(width, bytes.0).map_err(de::Error::custom)
+    }
+}
+
+/// Bytes that serialize as a byte string rather than a sequence.
+struct ByteStr<'a>(&'a [u8]);
+
+impl Serialize for ByteStr<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Bytes that deserialize from a byte string or a sequence.
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a byte string")
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(bytes.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(bytes))
+            }
+
+            // Formats without byte strings, like JSON, use a sequence.
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<ByteBuf, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(ByteBuf(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(Visitor)
+    }
+}
+
+/// A `hexdump -C` style formatter for the bytes of a bitvector,
+/// created by [`BitVector::hexdump`].
+#[derive(Debug, Clone, Copy)]
+pub struct Hexdump<'a> {
+    bits: &'a BitVector,
+}
+
+impl fmt::Display for Hexdump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (line, chunk) in self.bits.bytes.chunks(16).enumerate() {
+            if line > 0 {
+                f.write_char('\n')?;
+            }
+            write!(f, "{:08x} ", line * 16)?;
+            for i in 0..16 {
+                if i == 8 {
+                    f.write_char(' ')?;
+                }
+                match chunk.get(i) {
+                    Some(byte) => write!(f, " {byte:02x}")?,
+                    None => f.write_str("   ")?,
+                }
+            }
+            f.write_str("  |")?;
+            for &byte in chunk {
+                let c = if byte.is_ascii_graphic() || byte == b' ' {
+                    char::from(byte)
+                } else {
+                    '.'
+                };
+                f.write_char(c)?;
+            }
+            f.write_char('|')?;
+        }
+        Ok(())
+    }
+}
+
+/// With the `bitvec` feature, a bit slice converts to a bitvector of
+/// its length whose most significant bit is the first bit of the
+/// slice, matching Cryptol's indexing of `[n]` with `@`.
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> From<&bitvec::slice::BitSlice<T, O>>
+    for BitVector
+{
+    fn from(bits: &bitvec::slice::BitSlice<T, O>) -> Self {
+        let width = bits.len();
+        let mut bytes = vec![0u8; width.div_ceil(8)];
+        let offset = bytes.len() * 8 - width;
+        for i in bits.iter_ones() {
+            let j = offset + i;
+            bytes[j / 8] |= 0x80 >> (j % 8);
+        }
+        BitVector { width, bytes }
+    }
+}
+
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> From<bitvec::vec::BitVec<T, O>>
+    for BitVector
+{
+    fn from(bits: bitvec::vec::BitVec<T, O>) -> Self {
+        BitVector::from(bits.as_bitslice())
+    }
+}
+
+/// With the `bitvec` feature, a bitvector converts to a bit vector
+/// whose first bit is its most significant bit.
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> From<&BitVector>
+    for bitvec::vec::BitVec<T, O>
+{
+    fn from(bits: &BitVector) -> Self {
+        (0..bits.width)
+            .rev()
+            .map(|i| bits.bit_from_lsb(i))
+            .collect()
+    }
+}
+
+#[cfg(feature = "bitvec")]
+impl<T: bitvec::store::BitStore, O: bitvec::order::BitOrder> From<BitVector>
+    for bitvec::vec::BitVec<T, O>
+{
+    fn from(bits: BitVector) -> Self {
+        Self::from(&bits)
+    }
+}
+
+/// An arbitrary-precision integer, i.e. a value of Cryptol type
+/// `Integer`, such as an RSA modulus.
+///
+/// The integer is stored as its sign and the big-endian bytes of its
+/// magnitude, which [`bignum`](crate::bignum) backends convert from
+/// directly. It converts from and to Rust's integer types, and is
+/// displayed and parsed in decimal.
+///
+/// ```
+/// use cryptol_client::value::Integer;
+///
+/// let n: Integer = "-340282366920938463463374607431768211456".parse().unwrap();
+/// assert!(n.is_negative());
+/// assert_eq!(n.magnitude(), [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+/// assert!(i128::try_from(&n).is_err());
+/// assert_eq!(i8::try_from(&Integer::from(-5)).unwrap(), -5);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Integer {
+    negative: bool,
+    magnitude: Vec<u8>,
+}
+
+/// The largest power of ten that fits in a `u32`, used to convert
+/// integers to and from decimal nine digits at a time.
+const DECIMAL_CHUNK: u64 = 1_000_000_000;
+
+impl Integer {
+    /// This function creates the integer with the given sign and the
+    /// big-endian bytes of its magnitude, which may have leading zero
+    /// bytes. Zero is never negative.
+    #[must_use]
+    pub fn from_sign_magnitude(negative: bool, magnitude: &[u8]) -> Self {
+        let leading = magnitude.iter().take_while(|&&b| b == 0).count();
+        let magnitude = magnitude[leading..].to_vec();
+        Integer {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    /// This function returns whether the integer is less than zero.
+    #[must_use]
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// This function returns the big-endian bytes of the absolute value
+    /// of the integer, without leading zero bytes. It is empty for
+    /// zero.
+    #[must_use]
+    pub fn magnitude(&self) -> &[u8] {
+        &self.magnitude
+    }
+
+    /// This function overwrites the bytes of the integer with zeros and
+    /// leaves it zero.
+    #[cfg(feature = "zeroize")]
+    pub(crate) fn zeroize_magnitude(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.magnitude);
+        self.negative = false;
+    }
+}
+
+impl From<i128> for Integer {
+    fn from(n: i128) -> Self {
+        Integer::from_sign_magnitude(n < 0, &n.unsigned_abs().to_be_bytes())
+    }
+}
+
+impl From<u128> for Integer {
+    fn from(n: u128) -> Self {
+        Integer::from_sign_magnitude(false, &n.to_be_bytes())
+    }
+}
+
+/// Narrower integers convert through `i128` and `u128`.
+macro_rules! integer_from {
+    ($($t:ty => $wide:ty),*) => {$(
+        impl From<$t> for Integer {
+            fn from(n: $t) -> Self {
+                Integer::from(<$wide>::from(n))
+            }
+        }
+    )*};
+}
+
+integer_from!(
+    i8 => i128, i16 => i128, i32 => i128, i64 => i128,
+    u8 => u128, u16 => u128, u32 => u128, u64 => u128
+);
+
+/// Integers convert to Rust's integer types as long as they fit.
+macro_rules! integer_try_into {
+    ($($t:ty),*) => {$(
+        impl TryFrom<&Integer> for $t {
+            type Error = Error;
+
+            fn try_from(n: &Integer) -> Result<Self, Error> {
+                let out_of_range =
+                    || Error::Decode(format!("integer {n} does not fit in {}", stringify!($t)));
+                if n.magnitude.len() > 16 {
+                    return Err(out_of_range());
+                }
+                let magnitude = n
+                    .magnitude
+                    .iter()
+                    .fold(0u128, |acc, &b| (acc << 8) | u128::from(b));
+                let fits = if n.negative {
+                    0i128
+                        .checked_sub_unsigned(magnitude)
+                        .and_then(|n| <$t>::try_from(n).ok())
+                } else {
+                    <$t>::try_from(magnitude).ok()
+                };
+                fits.ok_or_else(out_of_range)
+            }
+        }
+    )*};
+}
+
+integer_try_into!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl std::str::FromStr for Integer {
+    type Err = Error;
+
+    /// This function parses a decimal integer with an optional leading
+    /// `-`, e.g. `-12`.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(Error::Decode(format!("invalid integer {s:?}")));
+        }
+
+        // Multiply in the digits a chunk at a time, the first chunk
+        // taking what is left over.
+        let mut magnitude: Vec<u8> = Vec::new();
+        let first = match digits.len() % 9 {
+            0 => 9,
+            n => n,
+        };
+        let mut rest = digits;
+        let mut len = first.min(digits.len());
+        while !rest.is_empty() {
+            let (chunk, tail) = rest.split_at(len);
+            rest = tail;
+            len = 9;
+            let scale = 10u64.pow(u32::try_from(chunk.len()).unwrap_or(9));
+            let mut carry: u64 = chunk.parse().unwrap_or_default();
+            for byte in magnitude.iter_mut().rev() {
+                let acc = u64::from(*byte) * scale + carry;
+                let [.., low] = acc.to_be_bytes();
+                *byte = low;
+                carry = acc >> 8;
+            }
+            while carry > 0 {
+                let [.., low] = carry.to_be_bytes();
+                magnitude.insert(0, low);
+                carry >>= 8;
+            }
+        }
+        Ok(Integer::from_sign_magnitude(negative, &magnitude))
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Divide out the digits a chunk at a time, least significant
+        // first.
+        let mut chunks = Vec::new();
+        let mut n = self.magnitude.clone();
+        while !n.is_empty() {
+            let mut remainder = 0u64;
+            for byte in &mut n {
+                let acc = (remainder << 8) | u64::from(*byte);
+                let [.., quotient] = (acc / DECIMAL_CHUNK).to_be_bytes();
+                *byte = quotient;
+                remainder = acc % DECIMAL_CHUNK;
+            }
+            let leading = n.iter().take_while(|&&b| b == 0).count();
+            n.drain(..leading);
+            chunks.push(remainder);
+        }
+
+        let mut digits = String::new();
+        match chunks.split_last() {
+            None => digits.push('0'),
+            Some((first, rest)) => {
+                write!(digits, "{first}")?;
+                for chunk in rest.iter().rev() {
+                    write!(digits, "{chunk:09}")?;
+                }
+            }
+        }
+        f.pad_integral(!self.negative, "", &digits)
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Magnitudes have no leading zero bytes, so the longer one is
+        // the larger.
+        let magnitude = || {
+            self.magnitude
+                .len()
+                .cmp(&other.magnitude.len())
+                .then_with(|| self.magnitude.cmp(&other.magnitude))
+        };
+        match (self.negative, other.negative) {
+            (false, false) => magnitude(),
+            (true, true) => magnitude().reverse(),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Debug for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// An integer is serialized as its decimal digits, e.g. `"-12"`, so
+/// that formats without big numbers store it exactly.
+impl Serialize for Integer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Integer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let digits = String::deserialize(deserializer)?;
+        digits.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A Cryptol value as encoded by `cryptol-remote-api`.
+///
+/// A value is displayed the way the Cryptol REPL prints it, e.g.
+/// `[0x01, 0x02]` or `{x = 1, y = True}`. Its `Debug` form is its
+/// concrete syntax, which also tells apart values the REPL prints
+/// alike, e.g. `3` and `(3 : Z 7)`.
+///
+/// Values implement `Serialize` and `Deserialize` losslessly, so they
+/// can be stored or sent in any `serde` format, e.g. CBOR or bincode.
+/// This encoding is this crate's own, not that of `cryptol-remote-api`.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CryptolValue {
+    /// A value of type `Bit`.
+    Bit(bool),
+    /// A value of type `Integer`.
+    Integer(Integer),
+    /// A value of type `Z n`.
+    IntegerModulo {
+        /// The value, in the range `0 .. modulus`.
+        value: Integer,
+        /// The modulus `n`.
+        modulus: Integer,
+    },
+    /// A value of type `[n]`.
+    BitVector(BitVector),
+    /// A value of type `[n]a` where `a` is not `Bit`.
+    Sequence(Vec<CryptolValue>),
+    /// A value of a tuple type.
+    Tuple(Vec<CryptolValue>),
+    /// A value of a record type, keyed by field name.
+    Record(BTreeMap<String, CryptolValue>),
+    /// The value `()`.
+    Unit,
+    /// A value the server cannot encode as JSON (for example a
+    /// function). The server keeps the value and hands out an
+    /// identifier that can be used to refer to it in later requests.
+    Opaque(String),
+}
+
+impl CryptolValue {
+    /// This function renders the value in Cryptol's concrete syntax,
+    /// e.g. `0x0001` or `[True, False]`, so that it can be spliced
+    /// into an expression. The rendering is always a single atom, so
+    /// it can be used as a function argument without parentheses, and
+    /// carries enough type information to have the value's type:
+    /// bitvectors keep their width, and `Integer` and `Z n` values are
+    /// annotated, e.g. `(5 : Integer)`, since a bare literal would take
+    /// whatever type its use site needs.
+    #[must_use]
+    pub fn to_expression(&self) -> String {
+        let mut out = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = write_value(self, true, &mut out);
+        out
+    }
+}
+
+impl fmt::Display for CryptolValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(self, false, f)
+    }
+}
+
+impl fmt::Debug for CryptolValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(self, true, f)
+    }
+}
+
+/// This function writes `value` to `out`, as concrete syntax if
+/// `expression` is set and the way the Cryptol REPL prints it
+/// otherwise.
+fn write_value<W: Write>(value: &CryptolValue, expression: bool, out: &mut W) -> fmt::Result {
+    let list = |out: &mut W, open, close, elements: &[CryptolValue]| {
+        out.write_char(open)?;
+        for (i, element) in elements.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            write_value(element, expression, out)?;
+        }
+        out.write_char(close)
+    };
+
+    match value {
+        CryptolValue::Bit(true) => out.write_str("True"),
+        CryptolValue::Bit(false) => out.write_str("False"),
+        CryptolValue::Integer(n) if expression => write!(out, "({n} : Integer)"),
+        CryptolValue::Integer(n) => write!(out, "{n}"),
+        CryptolValue::IntegerModulo { value, modulus } if expression => {
+            write!(out, "({value} : Z {modulus})")
+        }
+        CryptolValue::IntegerModulo { value, .. } => write!(out, "{value}"),
+        CryptolValue::BitVector(bits) if expression => write_bits_expression(bits, out),
+        CryptolValue::BitVector(bits) => write!(out, "{bits}"),
+        CryptolValue::Sequence(elements) => list(out, '[', ']', elements),
+        CryptolValue::Tuple(elements) => list(out, '(', ')', elements),
+        CryptolValue::Record(fields) => {
+            out.write_char('{')?;
+            for (i, (name, field)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+                write!(out, "{name} = ")?;
+                write_value(field, expression, out)?;
+            }
+            out.write_char('}')
+        }
+        CryptolValue::Unit => out.write_str("()"),
+        CryptolValue::Opaque(identifier) => out.write_str(identifier),
+    }
+}
+
+/// This function writes the concrete syntax of `bits` to `out`: hex
+/// digits when the width is a multiple of 4, so that the literal has
+/// the right width, and binary digits otherwise.
+fn write_bits_expression<W: Write>(bits: &BitVector, out: &mut W) -> fmt::Result {
+    if bits.width == 0 {
+        out.write_str("(0 : [0])")
+    } else if bits.width.is_multiple_of(4) {
+        out.write_str("0x")?;
+        write_hex(bits, out)
+    } else {
+        out.write_str("0b")?;
+        for i in (0..bits.width).rev() {
+            out.write_char(if bits.bit_from_lsb(i) { '1' } else { '0' })?;
+        }
+        Ok(())
+    }
+}
+
+/// This function writes the hex digits of `bits` to `out`, one for
+/// every 4 bits of width or part thereof.
+fn write_hex<W: Write>(bits: &BitVector, out: &mut W) -> fmt::Result {
+    let digits = bits.width.div_ceil(4);
+    for (i, byte) in bits.bytes.iter().enumerate() {
+        if i == 0 && digits % 2 == 1 {
+            write!(out, "{:x}", byte & 0xf)?;
+        } else {
+            write!(out, "{byte:02x}")?;
+        }
+    }
+    Ok(())
+}
+
+/// This function decodes the standard base64 alphabet, with or without
+/// padding.
+pub(crate) fn decode_base64(data: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(data.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in data.bytes().filter(|&c| c != b'=') {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => {
+                return Err(Error::Decode(format!(
+                    "invalid base64 character {:?}",
+                    c as char
+                )))
+            }
+        };
+        buffer = (buffer << 6) | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            let [.., byte] = (buffer >> bits).to_be_bytes();
+            bytes.push(byte);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,156 @@
+//! # Watch mode
+//!
+//! `CryptolClient::watch` supports an edit-evaluate loop on a
+//! specification: it polls a set of `.cry` files and, whenever one of
+//! them changes, reloads the modules of the session from a fresh state
+//! of the server and hands the client to a callback, e.g. to run the
+//! checks of a test harness again.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use cryptol_client::watch::Control;
+//! use cryptol_client::CryptolClient;
+//!
+//! let mut cryptol_client = CryptolClient::connect().unwrap();
+//! cryptol_client.load_module("MySpec").unwrap();
+//! cryptol_client
+//!     .watch(&["specs/MySpec.cry"], Duration::from_millis(500), |client, event| {
+//!         match event.error {
+//!             Some(e) => eprintln!("cannot reload: {e}"),
+//!             None => println!("{:?}", client.evaluate("MySpec::check").map(|a| a.value)),
+//!         }
+//!         Control::Continue
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::{CryptolClient, Result};
+
+/// What happened when the watched files changed, handed to the
+/// callback of [`CryptolClient::watch`].
+#[derive(Debug, Clone, Copy)]
+pub struct WatchEvent<'a> {
+    /// The files that changed since the last event, in the order they
+    /// were given.
+    pub changed: &'a [PathBuf],
+    /// Why the modules could not be reloaded, or `None` if they were.
+    /// If they could not, the client is left in the state it was in
+    /// before the change.
+    pub error: Option<&'a (dyn std::error::Error + 'static)>,
+}
+
+/// What a watch callback asks of the watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// Keep watching the files.
+    Continue,
+    /// Stop watching and return from `watch`.
+    Stop,
+}
+
+/// What identifies a version of a file: its modification time and
+/// length.
+type Version = Option<(SystemTime, u64)>;
+
+/// This function returns the version of the file at `path`, or `None`
+/// if it does not exist.
+fn version(path: &Path) -> io::Result<Version> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(Some((metadata.modified()?, metadata.len()))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl CryptolClient {
+    /// This function checks the given files every `interval` and,
+    /// whenever any of them has changed, reloads the modules loaded in
+    /// this session, in order, from a fresh state of the server, and
+    /// calls `callback` with the client. Changes that happen while the
+    /// callback runs are picked up afterwards. The function returns
+    /// when the callback returns [`Control::Stop`].
+    ///
+    /// A file counts as changed when its modification time or length
+    /// differs, or when it is removed or created. Since the server
+    /// loads modules by name, the files should be the sources of the
+    /// loaded modules and of the modules they import.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a file does not exist when
+    /// watching starts or its metadata cannot be read, or if the
+    /// initial modules of a lazy connection cannot be loaded. Failures
+    /// to reload the modules are passed to the callback instead.
+    pub fn watch<P: AsRef<Path>>(
+        &mut self,
+        files: &[P],
+        interval: Duration,
+        mut callback: impl FnMut(&mut CryptolClient, &WatchEvent<'_>) -> Control,
+    ) -> Result<()> {
+        self.load_pending_modules()?;
+        let files: Vec<PathBuf> = files.iter().map(|f| f.as_ref().to_path_buf()).collect();
+        let mut versions = Vec::with_capacity(files.len());
+        for file in &files {
+            match version(file)? {
+                Some(version) => versions.push(Some(version)),
+                None => return Err(format!("cannot watch {}: no such file", file.display()).into()),
+            }
+        }
+        tracing::info!(files = files.len(), "watching for changes");
+
+        loop {
+            thread::sleep(interval);
+            let mut changed = Vec::new();
+            for (file, known) in files.iter().zip(&mut versions) {
+                let current = version(file)?;
+                if current != *known {
+                    *known = current;
+                    changed.push(file.clone());
+                }
+            }
+            if changed.is_empty() {
+                continue;
+            }
+
+            tracing::info!(changed = ?changed, "files changed, reloading modules");
+            let result = self.reload_modules();
+            if let Err(e) = &result {
+                tracing::warn!(error = %e, "cannot reload modules");
+            }
+            let event = WatchEvent {
+                changed: &changed,
+                error: result.as_ref().err().map(AsRef::as_ref),
+            };
+            if callback(self, &event) == Control::Stop {
+                return Ok(());
+            }
+        }
+    }
+
+    /// This function loads the modules of the session again, in order,
+    /// from the initial state of the server. If a module cannot be
+    /// loaded, the client is returned to the state it was in before.
+    fn reload_modules(&mut self) -> Result<()> {
+        let state = self.session.state().map(str::to_string);
+        let loaded = self.loaded.take();
+        let modules = mem::take(&mut self.modules);
+        self.session.set_state(None);
+        for module in &modules {
+            if let Err(e) = self.load_module(module) {
+                self.session.set_state(state);
+                self.loaded = loaded;
+                self.modules = modules;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! `Zeroize` and wiping `Drop` implementations for the `zeroize`
+//! feature. See the crate documentation.
+
+use serde_json::{Map, Value};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::transcript::TranscriptEntry;
+use crate::value::{BitVector, CryptolValue, Integer};
+use crate::Args;
+
+impl Zeroize for BitVector {
+    fn zeroize(&mut self) {
+        self.zeroize_bytes();
+    }
+}
+
+impl Drop for BitVector {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for BitVector {}
+
+impl Zeroize for Integer {
+    fn zeroize(&mut self) {
+        self.zeroize_magnitude();
+    }
+}
+
+impl Drop for Integer {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Integer {}
+
+impl Zeroize for CryptolValue {
+    fn zeroize(&mut self) {
+        match self {
+            CryptolValue::Bit(bit) => bit.zeroize(),
+            CryptolValue::Integer(n) => n.zeroize(),
+            CryptolValue::IntegerModulo { value, .. } => value.zeroize(),
+            CryptolValue::BitVector(bits) => bits.zeroize(),
+            CryptolValue::Sequence(values) | CryptolValue::Tuple(values) => {
+                values.iter_mut().for_each(Zeroize::zeroize);
+            }
+            CryptolValue::Record(fields) => fields.values_mut().for_each(Zeroize::zeroize),
+            CryptolValue::Unit => {}
+            CryptolValue::Opaque(name) => name.zeroize(),
+        }
+    }
+}
+
+impl Drop for Args {
+    fn drop(&mut self) {
+        self.arguments_mut().iter_mut().for_each(json);
+    }
+}
+
+impl Drop for TranscriptEntry {
+    fn drop(&mut self) {
+        map(&mut self.params);
+    }
+}
+
+/// This function overwrites the strings and numbers of a JSON value,
+/// which hold the encodings of any Cryptol values in it.
+pub(crate) fn json(value: &mut Value) {
+    match value {
+        Value::String(s) => s.zeroize(),
+        Value::Number(n) => *n = 0.into(),
+        Value::Array(values) => values.iter_mut().for_each(json),
+        Value::Object(object) => map(object),
+        Value::Null | Value::Bool(_) => {}
+    }
+}
+
+/// This function overwrites the values of JSON request parameters.
+pub(crate) fn map(params: &mut Map<String, Value>) {
+    params.values_mut().for_each(json);
+}